@@ -29,7 +29,7 @@ pub fn shader_derive(input: TokenStream) -> TokenStream {
     let mut binding_idx: u32 = 1;
     for field in fields {
         let name = field.ident.as_ref().unwrap().to_string();
-        let is_uniform = field.attrs.iter().any(|attr| is_uniform_attr(attr));
+        let is_uniform = field.attrs.iter().any(is_uniform_attr);
         let ty = &field.ty;
         if !is_uniform {
             continue;
@@ -38,7 +38,7 @@ pub fn shader_derive(input: TokenStream) -> TokenStream {
             let sampler_binding = binding_idx;
             let texture_binding = binding_idx + 1;
             texture_fields.push(TexField {
-                name: name,
+                name,
                 sampler_binding,
                 texture_binding,
             });
@@ -152,7 +152,7 @@ pub fn shader_derive(input: TokenStream) -> TokenStream {
             if align > struct_align {
                 struct_align = align;
             }
-            let aligned_off = ((offset + align - 1) / align) * align;
+            let aligned_off = offset.div_ceil(align) * align;
 
             let field_ident = format_ident!("{}", uf.name);
             let write = match uf.wgsl_ty.as_str() {
@@ -198,7 +198,7 @@ pub fn shader_derive(input: TokenStream) -> TokenStream {
             offset = aligned_off + slot_size;
         }
 
-        let total_size = ((offset + struct_align - 1) / struct_align) * struct_align;
+        let total_size = offset.div_ceil(struct_align) * struct_align;
 
         quote! {
             let mut dst = vec![0u8; #total_size as usize];
@@ -291,16 +291,15 @@ fn is_texture_ref(ty: &syn::Type) -> bool {
                 return true;
             }
 
-            if let Some(seg) = path.segments.last() {
-                if seg.ident == "Option" {
-                    if let PathArguments::AngleBracketed(ref ab) = seg.arguments {
-                        for arg in &ab.args {
-                            if let GenericArgument::Type(inner_ty) = arg {
-                                if is_texture_ref(inner_ty) {
-                                    return true;
-                                }
-                            }
-                        }
+            if let Some(seg) = path.segments.last()
+                && seg.ident == "Option"
+                && let PathArguments::AngleBracketed(ref ab) = seg.arguments
+            {
+                for arg in &ab.args {
+                    if let GenericArgument::Type(inner_ty) = arg
+                        && is_texture_ref(inner_ty)
+                    {
+                        return true;
                     }
                 }
             }
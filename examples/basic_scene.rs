@@ -68,7 +68,7 @@ impl ApplicationHandler<App> for AppHandler {
             let window_attributes = Window::default_attributes().with_title("Basic Scene Example");
             let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
 
-            let app = block_on(App::new_benchmark(window, 1000_000));
+            let app = block_on(App::new_benchmark(window, 1_000_000));
             app.window.request_redraw();
             self.app = Some(app);
         }
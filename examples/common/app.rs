@@ -1,13 +1,13 @@
 use image::GenericImageView;
 use rand::Rng;
-use rayon::prelude::*;
 use std::sync::Arc;
 use winit::window::Window;
 use zen_rs_poc::{
     Resources,
     camera::{Camera, PerspectiveProjection},
     geometry::Geometry,
-    math::{Color4, Mat4, Vec3},
+    light::Light,
+    math::{Color3, Color4, Mat4, Vec3},
     primitive::Primitive,
     render::Renderer,
     symbol,
@@ -26,7 +26,7 @@ impl MainCamera {
     pub fn new(eye: Vec3, target: Vec3, up: Vec3, proj: PerspectiveProjection) -> Self {
         let mut camera = Camera::default();
         camera.set_view(Mat4::look_at_rh(eye, target, up));
-        camera.set_projection(proj.to_mat4());
+        camera.set_projection(proj);
         Self {
             eye,
             target,
@@ -42,12 +42,6 @@ impl MainCamera {
         self
     }
 
-    pub fn update_aspect(&mut self, aspect: f32) -> &mut Self {
-        self.proj.aspect = aspect;
-        self.inner.set_projection(self.proj.to_mat4());
-        self
-    }
-
     pub fn fovy(&self) -> f32 {
         self.proj.fovy_deg.to_radians()
     }
@@ -59,6 +53,7 @@ pub struct App {
     renderer: Renderer,
     screen_render_target: RenderTarget,
     primitives: Vec<Primitive>,
+    lights: Vec<Light>,
     pub camera: MainCamera,
 }
 
@@ -79,7 +74,9 @@ impl App {
 
         let surface = instance.create_surface(window.clone()).unwrap();
 
-        let renderer = Renderer::new(&instance, &surface).await;
+        let renderer = Renderer::new(&instance, &surface)
+            .await
+            .expect("Failed to create renderer");
 
         let mut resources = Resources::default();
 
@@ -107,6 +104,12 @@ impl App {
             renderer,
             screen_render_target,
             primitives: Vec::new(),
+            lights: vec![
+                Light::directional(Vec3::new(-0.4, -1.0, -0.6), Color3::new(1.0, 1.0, 1.0), 3.0)
+                    .with_shadows(),
+                Light::point(Vec3::new(0.0, 3.0, 3.0), Color3::new(1.0, 0.8, 0.6), 20.0)
+                    .with_range(10.0),
+            ],
             camera,
         }
     }
@@ -182,22 +185,25 @@ impl App {
     }
 
     pub fn set_window_resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        self.camera
-            .update_aspect(new_size.width as f32 / new_size.height as f32);
         self.screen_render_target
             .set_size(&mut self.resources, new_size.width, new_size.height);
     }
 
     pub fn render(&mut self) {
-        self.primitives
-            .par_sort_unstable_by_key(|item| (item.material().raw(), item.geometry().raw()));
-
-        self.renderer.render(
+        match self.renderer.render(
             &self.primitives,
+            &self.lights,
             &self.camera.inner,
             &self.screen_render_target,
-            &mut self.resources,
-        );
+            None,
+            &self.resources,
+        ) {
+            Ok(report) if !report.skipped.is_empty() => {
+                log::warn!("Skipped {} primitives", report.skipped.len());
+            }
+            Ok(_) => (),
+            Err(err) => log::error!("Render failed: {}", err),
+        }
 
         // if let Some(primitive) = self.primitives.first() {
         //     let material_handel = primitive.material();
@@ -24,8 +24,7 @@ impl FpsCounter {
         if self.frame_times.len() > 1 {
             let oldest = self.frame_times.front().unwrap();
             let elapsed = now.duration_since(*oldest).as_secs_f64();
-            let fps = (self.frame_times.len() - 1) as f64 / elapsed;
-            fps
+            (self.frame_times.len() - 1) as f64 / elapsed
         } else {
             0.0
         }
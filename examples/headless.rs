@@ -0,0 +1,95 @@
+use pollster::block_on;
+use zen_rs_poc::{
+    Resources,
+    camera::{Camera, PerspectiveProjection},
+    geometry::Geometry,
+    material::Material,
+    math::{Color4, EulerRot, Mat4, Quat, Vec3},
+    primitive::Primitive,
+    render::Renderer,
+    shader::builtins::unlit_shader,
+    symbol,
+    target::{LoadOp, RenderTargetBuilder},
+    texture::TextureKind,
+};
+
+const WIDTH: u32 = 512;
+const HEIGHT: u32 = 512;
+
+/// Renders a cube without a window and saves it as a PNG, to the path given as
+/// the first argument or to `headless.png` in the temporary directory.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args_os()
+        .nth(1)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("headless.png"));
+
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let mut renderer = block_on(Renderer::new_headless(&instance))?;
+
+    let mut resources = Resources::default();
+
+    let mut render_target = RenderTargetBuilder::new()
+        .name("Offscreen Render Target")
+        .size(WIDTH, HEIGHT)
+        .attach_color(
+            TextureKind::Render {
+                width: WIDTH,
+                height: HEIGHT,
+            },
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        )
+        .attach_depth24()
+        .build(&mut resources);
+    render_target.color_attachments_mut()[0].ops.load =
+        LoadOp::Clear(Color4::new(0.1, 0.2, 0.3, 1.0));
+
+    let geometry = Geometry::create_unit_cube(&mut resources);
+    let geometry_handle = resources.insert_geometry(geometry);
+
+    let mut material = Material::from_shader(unlit_shader());
+    material.set_param_vec4f(symbol!("albedo_factor"), [1.0, 0.5, 0.2, 1.0]);
+    let material_handle = resources.insert_material(material);
+
+    let mut primitive = Primitive::new(geometry_handle, material_handle);
+    primitive.set_transform(Mat4::from_quat(Quat::from_euler(
+        EulerRot::YXZ,
+        0.6,
+        0.4,
+        0.0,
+    )));
+
+    let mut camera = Camera::default();
+    camera.set_view(Mat4::look_at_rh(
+        Vec3::new(0.0, 0.0, 3.0),
+        Vec3::ZERO,
+        Vec3::Y,
+    ));
+    camera.set_projection(PerspectiveProjection::new(
+        45.0,
+        WIDTH as f32 / HEIGHT as f32,
+        0.1,
+        100.0,
+    ));
+
+    renderer.render(&[primitive], &[], &camera, &render_target, None, &resources)?;
+
+    let pixels = renderer.read_render_target(&render_target, 0, &resources)?;
+
+    image::save_buffer(
+        &path,
+        &pixels.bytes,
+        pixels.width,
+        pixels.height,
+        image::ColorType::Rgba8,
+    )?;
+
+    println!(
+        "Saved {}x{} image to {}",
+        pixels.width,
+        pixels.height,
+        path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use crate::TextureHandle;
+use crate::math::Color4;
+
+/// What a pass shows where no primitive was drawn, instead of its clear color.
+///
+/// Backgrounds are drawn after opaque primitives and before blended ones, at the far plane
+/// with the depth test on, so only uncovered pixels are shaded. They follow the rotation of
+/// the camera but not its position, as if infinitely far away. Without a depth attachment
+/// the background is drawn before every primitive instead.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    /// A `TextureKind::Cube` texture seen from its center.
+    Skybox(TextureHandle),
+    /// An equirectangular panorama in a `TextureKind::D2` texture: u runs once around the
+    /// horizon with +X at the center, v from straight up (0) to straight down (1).
+    Panorama(TextureHandle),
+    /// Colors blended from straight down (`bottom`) to straight up (`top`).
+    Gradient { top: Color4, bottom: Color4 },
+}
@@ -1,40 +1,53 @@
+mod frustum;
 mod projection;
+mod viewport;
 
+pub use frustum::*;
 pub use projection::*;
+pub use viewport::*;
 
-use crate::math::Mat4;
+use crate::math::{Mat4, ToMat4};
 
 /// Camera data for rendering.
 ///
-/// Stores world transform and projection matrix.
+/// Stores world transform and projection.
 /// Provides methods to set/get transform, projection, view, and view-projection matrices.
 ///
 /// - `transform`: camera position and orientation in world space.
-/// - `projection`: projection matrix (perspective or orthographic).
+/// - `projection`: perspective, orthographic or custom [`Projection`]; perspective and
+///   orthographic projections take the aspect ratio of the viewport they are drawn into.
+/// - `viewport`: region of the render target drawn into, or the whole target when `None`.
+/// - `scissor`: region outside of which nothing is drawn, or no clipping when `None`.
 ///
 /// Typical usage: set transform and projection, then query view/view-projection for rendering.
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Camera {
     transform: Mat4,
-    projection: Mat4,
+    projection: Projection,
+    viewport: Option<Viewport>,
+    scissor: Option<ScissorRect>,
 }
 
 impl Camera {
-    /// Creates a camera from transform and projection matrices.
+    /// Creates a camera from a transform matrix and a projection.
     #[inline]
-    pub fn new(transform: Mat4, projection: Mat4) -> Self {
+    pub fn new(transform: Mat4, projection: impl Into<Projection>) -> Self {
         Self {
             transform,
-            projection,
+            projection: projection.into(),
+            viewport: None,
+            scissor: None,
         }
     }
 
-    /// Creates a camera with identity transform and given projection matrix.
+    /// Creates a camera with identity transform and given projection.
     #[inline]
-    pub fn from_projection(projection: Mat4) -> Self {
+    pub fn from_projection(projection: impl Into<Projection>) -> Self {
         Self {
             transform: Mat4::IDENTITY,
-            projection,
+            projection: projection.into(),
+            viewport: None,
+            scissor: None,
         }
     }
 
@@ -51,17 +64,30 @@ impl Camera {
         self.transform
     }
 
-    /// Sets the projection matrix.
+    /// Sets the projection: a [`PerspectiveProjection`], an [`OrthographicProjection`]
+    /// or a custom matrix.
     #[inline]
-    pub fn set_projection(&mut self, projection: Mat4) -> &mut Self {
-        self.projection = projection;
+    pub fn set_projection(&mut self, projection: impl Into<Projection>) -> &mut Self {
+        self.projection = projection.into();
         self
     }
 
     /// Returns the projection matrix.
     #[inline]
     pub fn projection(&self) -> Mat4 {
-        self.projection
+        self.projection.to_mat4()
+    }
+
+    /// Returns the projection parameters.
+    #[inline]
+    pub fn projection_params(&self) -> &Projection {
+        &self.projection
+    }
+
+    /// Returns the projection parameters for modification.
+    #[inline]
+    pub fn projection_params_mut(&mut self) -> &mut Projection {
+        &mut self.projection
     }
 
     /// Sets the view matrix (camera-to-world inverse).
@@ -81,6 +107,62 @@ impl Camera {
     /// Returns the combined projection * view matrix.
     #[inline]
     pub fn view_projection(&self) -> Mat4 {
-        self.projection * self.view()
+        self.projection() * self.view()
+    }
+
+    /// Sets the region of the render target to draw into (`None` for the whole target).
+    ///
+    /// Perspective and orthographic projections follow the aspect ratio of the viewport;
+    /// custom projection matrices should be built with [`Camera::aspect_ratio`]
+    /// so the image is not stretched.
+    #[inline]
+    pub fn set_viewport(&mut self, viewport: Option<Viewport>) -> &mut Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Returns the viewport, if any.
+    #[inline]
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+
+    /// Sets the scissor rectangle (`None` to disable clipping).
+    #[inline]
+    pub fn set_scissor(&mut self, scissor: Option<ScissorRect>) -> &mut Self {
+        self.scissor = scissor;
+        self
+    }
+
+    /// Returns the scissor rectangle, if any.
+    #[inline]
+    pub fn scissor(&self) -> Option<ScissorRect> {
+        self.scissor
+    }
+
+    /// Returns the aspect ratio of the viewport, or of a `target_width` × `target_height`
+    /// target when no viewport is set.
+    #[inline]
+    pub fn aspect_ratio(&self, target_width: u32, target_height: u32) -> f32 {
+        self.viewport
+            .unwrap_or_else(|| Viewport::new(0.0, 0.0, target_width as f32, target_height as f32))
+            .aspect_ratio()
+    }
+
+    /// Returns a copy of the camera whose projection uses the aspect ratio of its
+    /// viewport (or of a `target_width` × `target_height` target), as drawn by the renderer.
+    #[inline]
+    pub fn fit_to_target(&self, target_width: u32, target_height: u32) -> Self {
+        let mut camera = *self;
+        camera
+            .projection
+            .set_aspect(self.aspect_ratio(target_width, target_height));
+        camera
+    }
+
+    /// Returns the view frustum in world space, built from the view-projection matrix.
+    #[inline]
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_projection(&self.view_projection())
     }
 }
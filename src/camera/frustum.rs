@@ -0,0 +1,116 @@
+use crate::geometry::{BoundingBox, BoundingSphere};
+use crate::math::{Mat4, Vec3, Vec4};
+
+/// View frustum as six inward-facing planes, in the space the matrix maps from.
+///
+/// Built from a view-projection matrix with wgpu's `[0, 1]` clip depth.
+/// Each plane is `(normal, distance)`: a point `p` is inside when `normal.dot(p) + distance >= 0`.
+/// Degenerate planes (e.g. the far plane of an infinite projection) are dropped.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+    count: usize,
+}
+
+impl Frustum {
+    /// Extracts the planes of `view_projection` (Gribb/Hartmann).
+    pub fn from_view_projection(view_projection: &Mat4) -> Self {
+        let m = view_projection;
+        let rows = [m.row(0), m.row(1), m.row(2), m.row(3)];
+
+        let candidates = [
+            rows[3] + rows[0], // left
+            rows[3] - rows[0], // right
+            rows[3] + rows[1], // bottom
+            rows[3] - rows[1], // top
+            rows[2],           // near
+            rows[3] - rows[2], // far
+        ];
+
+        let mut planes = [Vec4::ZERO; 6];
+        let mut count = 0;
+        for plane in candidates {
+            let length = plane.truncate().length();
+            if length > f32::EPSILON {
+                planes[count] = plane / length;
+                count += 1;
+            }
+        }
+
+        Self { planes, count }
+    }
+
+    #[inline]
+    pub fn planes(&self) -> &[Vec4] {
+        &self.planes[..self.count]
+    }
+
+    /// Whether `point` is inside or on the frustum.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes()
+            .iter()
+            .all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+    }
+
+    /// Whether any part of `sphere` may be inside the frustum.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes()
+            .iter()
+            .all(|plane| plane.truncate().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+
+    /// Whether any part of `aabb` may be inside the frustum.
+    ///
+    /// Conservative: boxes near a frustum corner can be reported as intersecting.
+    pub fn intersects_box(&self, aabb: &BoundingBox) -> bool {
+        self.planes().iter().all(|plane| {
+            let normal = plane.truncate();
+            // the box corner farthest along the plane normal
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frustum() -> Frustum {
+        let projection = Mat4::perspective_rh(90f32.to_radians(), 1.0, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        Frustum::from_view_projection(&(projection * view))
+    }
+
+    #[test]
+    fn test_frustum_points() {
+        let frustum = frustum();
+        assert_eq!(frustum.planes().len(), 6);
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.05)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -200.0)));
+        assert!(!frustum.contains_point(Vec3::new(20.0, 0.0, -10.0)));
+    }
+
+    #[test]
+    fn test_frustum_volumes() {
+        let frustum = frustum();
+
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(Vec3::new(0.0, 0.0, 1.0), 2.0)));
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(Vec3::new(0.0, 0.0, 5.0), 2.0)));
+
+        let aabb = BoundingBox::new(Vec3::new(10.5, -1.0, -11.0), Vec3::new(12.0, 1.0, -9.0));
+        assert!(frustum.intersects_box(&aabb));
+        let aabb = BoundingBox::new(Vec3::new(12.0, -1.0, -11.0), Vec3::new(14.0, 1.0, -9.0));
+        assert!(!frustum.intersects_box(&aabb));
+    }
+
+    #[test]
+    fn test_infinite_far_plane_is_dropped() {
+        let projection = Mat4::perspective_infinite_rh(90f32.to_radians(), 1.0, 0.1);
+        let frustum = Frustum::from_view_projection(&projection);
+        assert_eq!(frustum.planes().len(), 5);
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -1.0e6)));
+    }
+}
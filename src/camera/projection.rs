@@ -1,9 +1,13 @@
-use crate::math::Mat4;
+use crate::math::{Mat4, ToMat4};
 
 /// Perspective projection parameters for 3D rendering.
 ///
 /// Stores vertical field of view (in degrees), aspect ratio, near and far plane distances.
 /// Use [`to_mat4`] to generate a perspective projection matrix (right-handed).
+///
+/// - A `far` of `f32::INFINITY` produces an infinite projection with no far plane.
+/// - `reverse_z` maps the near plane to depth 1.0 and the far plane to 0.0,
+///   for targets with reverse-Z enabled.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct PerspectiveProjection {
     /// Vertical field of view in degrees.
@@ -12,8 +16,10 @@ pub struct PerspectiveProjection {
     pub aspect: f32,
     /// Near plane distance.
     pub near: f32,
-    /// Far plane distance.
+    /// Far plane distance, or `f32::INFINITY`.
     pub far: f32,
+    /// Whether depth is reversed (1.0 near, 0.0 far).
+    pub reverse_z: bool,
 }
 
 impl PerspectiveProjection {
@@ -30,13 +36,47 @@ impl PerspectiveProjection {
             aspect,
             near,
             far,
+            reverse_z: false,
         }
     }
 
+    /// Creates a perspective projection without a far plane.
+    #[inline]
+    pub fn infinite(fovy_deg: f32, aspect: f32, near: f32) -> Self {
+        Self::new(fovy_deg, aspect, near, f32::INFINITY)
+    }
+
+    /// Sets whether depth is reversed (1.0 near, 0.0 far).
+    #[inline]
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    /// Whether the projection has no far plane.
+    #[inline]
+    pub fn is_infinite(&self) -> bool {
+        self.far.is_infinite()
+    }
+
     /// Converts parameters to a perspective projection matrix (right-handed).
     #[inline]
     pub fn to_mat4(&self) -> Mat4 {
-        Mat4::perspective_rh(self.fovy_deg.to_radians(), self.aspect, self.near, self.far)
+        let fovy = self.fovy_deg.to_radians();
+        match (self.is_infinite(), self.reverse_z) {
+            (false, false) => Mat4::perspective_rh(fovy, self.aspect, self.near, self.far),
+            // swapping the planes maps near to 1.0 and far to 0.0
+            (false, true) => Mat4::perspective_rh(fovy, self.aspect, self.far, self.near),
+            (true, false) => Mat4::perspective_infinite_rh(fovy, self.aspect, self.near),
+            (true, true) => Mat4::perspective_infinite_reverse_rh(fovy, self.aspect, self.near),
+        }
+    }
+}
+
+impl ToMat4 for PerspectiveProjection {
+    #[inline]
+    fn to_mat4(&self) -> Mat4 {
+        PerspectiveProjection::to_mat4(self)
     }
 }
 
@@ -46,12 +86,14 @@ impl Default for PerspectiveProjection {
     /// - aspect: 1.0
     /// - near: 0.1
     /// - far: 1000.0
+    /// - reverse_z: false
     fn default() -> Self {
         Self {
             fovy_deg: 60.0,
             aspect: 1.0,
             near: 0.1,
             far: 1000.0,
+            reverse_z: false,
         }
     }
 }
@@ -60,6 +102,7 @@ impl Default for PerspectiveProjection {
 ///
 /// Stores left/right/bottom/top bounds and near/far plane distances.
 /// Use [`to_mat4`] to generate an orthographic projection matrix (right-handed).
+/// `reverse_z` maps the near plane to depth 1.0 and the far plane to 0.0.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct OrthographicProjection {
     /// Left bound of the view volume.
@@ -74,6 +117,8 @@ pub struct OrthographicProjection {
     pub near: f32,
     /// Far plane distance.
     pub far: f32,
+    /// Whether depth is reversed (1.0 near, 0.0 far).
+    pub reverse_z: bool,
 }
 
 impl OrthographicProjection {
@@ -90,6 +135,7 @@ impl OrthographicProjection {
             top,
             near,
             far,
+            reverse_z: false,
         }
     }
 
@@ -108,20 +154,55 @@ impl OrthographicProjection {
             top: hh,
             near,
             far,
+            reverse_z: false,
+        }
+    }
+
+    /// Sets whether depth is reversed (1.0 near, 0.0 far).
+    #[inline]
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    /// Width / height of the view volume.
+    #[inline]
+    pub fn aspect(&self) -> f32 {
+        (self.right - self.left) / (self.top - self.bottom)
+    }
+
+    /// Widens or narrows the view volume to `aspect`, keeping its height.
+    ///
+    /// `left` and `right` are scaled about the view axis, like the sides of a
+    /// perspective frustum: centered volumes stay centered, and off-center ones
+    /// keep their offset relative to the width (e.g. `0.0..width` keeps `left` at 0).
+    pub fn set_aspect(&mut self, aspect: f32) -> &mut Self {
+        let width = self.right - self.left;
+        if width == 0.0 {
+            return self;
         }
+        let scale = (self.top - self.bottom) * aspect / width;
+        self.left *= scale;
+        self.right *= scale;
+        self
     }
 
     /// Converts parameters to an orthographic projection matrix (right-handed).
     #[inline]
     pub fn to_mat4(&self) -> Mat4 {
-        Mat4::orthographic_rh(
-            self.left,
-            self.right,
-            self.bottom,
-            self.top,
-            self.near,
-            self.far,
-        )
+        let (near, far) = if self.reverse_z {
+            (self.far, self.near)
+        } else {
+            (self.near, self.far)
+        };
+        Mat4::orthographic_rh(self.left, self.right, self.bottom, self.top, near, far)
+    }
+}
+
+impl ToMat4 for OrthographicProjection {
+    #[inline]
+    fn to_mat4(&self) -> Mat4 {
+        OrthographicProjection::to_mat4(self)
     }
 }
 
@@ -130,6 +211,7 @@ impl Default for OrthographicProjection {
     /// - left: -1.0, right: 1.0
     /// - bottom: -1.0, top: 1.0
     /// - near: 0.0, far: 1.0
+    /// - reverse_z: false
     fn default() -> Self {
         Self {
             left: -1.0,
@@ -138,6 +220,156 @@ impl Default for OrthographicProjection {
             top: 1.0,
             near: 0.0,
             far: 1.0,
+            reverse_z: false,
+        }
+    }
+}
+
+/// Projection of a [`Camera`](super::Camera).
+///
+/// Perspective and orthographic projections follow the aspect ratio of the
+/// viewport (or render target) they are drawn into; the renderer updates it every
+/// pass, so resizing needs no projection changes. `Custom` matrices are used as-is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    Perspective(PerspectiveProjection),
+    Orthographic(OrthographicProjection),
+    Custom(Mat4),
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Custom(Mat4::IDENTITY)
+    }
+}
+
+impl Projection {
+    /// Updates the aspect ratio (width / height); orthographic volumes keep their height,
+    /// see [`OrthographicProjection::set_aspect`].
+    /// Custom projections are left unchanged.
+    pub fn set_aspect(&mut self, aspect: f32) -> &mut Self {
+        match self {
+            Projection::Perspective(perspective) => perspective.aspect = aspect,
+            Projection::Orthographic(orthographic) => {
+                orthographic.set_aspect(aspect);
+            }
+            Projection::Custom(_) => {}
+        }
+        self
+    }
+}
+
+impl ToMat4 for Projection {
+    fn to_mat4(&self) -> Mat4 {
+        match self {
+            Projection::Perspective(perspective) => perspective.to_mat4(),
+            Projection::Orthographic(orthographic) => orthographic.to_mat4(),
+            Projection::Custom(matrix) => *matrix,
         }
     }
 }
+
+impl From<PerspectiveProjection> for Projection {
+    fn from(perspective: PerspectiveProjection) -> Self {
+        Self::Perspective(perspective)
+    }
+}
+
+impl From<OrthographicProjection> for Projection {
+    fn from(orthographic: OrthographicProjection) -> Self {
+        Self::Orthographic(orthographic)
+    }
+}
+
+impl From<Mat4> for Projection {
+    fn from(matrix: Mat4) -> Self {
+        Self::Custom(matrix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::math::Vec4;
+
+    fn depth(projection: &PerspectiveProjection, distance: f32) -> f32 {
+        let clip = projection.to_mat4() * Vec4::new(0.0, 0.0, -distance, 1.0);
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn test_perspective_depth_ranges() {
+        let finite = PerspectiveProjection::new(60.0, 1.0, 0.1, 100.0);
+        assert!(depth(&finite, 0.1).abs() < 1e-5);
+        assert!((depth(&finite, 100.0) - 1.0).abs() < 1e-5);
+
+        let reversed = finite.with_reverse_z(true);
+        assert!((depth(&reversed, 0.1) - 1.0).abs() < 1e-5);
+        assert!(depth(&reversed, 100.0).abs() < 1e-5);
+
+        let infinite = PerspectiveProjection::infinite(60.0, 1.0, 0.1);
+        assert!(depth(&infinite, 0.1).abs() < 1e-5);
+        assert!(depth(&infinite, 1.0e6) < 1.0);
+
+        let infinite_reversed = infinite.with_reverse_z(true);
+        assert!((depth(&infinite_reversed, 0.1) - 1.0).abs() < 1e-5);
+        assert!(depth(&infinite_reversed, 1.0e6) > 0.0);
+    }
+
+    #[test]
+    fn test_projection_aspect() {
+        let mut projection = Projection::from(PerspectiveProjection::default());
+        projection.set_aspect(2.0);
+        assert_eq!(
+            projection,
+            PerspectiveProjection::new(60.0, 2.0, 0.1, 1000.0).into()
+        );
+
+        let mut projection = Projection::from(OrthographicProjection::from_width_height(
+            2.0, 2.0, 0.0, 1.0,
+        ));
+        projection.set_aspect(2.0);
+        let Projection::Orthographic(orthographic) = projection else {
+            unreachable!()
+        };
+        assert_eq!((orthographic.left, orthographic.right), (-2.0, 2.0));
+        assert_eq!((orthographic.bottom, orthographic.top), (-1.0, 1.0));
+
+        let mut projection = Projection::Custom(Mat4::IDENTITY);
+        projection.set_aspect(2.0);
+        assert_eq!(projection, Projection::Custom(Mat4::IDENTITY));
+    }
+
+    #[test]
+    fn test_off_center_orthographic_volumes_survive_fitting() {
+        let orthographic = |camera: &Camera| {
+            let Projection::Orthographic(orthographic) = camera.projection_params() else {
+                unreachable!()
+            };
+            *orthographic
+        };
+
+        // a pixel-space volume keeps its origin at the left edge
+        let mut camera = Camera::default();
+        camera.set_projection(OrthographicProjection::new(
+            0.0, 800.0, 0.0, 600.0, 0.0, 1.0,
+        ));
+        assert_eq!(
+            orthographic(&camera.fit_to_target(800, 600)),
+            orthographic(&camera)
+        );
+        let fitted = orthographic(&camera.fit_to_target(1000, 600));
+        assert_eq!((fitted.left, fitted.right), (0.0, 1000.0));
+        assert_eq!((fitted.bottom, fitted.top), (0.0, 600.0));
+
+        // an off-center volume keeps its offset relative to its width
+        camera.set_projection(OrthographicProjection::new(-1.0, 3.0, -1.0, 1.0, 0.0, 1.0));
+        assert_eq!(
+            orthographic(&camera.fit_to_target(200, 100)),
+            orthographic(&camera)
+        );
+        let fitted = orthographic(&camera.fit_to_target(400, 100));
+        assert_eq!((fitted.left, fitted.right), (-2.0, 6.0));
+    }
+}
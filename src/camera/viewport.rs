@@ -0,0 +1,99 @@
+/// Region of the render target a camera draws into, in pixels from the top-left corner.
+///
+/// The region may extend past the target; whatever falls outside is clipped.
+/// `min_depth`/`max_depth` remap the depth range and must lie in `[0, 1]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+impl Viewport {
+    /// Creates a viewport with the full `[0, 1]` depth range.
+    #[inline]
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+
+    /// Creates a viewport from fractions of a `target_width` × `target_height` target,
+    /// e.g. `(0.5, 0.0, 0.5, 0.5)` for the top-right quarter.
+    #[inline]
+    pub fn from_fractions(
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        target_width: u32,
+        target_height: u32,
+    ) -> Self {
+        let (target_width, target_height) = (target_width as f32, target_height as f32);
+        Self::new(
+            x * target_width,
+            y * target_height,
+            width * target_width,
+            height * target_height,
+        )
+    }
+
+    /// Width / height, the aspect ratio a projection drawn into this viewport should use.
+    /// Returns 1.0 for an empty viewport.
+    #[inline]
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.width > 0.0 && self.height > 0.0 {
+            self.width / self.height
+        } else {
+            1.0
+        }
+    }
+
+    /// Whether the viewport covers no pixels.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+}
+
+/// Rectangle outside of which fragments are discarded, in pixels from the top-left corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ScissorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScissorRect {
+    #[inline]
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The part of the rectangle inside a `width` × `height` target.
+    pub fn clamped(&self, width: u32, height: u32) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Self::new(x, y, self.width.min(width - x), self.height.min(height - y))
+    }
+
+    /// Whether the rectangle covers no pixels.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
@@ -1,9 +1,14 @@
+mod bounds;
 mod factory;
 
+pub use bounds::*;
+
 use crate::{
-    BufferHandle, DirtyVersion, GeometryHandle, Resource, Resources, Symbol, buffer::BufferSlice,
+    BufferHandle, DirtyVersion, GeometryHandle, Resource, ResourceKey, Resources, Symbol,
+    buffer::BufferSlice, math::Vec3, symbol,
 };
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct VertexBuffer {
@@ -32,11 +37,26 @@ impl IndexBuffer {
     }
 }
 
+/// Versions bounds were computed at: the geometry's, and the position buffer's key and version.
+type BoundsKey = (u64, ResourceKey, u64);
+
+/// Bounds computed from the position buffer, with the versions they are valid for.
+#[derive(Debug, Default)]
+struct BoundsCache(Mutex<Option<(BoundsKey, Bounds)>>);
+
+impl Clone for BoundsCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(*self.0.lock().unwrap()))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Geometry {
     attributes: HashMap<Symbol, VertexAttribute>,
     indices: Option<IndexBuffer>,
     version: DirtyVersion,
+    bounds: BoundsCache,
+    bounds_override: Option<Bounds>,
 }
 
 impl Resource for Geometry {}
@@ -47,24 +67,32 @@ impl Geometry {
     }
 }
 
+impl Default for Geometry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Geometry {
     pub fn new() -> Self {
         Geometry {
             attributes: HashMap::new(),
             indices: None,
             version: DirtyVersion::new(),
+            bounds: BoundsCache::default(),
+            bounds_override: None,
         }
     }
 
     pub fn with_attribute(mut self, key: Symbol, attr: VertexAttribute) -> Self {
         self.attributes.insert(key, attr);
-        self.version.bump();
+        self.mark_changed();
         self
     }
 
     pub fn with_indices(mut self, indices: IndexBuffer) -> Self {
         self.indices = Some(indices);
-        self.version.bump();
+        self.mark_changed();
         self
     }
 }
@@ -72,13 +100,13 @@ impl Geometry {
 impl Geometry {
     pub fn set_attribute(&mut self, key: Symbol, attr: VertexAttribute) -> &mut Self {
         self.attributes.insert(key, attr);
-        self.version.bump();
+        self.mark_changed();
         self
     }
 
     pub fn remove_attribute(&mut self, key: Symbol) -> &mut Self {
         self.attributes.remove(&key);
-        self.version.bump();
+        self.mark_changed();
         self
     }
 
@@ -89,13 +117,13 @@ impl Geometry {
 
     pub fn set_indices(&mut self, idx: IndexBuffer) -> &mut Self {
         self.indices = Some(idx);
-        self.version.bump();
+        self.mark_changed();
         self
     }
 
     pub fn remove_indices(&mut self) -> &mut Self {
         self.indices = None;
-        self.version.bump();
+        self.mark_changed();
         self
     }
 
@@ -105,6 +133,89 @@ impl Geometry {
     }
 }
 
+impl Geometry {
+    /// Local-space bounds computed from the `positions` attribute.
+    ///
+    /// Computed on first use and cached until the geometry's attributes or indices change,
+    /// or the position buffer is replaced or marked dirty with `Buffer::mark_dirty`.
+    /// Returns `None` if there is no `positions` attribute, its buffer is missing, or its
+    /// format is not `Float32x2/3/4`; `None` is not cached.
+    pub fn bounds(&self, resources: &Resources) -> Option<Bounds> {
+        if let Some(bounds) = self.bounds_override {
+            return Some(bounds);
+        }
+
+        let handle = &self
+            .get_attribute(symbol!("positions"))?
+            .vertex_buffer
+            .buffer_slice
+            .buffer;
+        let buffer = resources.get_buffer(handle)?;
+        let key = (self.ver(), handle.raw(), buffer.ver().as_u64());
+
+        let mut cache = self.bounds.0.lock().unwrap();
+        if let Some((cached_key, bounds)) = *cache
+            && cached_key == key
+        {
+            return Some(bounds);
+        }
+        let bounds = self.compute_bounds(resources)?;
+        *cache = Some((key, bounds));
+        Some(bounds)
+    }
+
+    /// Overrides the computed bounds until the attributes or indices change,
+    /// e.g. for geometry animated on the GPU.
+    pub fn set_bounds(&mut self, bounds: Bounds) -> &mut Self {
+        self.bounds_override = Some(bounds);
+        self
+    }
+
+    fn compute_bounds(&self, resources: &Resources) -> Option<Bounds> {
+        let attribute = self.get_attribute(symbol!("positions"))?;
+        let components = match attribute.format {
+            wgpu::VertexFormat::Float32x2 => 2,
+            wgpu::VertexFormat::Float32x3 => 3,
+            wgpu::VertexFormat::Float32x4 => 4,
+            _ => return None,
+        };
+
+        let vertex_buffer = &attribute.vertex_buffer;
+        let buffer = resources.get_buffer(&vertex_buffer.buffer_slice.buffer)?;
+        let bytes = buffer.raw().get(vertex_buffer.buffer_slice.range())?;
+
+        let stride = vertex_buffer.stride as usize;
+        let offset = attribute.byte_offset as usize;
+        let size = components * 4;
+        if stride == 0 {
+            return None;
+        }
+
+        // the last vertex of an interleaved buffer may end before a full stride
+        let count = bytes
+            .len()
+            .checked_sub(offset + size)
+            .map_or(0, |rest| rest / stride + 1);
+
+        let positions = (0..count).filter_map(|i| {
+            let start = i * stride + offset;
+            let texel = bytes.get(start..start + size)?;
+            let mut v = [0.0f32; 3];
+            for (c, chunk) in texel.chunks_exact(4).take(3).enumerate() {
+                v[c] = f32::from_le_bytes(chunk.try_into().unwrap());
+            }
+            Some(Vec3::from_array(v))
+        });
+
+        Bounds::from_points(positions)
+    }
+
+    fn mark_changed(&mut self) {
+        self.version.bump();
+        self.bounds_override = None;
+    }
+}
+
 impl Geometry {
     pub(crate) fn buffers(&self) -> impl Iterator<Item = &BufferHandle> {
         self.attributes
@@ -122,3 +233,52 @@ impl Geometry {
         self.version.as_u64()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer;
+
+    fn triangle(resources: &mut Resources, x: f32) -> (Geometry, BufferHandle) {
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, x, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let buffer =
+            Buffer::for_vertex(bytemuck::cast_slice(&positions).to_vec()).into_handle(resources);
+        let geometry = Geometry::new().with_attribute(
+            symbol!("positions"),
+            VertexAttribute {
+                vertex_buffer: VertexBuffer {
+                    buffer_slice: BufferSlice::from_entire_buffer(resources, buffer.clone()),
+                    stride: 12,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                },
+                byte_offset: 0,
+                format: wgpu::VertexFormat::Float32x3,
+            },
+        );
+        (geometry, buffer)
+    }
+
+    #[test]
+    fn test_bounds_of_a_late_buffer() {
+        let mut resources = Resources::default();
+        let (geometry, _) = triangle(&mut resources, 2.0);
+
+        // asked before the position buffer is in the resources
+        assert_eq!(geometry.bounds(&Resources::default()), None);
+
+        let bounds = geometry.bounds(&resources).unwrap();
+        assert_eq!(bounds.aabb.max, Vec3::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_bounds_follow_buffer_edits() {
+        let mut resources = Resources::default();
+        let (geometry, buffer) = triangle(&mut resources, 2.0);
+        assert_eq!(geometry.bounds(&resources).unwrap().aabb.max.x, 2.0);
+
+        let buffer = resources.get_buffer_mut(&buffer).unwrap();
+        buffer.raw_mut()[12..16].copy_from_slice(&5.0f32.to_le_bytes());
+        buffer.mark_dirty();
+        assert_eq!(geometry.bounds(&resources).unwrap().aabb.max.x, 5.0);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::math::{Mat4, Vec3};
+
+/// Axis-aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    #[inline]
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box containing every point, or `None` for an empty iterator.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| {
+            Self::new(aabb.min.min(point), aabb.max.max(point))
+        }))
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[inline]
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Box containing this box after `transform` is applied.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        let center = transform.transform_point3(self.center());
+        let half_extents = self.half_extents();
+        // each world axis extent is the sum of the absolute contributions of the local axes
+        let extents = transform.x_axis.truncate().abs() * half_extents.x
+            + transform.y_axis.truncate().abs() * half_extents.y
+            + transform.z_axis.truncate().abs() * half_extents.z;
+        Self::new(center - extents, center + extents)
+    }
+}
+
+/// Bounding sphere.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    #[inline]
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Sphere containing this sphere after `transform` is applied.
+    /// Non-uniform scale is covered by using the largest axis scale.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        let scale = transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(transform.y_axis.truncate().length())
+            .max(transform.z_axis.truncate().length());
+        Self::new(transform.transform_point3(self.center), self.radius * scale)
+    }
+}
+
+/// Bounding volumes of a geometry in its local space.
+///
+/// - `aabb`: tight box around every position.
+/// - `sphere`: centered on the box, with the radius reaching the farthest position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: BoundingBox,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    /// Bounds of a point set, or `None` when it is empty.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3> + Clone) -> Option<Self> {
+        let aabb = BoundingBox::from_points(points.clone())?;
+        let center = aabb.center();
+        let radius = points
+            .into_iter()
+            .map(|point| point.distance_squared(center))
+            .fold(0.0f32, f32::max)
+            .sqrt();
+        Some(Self {
+            aabb,
+            sphere: BoundingSphere::new(center, radius),
+        })
+    }
+}
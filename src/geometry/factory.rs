@@ -23,7 +23,7 @@ fn create_vertex_attribute_from_f32v(
     VertexAttribute {
         vertex_buffer: VertexBuffer {
             buffer_slice,
-            stride: format.size() as u64,
+            stride: format.size(),
             step_mode: VertexStepMode::Vertex,
         },
         byte_offset: 0,
@@ -63,12 +63,26 @@ impl Geometry {
             VertexFormat::Float32x3,
         );
 
+        let normals_attr = create_vertex_attribute_from_f32v(
+            resources,
+            [0.0, 0.0, 1.0].repeat(4),
+            VertexFormat::Float32x3,
+        );
+
+        let tangents_attr = create_vertex_attribute_from_f32v(
+            resources,
+            [1.0, 0.0, 0.0, 1.0].repeat(4),
+            VertexFormat::Float32x4,
+        );
+
         let index_buffer = create_index_buffer_from_u32v(vec![0, 1, 2, 2, 3, 0], resources);
 
         Self::new()
             .with_attribute(symbol!("positions"), positions_attr)
             .with_attribute(symbol!("tex_coords"), tex_coords_attr)
             .with_attribute(symbol!("colors"), color_attr)
+            .with_attribute(symbol!("normals"), normals_attr)
+            .with_attribute(symbol!("tangents"), tangents_attr)
             .with_indices(index_buffer)
     }
 
@@ -103,6 +117,24 @@ impl Geometry {
             colors.extend_from_slice(&[1.0, 1.0, 1.0]);
         }
 
+        // per face: the outward normal and the tangent along increasing u
+        let face_frames = [
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),   // Front (+Z)
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0]), // Back (-Z)
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),  // Left (-X)
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),  // Right (+X)
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),   // Top (+Y)
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0]),  // Bottom (-Y)
+        ];
+        let mut normals = Vec::with_capacity(6 * 4 * 3);
+        let mut tangents = Vec::with_capacity(6 * 4 * 4);
+        for ([nx, ny, nz], [tx, ty, tz]) in face_frames {
+            for _ in 0..4 {
+                normals.extend_from_slice(&[nx, ny, nz]);
+                tangents.extend_from_slice(&[tx, ty, tz, 1.0]);
+            }
+        }
+
         let mut indices = Vec::with_capacity(6 * 6);
         for face in 0..6 {
             let base = face * 4;
@@ -115,12 +147,18 @@ impl Geometry {
             create_vertex_attribute_from_f32v(resources, tex_coords, VertexFormat::Float32x2);
         let colors_attr =
             create_vertex_attribute_from_f32v(resources, colors, VertexFormat::Float32x3);
+        let normals_attr =
+            create_vertex_attribute_from_f32v(resources, normals, VertexFormat::Float32x3);
+        let tangents_attr =
+            create_vertex_attribute_from_f32v(resources, tangents, VertexFormat::Float32x4);
         let indices = create_index_buffer_from_u32v(indices, resources);
 
         Self::new()
             .with_attribute(symbol!("positions"), positions_attr)
             .with_attribute(symbol!("tex_coords"), tex_coords_attr)
             .with_attribute(symbol!("colors"), colors_attr)
+            .with_attribute(symbol!("normals"), normals_attr)
+            .with_attribute(symbol!("tangents"), tangents_attr)
             .with_indices(indices)
     }
 }
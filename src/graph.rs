@@ -0,0 +1,580 @@
+use crate::{
+    TextureHandle,
+    background::Background,
+    camera::{Camera, ScissorRect, Viewport},
+    light::{Environment, Light},
+    material::BlendMode,
+    math::Color4,
+    primitive::Primitive,
+    target::{LoadOp, Operations, RenderTarget, StoreOp},
+};
+
+/// Index of a transient texture inside its [`RenderGraph`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TransientId(pub(crate) usize);
+
+/// A texture referenced by a graph pass.
+///
+/// - `Imported`: a texture in `Resources` (render textures, surfaces); its contents outlive the graph.
+/// - `Transient`: a texture owned by the graph, allocated by the renderer for the passes that use it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GraphTexture {
+    Imported(TextureHandle),
+    Transient(TransientId),
+}
+
+impl From<TextureHandle> for GraphTexture {
+    fn from(handle: TextureHandle) -> Self {
+        Self::Imported(handle)
+    }
+}
+
+impl From<&TextureHandle> for GraphTexture {
+    fn from(handle: &TextureHandle) -> Self {
+        Self::Imported(handle.clone())
+    }
+}
+
+impl From<TransientId> for GraphTexture {
+    fn from(id: TransientId) -> Self {
+        Self::Transient(id)
+    }
+}
+
+/// Description of a transient texture.
+///
+/// Transients with the same size and format whose lifetimes do not overlap
+/// share one GPU texture, so their contents never survive past the last pass using them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TransientTexture {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+}
+
+impl TransientTexture {
+    #[inline]
+    pub fn new(
+        name: impl Into<String>,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            width,
+            height,
+            format,
+        }
+    }
+}
+
+/// A color attachment of a graph pass, see `RenderTargetColorAttachment`
+/// for `blend` and `write_mask`.
+#[derive(Clone, Debug)]
+pub struct GraphColorAttachment {
+    pub texture: GraphTexture,
+    pub ops: Operations<Color4>,
+    pub blend: Option<BlendMode>,
+    pub write_mask: wgpu::ColorWrites,
+}
+
+impl GraphColorAttachment {
+    #[inline]
+    pub fn new(texture: impl Into<GraphTexture>, ops: Operations<Color4>) -> Self {
+        Self {
+            texture: texture.into(),
+            ops,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+
+    /// Overrides the material's blending for this attachment.
+    #[inline]
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = Some(blend);
+        self
+    }
+
+    #[inline]
+    pub fn with_write_mask(mut self, write_mask: wgpu::ColorWrites) -> Self {
+        self.write_mask = write_mask;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GraphDepthStencilAttachment {
+    pub texture: GraphTexture,
+    pub depth_ops: Operations<f32>,
+    pub stencil_ops: Operations<u32>,
+}
+
+/// Textures of a G-buffer, written by a [`PassShading::GBuffer`] pass and read by a
+/// [`PassShading::Lighting`] pass.
+///
+/// | texture    | format             | contents                                         |
+/// |------------|--------------------|--------------------------------------------------|
+/// | `albedo`   | `Rgba8UnormSrgb`   | base color                                       |
+/// | `normal`   | `Rgba16Float`      | world-space normal; `w` is 1 where geometry was drawn |
+/// | `material` | `Rgba8Unorm`       | roughness, metallic, occlusion, lit (0 for unlit) |
+/// | `emissive` | `Rgba16Float`      | emitted radiance                                 |
+/// | `depth`    | `Depth32Float`     | depth, to reconstruct positions                  |
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GBuffer {
+    pub albedo: GraphTexture,
+    pub normal: GraphTexture,
+    pub material: GraphTexture,
+    pub emissive: GraphTexture,
+    pub depth: GraphTexture,
+}
+
+impl GBuffer {
+    pub const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+    pub const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    pub const MATERIAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+    pub const EMISSIVE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// Declares the transient textures of a `width` × `height` G-buffer in `graph`.
+    pub fn create(graph: &mut RenderGraph, width: u32, height: u32) -> Self {
+        let mut create = |name: &str, format| {
+            graph
+                .create_transient(TransientTexture::new(name, width, height, format))
+                .into()
+        };
+        Self {
+            albedo: create("G-Buffer Albedo", Self::ALBEDO_FORMAT),
+            normal: create("G-Buffer Normal", Self::NORMAL_FORMAT),
+            material: create("G-Buffer Material", Self::MATERIAL_FORMAT),
+            emissive: create("G-Buffer Emissive", Self::EMISSIVE_FORMAT),
+            depth: create("G-Buffer Depth", Self::DEPTH_FORMAT),
+        }
+    }
+
+    /// The color textures in attachment order.
+    #[inline]
+    pub fn color_textures(&self) -> [&GraphTexture; 4] {
+        [&self.albedo, &self.normal, &self.material, &self.emissive]
+    }
+}
+
+/// How a pass shades what it draws.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PassShading {
+    /// Primitives are drawn with their shader's `fs_main`.
+    #[default]
+    Forward,
+    /// Opaque primitives are drawn into the attachments of a [`GBuffer`] with their
+    /// shader's G-buffer entry point, see `ShaderBuilder::gbuffer_entry_point`.
+    GBuffer,
+    /// A full-screen pass lighting a [`GBuffer`] into the first color attachment;
+    /// pixels where nothing was drawn keep the attachment's contents.
+    Lighting(GBuffer),
+}
+
+/// Which primitives of a pass are drawn, by the blend mode of their material.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DrawFilter {
+    #[default]
+    All,
+    /// Only `BlendMode::Opaque` materials, alpha-masked ones included.
+    Opaque,
+    /// Only materials with any other blend mode.
+    Blended,
+}
+
+impl DrawFilter {
+    /// Whether a material with `blend` passes the filter.
+    #[inline]
+    pub fn accepts(&self, blend: BlendMode) -> bool {
+        match self {
+            DrawFilter::All => true,
+            DrawFilter::Opaque => blend == BlendMode::Opaque,
+            DrawFilter::Blended => blend != BlendMode::Opaque,
+        }
+    }
+}
+
+/// One render pass of a [`RenderGraph`].
+///
+/// A pass writes its attachments and reads every attachment it loads, plus the
+/// textures declared with [`GraphPass::read`] (e.g. textures sampled by its materials).
+pub struct GraphPass<'a> {
+    name: String,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+    reverse_z: bool,
+    color_attachments: Vec<GraphColorAttachment>,
+    depth_stencil_attachment: Option<GraphDepthStencilAttachment>,
+    reads: Vec<GraphTexture>,
+    primitives: &'a [Primitive],
+    camera: Option<&'a Camera>,
+    viewport: Option<Viewport>,
+    scissor: Option<ScissorRect>,
+    shading: PassShading,
+    filter: DrawFilter,
+    background: Option<&'a Background>,
+}
+
+impl<'a> GraphPass<'a> {
+    /// Creates a pass with no attachments and nothing to draw.
+    pub fn new(name: impl Into<String>, width: u32, height: u32) -> Self {
+        Self {
+            name: name.into(),
+            width,
+            height,
+            sample_count: 1,
+            reverse_z: false,
+            color_attachments: Vec::new(),
+            depth_stencil_attachment: None,
+            reads: Vec::new(),
+            primitives: &[],
+            camera: None,
+            viewport: None,
+            scissor: None,
+            shading: PassShading::Forward,
+            filter: DrawFilter::All,
+            background: None,
+        }
+    }
+
+    /// Creates a pass drawing into the attachments of `target`, with the target's load and store ops.
+    pub fn from_target(target: &RenderTarget) -> Self {
+        let (width, height) = target.size();
+        let mut pass = Self::new(target.name(), width, height)
+            .with_sample_count(target.sample_count())
+            .with_reverse_z(target.reverse_z());
+        for color_attachment in target.color_attachments() {
+            pass = pass.color_attachment(GraphColorAttachment {
+                texture: (&color_attachment.texture).into(),
+                ops: color_attachment.ops,
+                blend: color_attachment.blend,
+                write_mask: color_attachment.write_mask,
+            });
+        }
+        if let Some(depth_stencil_attachment) = target.depth_stencil_attachment() {
+            pass = pass.depth_stencil(
+                &depth_stencil_attachment.texture,
+                depth_stencil_attachment.depth_ops,
+                depth_stencil_attachment.stencil_ops,
+            );
+        }
+        pass
+    }
+
+    /// Adds a color attachment, written by the fragment output at the next `@location(n)`.
+    pub fn color(self, texture: impl Into<GraphTexture>, ops: Operations<Color4>) -> Self {
+        self.color_attachment(GraphColorAttachment::new(texture, ops))
+    }
+
+    /// Adds a color attachment with its own blending or write mask.
+    pub fn color_attachment(mut self, attachment: GraphColorAttachment) -> Self {
+        self.color_attachments.push(attachment);
+        self
+    }
+
+    pub fn depth_stencil(
+        mut self,
+        texture: impl Into<GraphTexture>,
+        depth_ops: Operations<f32>,
+        stencil_ops: Operations<u32>,
+    ) -> Self {
+        self.depth_stencil_attachment = Some(GraphDepthStencilAttachment {
+            texture: texture.into(),
+            depth_ops,
+            stencil_ops,
+        });
+        self
+    }
+
+    /// Switches every attachment to `LoadOp::Load`, keeping what earlier passes drew,
+    /// e.g. for a second camera drawing into another viewport of the same target.
+    pub fn load_attachments(mut self) -> Self {
+        for color_attachment in &mut self.color_attachments {
+            color_attachment.ops.load = LoadOp::Load;
+        }
+        if let Some(depth_stencil_attachment) = &mut self.depth_stencil_attachment {
+            depth_stencil_attachment.depth_ops.load = LoadOp::Load;
+            depth_stencil_attachment.stencil_ops.load = LoadOp::Load;
+        }
+        self
+    }
+
+    /// Sets the MSAA sample count, see `RenderTarget::set_sample_count`.
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count.max(1);
+        self
+    }
+
+    /// Enables reverse-Z, see `RenderTarget::set_reverse_z`.
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    /// Overrides the camera's viewport for this pass.
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    /// Overrides the camera's scissor rectangle for this pass.
+    pub fn with_scissor(mut self, scissor: ScissorRect) -> Self {
+        self.scissor = Some(scissor);
+        self
+    }
+
+    /// Only draws the primitives accepted by `filter`.
+    pub fn with_filter(mut self, filter: DrawFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Fills the pixels left uncovered by the pass's primitives with `background`, drawn
+    /// into the first color attachment. Needs a camera, see [`GraphPass::draw`].
+    pub fn with_background(mut self, background: &'a Background) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Makes the pass a geometry pass writing `gbuffer`: its attachments are replaced by the
+    /// cleared G-buffer textures and only opaque primitives are drawn.
+    pub fn write_gbuffer(mut self, gbuffer: &GBuffer) -> Self {
+        self.color_attachments = gbuffer
+            .color_textures()
+            .into_iter()
+            .map(|texture| {
+                GraphColorAttachment::new(
+                    texture.clone(),
+                    Operations {
+                        load: LoadOp::Clear(Color4::new(0.0, 0.0, 0.0, 0.0)),
+                        store: StoreOp::Store,
+                    },
+                )
+            })
+            .collect();
+        self.depth_stencil_attachment = Some(GraphDepthStencilAttachment {
+            texture: gbuffer.depth.clone(),
+            depth_ops: Operations::default(),
+            stencil_ops: Operations::default(),
+        });
+        self.shading = PassShading::GBuffer;
+        self.filter = DrawFilter::Opaque;
+        self
+    }
+
+    /// Makes the pass a lighting pass: `gbuffer` seen from `camera` is lit into the first
+    /// color attachment. The depth attachment is removed; depth stays in the G-buffer.
+    pub fn light_gbuffer(mut self, gbuffer: &GBuffer, camera: &'a Camera) -> Self {
+        self.depth_stencil_attachment = None;
+        self.reads
+            .extend(gbuffer.color_textures().into_iter().cloned());
+        self.reads.push(gbuffer.depth.clone());
+        self.primitives = &[];
+        self.camera = Some(camera);
+        self.shading = PassShading::Lighting(gbuffer.clone());
+        self
+    }
+
+    /// Declares that the pass reads `texture` without attaching it, e.g. a transient
+    /// sampled by the materials of its primitives (see `Material::set_param_transient`).
+    pub fn read(mut self, texture: impl Into<GraphTexture>) -> Self {
+        self.reads.push(texture.into());
+        self
+    }
+
+    /// Sets the primitives drawn by the pass and the camera they are seen from.
+    pub fn draw(mut self, primitives: &'a [Primitive], camera: &'a Camera) -> Self {
+        self.primitives = primitives;
+        self.camera = Some(camera);
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    #[inline]
+    pub fn reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+
+    #[inline]
+    pub fn color_attachments(&self) -> &[GraphColorAttachment] {
+        &self.color_attachments
+    }
+
+    #[inline]
+    pub fn depth_stencil_attachment(&self) -> Option<&GraphDepthStencilAttachment> {
+        self.depth_stencil_attachment.as_ref()
+    }
+
+    #[inline]
+    pub fn reads(&self) -> &[GraphTexture] {
+        &self.reads
+    }
+
+    #[inline]
+    pub fn primitives(&self) -> &'a [Primitive] {
+        self.primitives
+    }
+
+    #[inline]
+    pub fn camera(&self) -> Option<&'a Camera> {
+        self.camera
+    }
+
+    #[inline]
+    pub fn shading(&self) -> &PassShading {
+        &self.shading
+    }
+
+    #[inline]
+    pub fn filter(&self) -> DrawFilter {
+        self.filter
+    }
+
+    #[inline]
+    pub fn background(&self) -> Option<&'a Background> {
+        self.background
+    }
+
+    /// The camera as drawn by the pass: with the pass viewport and a projection
+    /// fitted to its aspect ratio, see `Camera::fit_to_target`.
+    pub fn fitted_camera(&self) -> Option<Camera> {
+        let (width, height) = self.size();
+        self.camera.map(|camera| {
+            let mut camera = *camera;
+            camera.set_viewport(self.viewport());
+            camera.fit_to_target(width, height)
+        })
+    }
+
+    /// Viewport used for drawing: the pass override, else the camera's.
+    #[inline]
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+            .or_else(|| self.camera.and_then(|camera| camera.viewport()))
+    }
+
+    /// Scissor rectangle used for drawing: the pass override, else the camera's.
+    #[inline]
+    pub fn scissor(&self) -> Option<ScissorRect> {
+        self.scissor
+            .or_else(|| self.camera.and_then(|camera| camera.scissor()))
+    }
+}
+
+/// A frame as a set of render passes with texture dependencies.
+///
+/// Execution order follows the texture dependencies:
+/// - writes to a texture happen in the order their passes were added;
+/// - a read sees the latest write added before it, or, when there is none,
+///   the first write added after it (so a consumer may be added before its producer).
+///
+/// Passes whose output is never read and that write no imported texture are culled.
+/// Execute with `Renderer::execute`; every pass is recorded into a single command encoder.
+/// The lights of the frame are shared by every pass. Shadow maps of lights that cast
+/// shadows are rendered before the first pass, from the primitives set with
+/// [`RenderGraph::set_shadow_casters`]. Passes are lit by the environment set with
+/// [`RenderGraph::set_environment`], if any.
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    transients: Vec<TransientTexture>,
+    passes: Vec<GraphPass<'a>>,
+    lights: &'a [Light],
+    shadow_casters: Option<(&'a [Primitive], Camera)>,
+    environment: Option<Environment>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self {
+            transients: Vec::new(),
+            passes: Vec::new(),
+            lights: &[],
+            shadow_casters: None,
+            environment: None,
+        }
+    }
+
+    /// Sets the lights uploaded into the global light buffer for this frame.
+    pub fn set_lights(&mut self, lights: &'a [Light]) -> &mut Self {
+        self.lights = lights;
+        self
+    }
+
+    #[inline]
+    pub fn lights(&self) -> &'a [Light] {
+        self.lights
+    }
+
+    /// Sets the primitives that cast shadows, and the camera whose view the shadow cascades
+    /// are fitted to. Only opaque primitives cast shadows.
+    ///
+    /// The camera should be the one drawn by the passes, fitted to their target
+    /// (see `GraphPass::fitted_camera`), so the cascades match what is seen.
+    pub fn set_shadow_casters(&mut self, primitives: &'a [Primitive], camera: Camera) -> &mut Self {
+        self.shadow_casters = Some((primitives, camera));
+        self
+    }
+
+    #[inline]
+    pub fn shadow_casters(&self) -> Option<(&'a [Primitive], &Camera)> {
+        self.shadow_casters
+            .as_ref()
+            .map(|(primitives, camera)| (*primitives, camera))
+    }
+
+    /// Sets the environment lighting every pass, or `None` for no image-based lighting.
+    pub fn set_environment(&mut self, environment: Option<Environment>) -> &mut Self {
+        self.environment = environment;
+        self
+    }
+
+    #[inline]
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    /// Declares a transient texture, allocated by the renderer when the graph runs.
+    pub fn create_transient(&mut self, desc: TransientTexture) -> TransientId {
+        self.transients.push(desc);
+        TransientId(self.transients.len() - 1)
+    }
+
+    pub fn add_pass(&mut self, pass: GraphPass<'a>) -> &mut Self {
+        self.passes.push(pass);
+        self
+    }
+
+    #[inline]
+    pub fn transient(&self, id: TransientId) -> Option<&TransientTexture> {
+        self.transients.get(id.0)
+    }
+
+    #[inline]
+    pub fn transients(&self) -> &[TransientTexture] {
+        &self.transients
+    }
+
+    #[inline]
+    pub fn passes(&self) -> &[GraphPass<'a>] {
+        &self.passes
+    }
+}
@@ -1,9 +1,12 @@
 mod utils;
 pub use utils::*;
 
+pub mod background;
 pub mod buffer;
 pub mod camera;
 pub mod geometry;
+pub mod graph;
+pub mod light;
 pub mod material;
 pub mod math;
 pub mod primitive;
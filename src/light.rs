@@ -0,0 +1,144 @@
+use crate::TextureHandle;
+use crate::math::{Color3, Vec3};
+
+/// Shape of a [`Light`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightKind {
+    /// Parallel rays along `direction`, e.g. the sun. Position and range are ignored.
+    Directional,
+    /// Emits in every direction from `position`.
+    Point,
+    /// Emits from `position` in a cone around `direction`.
+    ///
+    /// Angles are measured from the cone axis in degrees: full intensity inside
+    /// `inner_cone_deg`, fading to nothing at `outer_cone_deg`.
+    Spot {
+        inner_cone_deg: f32,
+        outer_cone_deg: f32,
+    },
+}
+
+/// A light source, passed to `Renderer::render` (or `RenderGraph::set_lights`)
+/// and uploaded into the global light buffer every frame.
+///
+/// Shaders read the lights through the [`LIGHTS`](crate::shader::chunks::LIGHTS) WGSL chunk.
+///
+/// - `color` is scaled by `intensity`.
+/// - Point and spot lights fall off with the inverse square of the distance; with a `range`,
+///   the falloff is smoothly windowed to reach zero there.
+/// - With `cast_shadows`, the first such directional light gets cascaded shadow maps,
+///   point lights a cube of the point shadow cube array and spot lights a tile of the spot shadow atlas,
+///   within the budgets of `ShadowConfig`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color3,
+    pub intensity: f32,
+    /// Distance at which the light stops, or `None` for no limit.
+    pub range: Option<f32>,
+    /// World-space position of point and spot lights.
+    pub position: Vec3,
+    /// World-space direction the light travels, for directional and spot lights.
+    pub direction: Vec3,
+    /// Whether primitives block this light.
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    /// Creates a directional light shining along `direction`.
+    #[inline]
+    pub fn directional(direction: Vec3, color: Color3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional,
+            color,
+            intensity,
+            range: None,
+            position: Vec3::ZERO,
+            direction: direction.normalize_or(Vec3::NEG_Z),
+            cast_shadows: false,
+        }
+    }
+
+    /// Creates a point light at `position`.
+    #[inline]
+    pub fn point(position: Vec3, color: Color3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            color,
+            intensity,
+            range: None,
+            position,
+            direction: Vec3::NEG_Z,
+            cast_shadows: false,
+        }
+    }
+
+    /// Creates a spot light at `position` pointing along `direction`.
+    #[inline]
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        color: Color3,
+        intensity: f32,
+        inner_cone_deg: f32,
+        outer_cone_deg: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                inner_cone_deg,
+                outer_cone_deg,
+            },
+            color,
+            intensity,
+            range: None,
+            position,
+            direction: direction.normalize_or(Vec3::NEG_Z),
+            cast_shadows: false,
+        }
+    }
+
+    /// Limits the light to `range` world units.
+    #[inline]
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Makes primitives cast shadows from this light.
+    #[inline]
+    pub fn with_shadows(mut self) -> Self {
+        self.cast_shadows = true;
+        self
+    }
+}
+
+/// Image-based lighting from a cube map of the surroundings, see `Renderer::set_environment`.
+///
+/// The renderer precomputes a diffuse irradiance map and a specular map prefiltered per
+/// roughness from `texture` whenever it changes, and shaders read them through the
+/// [`ENVIRONMENT`](crate::shader::chunks::ENVIRONMENT) WGSL chunk.
+///
+/// - `texture` must be a `TextureKind::Cube` with a filterable format; HDR environments
+///   should use `Rgba16Float`.
+/// - The environment's radiance is scaled by `intensity`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+    pub texture: TextureHandle,
+    pub intensity: f32,
+}
+
+impl Environment {
+    #[inline]
+    pub fn new(texture: TextureHandle) -> Self {
+        Self {
+            texture,
+            intensity: 1.0,
+        }
+    }
+
+    #[inline]
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
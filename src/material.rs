@@ -9,9 +9,12 @@
 //! - All uniform bytes are zero-initialized; textures start as `None`.
 
 mod parameter;
+mod state;
 
 pub(crate) use parameter::*;
+pub use state::*;
 
+use crate::graph::{GraphTexture, TransientId};
 use crate::math::*;
 use crate::sampler::Sampler;
 use crate::shader::*;
@@ -51,11 +54,13 @@ macro_rules! impl_uniform_accessors {
 /// - `shader`: the shader this material adheres to (layout/metadata source).
 /// - `parameters`: per-binding storage:
 ///   - UniformBuffer: raw bytes sized and aligned by the builder
-///   - Texture: optional texture handle
+///   - Texture: optional texture handle, or transient of the render graph drawing the material
+/// - `render_state`: blending, culling, depth and topology used to build its pipeline.
 #[derive(Clone)]
 pub struct Material {
     shader: ShaderRc,
     parameters: Box<[MaterialParameter]>,
+    render_state: RenderState,
 }
 
 impl Resource for Material {}
@@ -70,6 +75,7 @@ impl Material {
         Self {
             parameters: MaterialParameter::from_shader(&shader),
             shader,
+            render_state: RenderState::default(),
         }
     }
 
@@ -87,9 +93,28 @@ impl Material {
         &self.shader
     }
 
+    /// Returns the fixed-function state this material is drawn with.
+    #[inline]
+    pub fn render_state(&self) -> &RenderState {
+        &self.render_state
+    }
+
+    /// Mutable access to the render state.
+    #[inline]
+    pub fn render_state_mut(&mut self) -> &mut RenderState {
+        &mut self.render_state
+    }
+
+    /// Replaces the render state.
+    #[inline]
+    pub fn set_render_state(&mut self, render_state: RenderState) -> &mut Self {
+        self.render_state = render_state;
+        self
+    }
+
     /// Internal accessor to the binding storage (schema-aligned).
     #[inline]
-    pub(crate) fn parameters(&self) -> &Box<[MaterialParameter]> {
+    pub(crate) fn parameters(&self) -> &[MaterialParameter] {
         &self.parameters
     }
 
@@ -173,6 +198,40 @@ impl Material {
     /// Assigns a texture to the specified texture binding key.
     #[inline]
     pub fn set_param_t(&mut self, key: Symbol, texture: Option<TextureHandle>) -> &mut Self {
+        self.set_param_texture(key, texture.map(GraphTexture::Imported))
+    }
+
+    /// Returns the texture handle stored at the binding (if any).
+    #[inline]
+    pub fn get_param_t(&self, key: Symbol) -> Option<&TextureHandle> {
+        match self.get_param_texture(key)? {
+            GraphTexture::Imported(handle) => Some(handle),
+            GraphTexture::Transient(_) => None,
+        }
+    }
+
+    /// Assigns a transient texture of a render graph to the specified texture binding key,
+    /// e.g. the output of an earlier pass for post-processing.
+    ///
+    /// The transient is resolved in the graph the material is drawn by, so the material
+    /// only belongs to passes of that graph; elsewhere the binding samples white. Passes
+    /// drawing it must declare the transient with `GraphPass::read` and must not write it.
+    /// Its format must be filterable (e.g. not `Rgba32Float`).
+    #[inline]
+    pub fn set_param_transient(&mut self, key: Symbol, texture: Option<TransientId>) -> &mut Self {
+        self.set_param_texture(key, texture.map(GraphTexture::Transient))
+    }
+
+    /// Returns the transient texture stored at the binding (if any).
+    #[inline]
+    pub fn get_param_transient(&self, key: Symbol) -> Option<TransientId> {
+        match self.get_param_texture(key)? {
+            GraphTexture::Transient(id) => Some(*id),
+            GraphTexture::Imported(_) => None,
+        }
+    }
+
+    fn set_param_texture(&mut self, key: Symbol, texture: Option<GraphTexture>) -> &mut Self {
         let meta = self.shader.texture_meta(key).expect("unknown texture key");
         let entry = &self.shader.binding_schema()[meta.index];
         let parameter = &mut self.parameters[meta.index];
@@ -191,14 +250,15 @@ impl Material {
         self
     }
 
-    /// Returns the texture handle stored at the binding (if any).
-    #[inline]
-    pub fn get_param_t(&self, key: Symbol) -> Option<&TextureHandle> {
+    fn get_param_texture(&self, key: Symbol) -> Option<&GraphTexture> {
         let meta = self.shader.texture_meta(key).expect("unknown texture key");
         self.parameters[meta.index].expect_texture().as_ref()
     }
 
     /// Assigns a sampler to the specified sampler binding key.
+    ///
+    /// Panics if `sampler.compare` is missing for a comparison sampler binding, or set for
+    /// a filtering one.
     #[inline]
     pub fn set_param_s(&mut self, key: Symbol, sampler: Sampler) -> &mut Self {
         let meta = self.shader.sampler_meta(key).expect("unknown sampler key");
@@ -206,7 +266,12 @@ impl Material {
         let parameter = &mut self.parameters[meta.index];
 
         match (&entry.ty, parameter) {
-            (BindingType::Sampler, MaterialParameter::Sampler { val, ver }) => {
+            (BindingType::Sampler { comparison }, MaterialParameter::Sampler { val, ver }) => {
+                assert_eq!(
+                    *comparison,
+                    sampler.compare.is_some(),
+                    "sampler compare function does not match the binding"
+                );
                 if val.as_deref() == Some(&sampler) {
                     return self;
                 }
@@ -228,9 +293,13 @@ impl Material {
 }
 
 impl Material {
+    /// Handles of the textures in `Resources` the material binds; transients are left out.
     pub(crate) fn textures(&self) -> impl Iterator<Item = &TextureHandle> {
         self.parameters.iter().filter_map(|param| match param {
-            MaterialParameter::Texture { val, .. } => val.as_ref(),
+            MaterialParameter::Texture {
+                val: Some(GraphTexture::Imported(handle)),
+                ..
+            } => Some(handle),
             _ => None,
         })
     }
@@ -255,11 +324,11 @@ mod tests {
 
         let mut material = Material::from_shader(shader.clone());
 
-        assert_eq!(material.parameters.len(), 3);
+        assert_eq!(material.parameters.len(), 11);
 
         match &material.parameters[0] {
             MaterialParameter::UniformBuffer { val, .. } => {
-                assert_eq!(val.len(), 32); // std140: vec4 + float
+                assert_eq!(val.len(), 48); // std140: vec4 + 4 floats + vec3
             }
             _ => panic!("Expected UniformBuffer"),
         }
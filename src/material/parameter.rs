@@ -4,24 +4,25 @@
 //! `Shader`'s binding schema. It is not exposed publicly; callers access it
 //! indirectly through `Material`.
 
+use crate::graph::GraphTexture;
 use crate::shader::*;
-use crate::{DirtyVersion, TextureHandle, sampler::Sampler};
+use crate::{DirtyVersion, sampler::Sampler};
 
 /// Per-binding data stored by a `Material`.
 ///
 /// Variants mirror the shader binding types:
 /// - `UniformBuffer`: raw bytes sized by the layout computed in the builder
-/// - `Texture`: an optional texture handle (`None` means unbound)
+/// - `Texture`: an optional texture in `Resources` or transient of a render graph (`None` means unbound)
 #[derive(Clone)]
 pub enum MaterialParameter {
     /// Raw bytes that back a uniform-buffer binding.
     /// The length equals the total size computed by the layout.
     UniformBuffer { val: Box<[u8]>, ver: DirtyVersion },
 
-    /// Texture binding stored as an optional handle.
+    /// Texture binding stored as an optional graph texture.
     /// `None` indicates the texture is currently unbound.
     Texture {
-        val: Option<TextureHandle>,
+        val: Option<GraphTexture>,
         ver: DirtyVersion,
     },
 
@@ -43,7 +44,7 @@ impl MaterialParameter {
     }
 
     #[inline]
-    pub fn texture(val: Option<TextureHandle>) -> Self {
+    pub fn texture(val: Option<GraphTexture>) -> Self {
         MaterialParameter::Texture {
             val,
             ver: DirtyVersion::new(),
@@ -70,7 +71,7 @@ impl MaterialParameter {
                     MaterialParameter::uniform_buffer(vec![0u8; *total_size])
                 }
                 BindingType::Texture => MaterialParameter::texture(None),
-                BindingType::Sampler => MaterialParameter::sampler(None),
+                BindingType::Sampler { .. } => MaterialParameter::sampler(None),
             };
             bindings.push(resource);
         }
@@ -91,12 +92,12 @@ impl MaterialParameter {
         }
     }
 
-    /// Returns the optional texture handle for this binding.
+    /// Returns the optional texture for this binding.
     ///
     /// Panics
     /// - If this binding is not `Texture`.
     #[inline(always)]
-    pub fn expect_texture(&self) -> &Option<TextureHandle> {
+    pub fn expect_texture(&self) -> &Option<GraphTexture> {
         match self {
             MaterialParameter::Texture { val, .. } => val,
             _ => panic!("expected Texture at index"),
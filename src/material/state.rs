@@ -0,0 +1,268 @@
+use std::hash::{Hash, Hasher};
+
+/// How fragment output is combined with the color already in the target.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Output replaces the target color.
+    #[default]
+    Opaque,
+    /// Classic `src * a + dst * (1 - a)` transparency.
+    Alpha,
+    /// `src * a + dst`, for glows and particles.
+    Additive,
+    /// `src + dst * (1 - a)`, for colors already multiplied by alpha.
+    Premultiplied,
+    /// Any other wgpu blend state.
+    Custom(wgpu::BlendState),
+}
+
+impl BlendMode {
+    /// The wgpu blend state applied to every color target.
+    pub fn blend_state(&self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            BlendMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Custom(state) => *state,
+        }
+    }
+}
+
+/// Stencil test and update applied to front and back faces.
+///
+/// The reference value compared and written is set per primitive with
+/// `Primitive::set_stencil_reference`. The test only runs on targets with a stencil
+/// aspect (e.g. `Depth24PlusStencil8`); the default is disabled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StencilState {
+    pub front: wgpu::StencilFaceState,
+    pub back: wgpu::StencilFaceState,
+    pub read_mask: u32,
+    pub write_mask: u32,
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        Self {
+            front: wgpu::StencilFaceState::IGNORE,
+            back: wgpu::StencilFaceState::IGNORE,
+            read_mask: 0,
+            write_mask: 0,
+        }
+    }
+}
+
+impl StencilState {
+    /// Same test and operations on both faces, with full read and write masks.
+    pub fn both(face: wgpu::StencilFaceState) -> Self {
+        Self {
+            front: face,
+            back: face,
+            read_mask: !0,
+            write_mask: !0,
+        }
+    }
+
+    /// Writes the reference value wherever the fragment passes the depth test,
+    /// e.g. to mark a portal or an object to outline.
+    pub fn replace() -> Self {
+        Self::both(wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        })
+    }
+
+    /// Draws only where the stencil equals the reference value, e.g. inside a portal.
+    pub fn equal() -> Self {
+        Self::test(wgpu::CompareFunction::Equal)
+    }
+
+    /// Draws only where the stencil differs from the reference value, e.g. an outline around a marked object.
+    pub fn not_equal() -> Self {
+        Self::test(wgpu::CompareFunction::NotEqual)
+    }
+
+    /// Compares against the reference value without modifying the stencil.
+    pub fn test(compare: wgpu::CompareFunction) -> Self {
+        Self {
+            write_mask: 0,
+            ..Self::both(wgpu::StencilFaceState {
+                compare,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Keep,
+            })
+        }
+    }
+
+    /// The wgpu stencil state.
+    pub fn to_wgpu(&self) -> wgpu::StencilState {
+        wgpu::StencilState {
+            front: self.front,
+            back: self.back,
+            read_mask: self.read_mask,
+            write_mask: self.write_mask,
+        }
+    }
+}
+
+/// Fixed-function state a material is drawn with.
+///
+/// - `blend`: how output is combined with the target.
+/// - `alpha_cutoff`: fragments with alpha below this are discarded. Requires the shader
+///   to declare `override alpha_cutoff: f32;` (the builtin shaders do).
+/// - `cull_mode` / `front_face`: which triangles are culled; `None` draws both sides.
+/// - `depth_compare` / `depth_write`: depth test and whether depth is written.
+/// - `depth_bias`: constant and slope-scaled bias, e.g. for decals.
+/// - `stencil`: stencil test and update, see [`StencilState`].
+/// - `topology`: how vertices are assembled into primitives.
+///
+/// The default matches an opaque, back-face culled triangle list with depth test and write.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderState {
+    pub blend: BlendMode,
+    pub alpha_cutoff: Option<f32>,
+    pub cull_mode: Option<wgpu::Face>,
+    pub front_face: wgpu::FrontFace,
+    pub depth_compare: wgpu::CompareFunction,
+    pub depth_write: bool,
+    pub depth_bias: wgpu::DepthBiasState,
+    pub stencil: StencilState,
+    pub topology: wgpu::PrimitiveTopology,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            blend: BlendMode::Opaque,
+            alpha_cutoff: None,
+            cull_mode: Some(wgpu::Face::Back),
+            front_face: wgpu::FrontFace::Ccw,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            depth_write: true,
+            depth_bias: wgpu::DepthBiasState::default(),
+            stencil: StencilState::default(),
+            topology: wgpu::PrimitiveTopology::TriangleList,
+        }
+    }
+}
+
+impl RenderState {
+    /// State for blended surfaces such as glass: alpha blending, depth test without depth write.
+    pub fn transparent() -> Self {
+        Self {
+            blend: BlendMode::Alpha,
+            depth_write: false,
+            ..Default::default()
+        }
+    }
+
+    /// State for alpha-tested, double-sided surfaces such as foliage.
+    pub fn masked(cutoff: f32) -> Self {
+        Self {
+            alpha_cutoff: Some(cutoff),
+            cull_mode: None,
+            ..Default::default()
+        }
+    }
+
+    /// State for overlays drawn on top of everything: alpha blending, no depth test or write.
+    pub fn overlay() -> Self {
+        Self {
+            blend: BlendMode::Alpha,
+            depth_compare: wgpu::CompareFunction::Always,
+            depth_write: false,
+            ..Default::default()
+        }
+    }
+}
+
+impl PartialEq for RenderState {
+    fn eq(&self, other: &Self) -> bool {
+        self.blend == other.blend
+            && self.alpha_cutoff.map(f32::to_bits) == other.alpha_cutoff.map(f32::to_bits)
+            && self.cull_mode == other.cull_mode
+            && self.front_face == other.front_face
+            && self.depth_compare == other.depth_compare
+            && self.depth_write == other.depth_write
+            && self.depth_bias == other.depth_bias
+            && self.stencil == other.stencil
+            && self.topology == other.topology
+    }
+}
+impl Eq for RenderState {}
+
+impl Hash for RenderState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.blend.hash(state);
+        self.alpha_cutoff.map(f32::to_bits).hash(state);
+        self.cull_mode.hash(state);
+        self.front_face.hash(state);
+        self.depth_compare.hash(state);
+        self.depth_write.hash(state);
+        self.depth_bias.hash(state);
+        self.stencil.hash(state);
+        self.topology.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::DefaultHasher;
+
+    fn hash(state: &RenderState) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_equal_states_hash_the_same() {
+        let a = RenderState::masked(0.5);
+        let b = RenderState {
+            cull_mode: None,
+            alpha_cutoff: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+    }
+
+    #[test]
+    fn test_alpha_cutoffs_compare_by_value() {
+        assert_ne!(RenderState::masked(0.5), RenderState::masked(0.25));
+        assert_ne!(RenderState::masked(0.5), RenderState::default());
+        assert_ne!(
+            hash(&RenderState::masked(0.5)),
+            hash(&RenderState::masked(0.25))
+        );
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let additive = BlendMode::Additive.blend_state();
+        assert_eq!(additive.color.src_factor, wgpu::BlendFactor::SrcAlpha);
+        assert_eq!(additive.color.dst_factor, wgpu::BlendFactor::One);
+        assert_eq!(
+            BlendMode::Premultiplied.blend_state(),
+            wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
+        );
+        let custom = wgpu::BlendState::ALPHA_BLENDING;
+        assert_eq!(BlendMode::Custom(custom).blend_state(), custom);
+    }
+}
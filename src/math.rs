@@ -13,7 +13,7 @@ pub type Mat4 = glam::Mat4;
 pub type Quat = glam::Quat;
 pub type EulerRot = glam::EulerRot;
 
-#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
 #[repr(C)]
 pub struct Color3 {
     pub r: f32,
@@ -27,7 +27,7 @@ impl Color3 {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
 #[repr(C)]
 pub struct Color4 {
     pub r: f32,
@@ -7,6 +7,7 @@ pub struct Primitive {
     transform: Mat4,
     geometry: GeometryHandle,
     material: MaterialHandle,
+    stencil_reference: u32,
 }
 
 impl Primitive {
@@ -15,6 +16,7 @@ impl Primitive {
             transform: Mat4::IDENTITY,
             geometry,
             material,
+            stencil_reference: 0,
         }
     }
 
@@ -50,4 +52,16 @@ impl Primitive {
     pub fn material(&self) -> &MaterialHandle {
         &self.material
     }
+
+    /// Sets the value the material's stencil test compares against and writes.
+    #[inline]
+    pub fn set_stencil_reference(&mut self, stencil_reference: u32) -> &mut Self {
+        self.stencil_reference = stencil_reference;
+        self
+    }
+
+    #[inline]
+    pub fn stencil_reference(&self) -> u32 {
+        self.stencil_reference
+    }
 }
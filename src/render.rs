@@ -1,27 +1,67 @@
+mod background;
 mod bindgroups;
 mod buffers;
+mod config;
+mod deferred;
+mod environment;
+mod error;
 mod geometries;
+mod graph;
 mod materials;
 mod pipelines;
+mod queue;
+mod readback;
+mod report;
 mod samplers;
+mod shadows;
 mod surfaces;
 mod targets;
 mod textures;
+mod transients;
 
-use crate::{ResourceKey, Resources, camera::Camera, primitive::Primitive, target::RenderTarget};
+use crate::{
+    GeometryHandle, MaterialHandle, ResourceKey, Resources, TextureHandle,
+    background::Background,
+    camera::{Camera, Frustum, Viewport},
+    graph::{
+        DrawFilter, GBuffer, GraphColorAttachment, GraphPass, GraphTexture, PassShading,
+        RenderGraph,
+    },
+    light::{Environment, Light},
+    material::BlendMode,
+    primitive::Primitive,
+    target::{LoadOp, Operations, RenderTarget, StoreOp},
+    texture::TextureKind,
+};
+use background::{BackgroundKind, Backgrounds};
 use bindgroups::{GlobalBindGroup, PrimitiveBindGroup};
 use buffers::Buffers;
+pub use config::{
+    OPTIONAL_FEATURES, REQUIRED_DOWNLEVEL_FLAGS, RendererConfig, ShadingPath, ShadowConfig,
+    ShadowFilter, SurfaceFormatPreference,
+};
+use deferred::Deferred;
+use environment::EnvironmentMaps;
+pub use error::RendererError;
 use geometries::Geometries;
+use graph::GraphPlan;
 use materials::Materials;
-use pipelines::Pipelines;
+use pipelines::{PipelineKey, PipelineVariant, Pipelines, TargetFormats};
+use queue::RenderQueue;
+pub use readback::TexturePixels;
+pub use report::{GraphReport, RenderReport};
 use samplers::Samplers;
-use std::collections::HashSet;
+use shadows::{FrameShadows, SHADOW_MAP_FORMAT, ShadowMaps, ShadowTarget};
+use std::collections::{HashMap, HashSet};
+pub use surfaces::FrameStatus;
 use surfaces::Surfaces;
 use targets::Targets;
 use textures::Textures;
+use transients::Transients;
 
 const GEOMETRY_CHANGED: u8 = 0b01;
 const MATERIAL_CHANGED: u8 = 0b10;
+const STENCIL_CHANGED: u8 = 0b100;
 
 pub struct Renderer {
     adapter: wgpu::Adapter,
@@ -30,6 +70,7 @@ pub struct Renderer {
     surfaces: Surfaces,
     pipelines: Pipelines,
     targets: Targets,
+    transients: Transients,
     geometries: Geometries,
     global_bind_group: GlobalBindGroup,
     primitive_bind_group: PrimitiveBindGroup,
@@ -37,52 +78,100 @@ pub struct Renderer {
     buffers: Buffers,
     samplers: Samplers,
     materials: Materials,
+    deferred: Deferred,
+    backgrounds: Backgrounds,
+    shadow_maps: ShadowMaps,
+    environment_maps: EnvironmentMaps,
+    shading_path: ShadingPath,
+    shadow_config: ShadowConfig,
+    environment: Option<Environment>,
 }
 
 impl Renderer {
-    pub async fn new(instance: &wgpu::Instance, surface: &wgpu::Surface<'_>) -> Self {
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(surface),
-                force_fallback_adapter: false,
-            })
+    /// Creates a renderer for `surface` with the default [`RendererConfig`].
+    pub async fn new(
+        instance: &wgpu::Instance,
+        surface: &wgpu::Surface<'_>,
+    ) -> Result<Self, RendererError> {
+        Self::with_config(instance, Some(surface), RendererConfig::default()).await
+    }
+
+    /// Creates a renderer that is not tied to any window surface.
+    ///
+    /// Falls back to the software adapter when no hardware adapter is available,
+    /// so it can run in CI, batch jobs and tests. Only `TextureKind::Render`
+    /// color attachments can be rendered to.
+    pub async fn new_headless(instance: &wgpu::Instance) -> Result<Self, RendererError> {
+        Self::with_config(instance, None, RendererConfig::default()).await
+    }
+
+    /// Creates a renderer from `config`.
+    ///
+    /// - `surface`: a surface the adapter must be able to present to, or `None` for headless use.
+    /// - Options the adapter cannot satisfy fall back as documented on [`RendererConfig`].
+    pub async fn with_config(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+        config: RendererConfig,
+    ) -> Result<Self, RendererError> {
+        let adapter = config
+            .request_adapter(instance, surface)
             .await
-            .unwrap();
+            .map_err(RendererError::AdapterUnavailable)?;
 
         println!("{:?}", adapter.get_info());
+        if let Some(surface) = surface {
+            println!("{:?}", surface.get_capabilities(&adapter));
+        }
+
+        let missing = REQUIRED_DOWNLEVEL_FLAGS - adapter.get_downlevel_capabilities().flags;
+        if !missing.is_empty() {
+            return Err(RendererError::MissingDownlevelFlags(missing));
+        }
 
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
+                required_features: config.features_for(adapter.features()),
+                required_limits: config.limits_for(adapter.limits()),
                 label: None,
                 memory_hints: wgpu::MemoryHints::Performance,
                 trace: wgpu::Trace::Off,
             })
             .await
-            .unwrap();
-
-        println!("{:?}", surface.get_capabilities(&adapter));
+            .map_err(RendererError::DeviceUnavailable)?;
 
-        let surfaces = Surfaces::new();
+        let shading_path = config.shading_path;
+        let shadow_config = config.shadows;
+        let surfaces = Surfaces::new(config);
         let geometries = Geometries::new();
-        let pipelines = Pipelines::new(surface.get_capabilities(&adapter).formats[0]);
+        let pipelines = Pipelines::new();
         let targets = Targets::new();
-        let global_bind_group = GlobalBindGroup::new(&device);
+        let transients = Transients::new();
+        let samplers = Samplers::new(&device);
+        let backend = adapter.get_info().backend;
+        let shadow_maps = ShadowMaps::new(&device, backend);
+        let environment_maps = EnvironmentMaps::new(&device, &queue);
+        let global_bind_group = GlobalBindGroup::new(
+            &device,
+            &shadow_maps,
+            samplers.get_default_gpu_comparison_sampler(),
+            &environment_maps,
+        );
         let primitive_bind_group = PrimitiveBindGroup::new(&device, 10_000);
-        let textures = Textures::new(&device, &queue);
+        let textures = Textures::new(&device, &queue, backend);
         let buffers = Buffers::new();
-        let samplers = Samplers::new(&device);
         let materials = Materials::new();
+        let deferred = Deferred::new(&device);
+        let backgrounds = Backgrounds::new(&device);
 
-        Self {
+        Ok(Self {
             adapter,
             device,
             queue,
             surfaces,
             pipelines,
             targets,
+            transients,
             geometries,
             global_bind_group,
             primitive_bind_group,
@@ -90,133 +179,366 @@ impl Renderer {
             buffers,
             samplers,
             materials,
-        }
+            deferred,
+            backgrounds,
+            shadow_maps,
+            environment_maps,
+            shading_path,
+            shadow_config,
+            environment: None,
+        })
+    }
+
+    /// Selects how [`Renderer::render`] shades primitives.
+    #[inline]
+    pub fn set_shading_path(&mut self, shading_path: ShadingPath) -> &mut Self {
+        self.shading_path = shading_path;
+        self
+    }
+
+    #[inline]
+    pub fn shading_path(&self) -> ShadingPath {
+        self.shading_path
+    }
+
+    /// Configures the shadow maps of lights that cast shadows.
+    #[inline]
+    pub fn set_shadow_config(&mut self, shadow_config: ShadowConfig) -> &mut Self {
+        self.shadow_config = shadow_config;
+        self
+    }
+
+    #[inline]
+    pub fn shadow_config(&self) -> &ShadowConfig {
+        &self.shadow_config
+    }
+
+    /// Lights [`Renderer::render`] with image-based lighting from `environment`, or only
+    /// with its lights for `None`.
+    #[inline]
+    pub fn set_environment(&mut self, environment: Option<Environment>) -> &mut Self {
+        self.environment = environment;
+        self
     }
 
+    #[inline]
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    /// Draws `primitives` lit by `lights` into `target` in front of `background`, if any,
+    /// and submits the frame.
+    ///
+    /// Primitives whose geometry, material or textures are missing, or whose
+    /// geometry lacks an attribute required by the shader, are skipped and listed
+    /// in the returned report. Lost or outdated surfaces are reconfigured and the
+    /// frame is rendered; if a surface times out the frame is skipped, and the
+    /// report's `status` says which happened. Other errors that affect the whole
+    /// frame are returned as `Err`.
+    ///
+    /// Primitives are shaded according to the renderer's [`ShadingPath`], and opaque ones
+    /// cast shadows from lights with `cast_shadows`, see [`ShadowConfig`]. The renderer's
+    /// environment, if any, adds image-based lighting. Draw calls and state switches of the
+    /// shadow map passes are included in the report; their primitives are not counted again.
     pub fn render(
         &mut self,
         primitives: &[Primitive],
+        lights: &[Light],
         camera: &Camera,
         target: &RenderTarget,
+        background: Option<&Background>,
         resources: &crate::Resources,
-    ) {
-        let surface_textures =
-            self.surfaces
-                .get_surface_textures(&self.adapter, &self.device, target, resources);
-
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    ) -> Result<RenderReport, RendererError> {
+        let (width, height) = target.size();
+        let mut graph = RenderGraph::new();
+        graph
+            .set_lights(lights)
+            .set_shadow_casters(primitives, camera.fit_to_target(width, height))
+            .set_environment(self.environment.clone());
+        match self.shading_path {
+            ShadingPath::Forward => {
+                let mut pass = GraphPass::from_target(target).draw(primitives, camera);
+                if let Some(background) = background {
+                    pass = pass.with_background(background);
+                }
+                graph.add_pass(pass);
+            }
+            ShadingPath::Deferred => {
+                Self::add_deferred_passes(&mut graph, primitives, camera, target, background)
+            }
+        }
 
-        let change_flags = {
-            let mut change_flags = Vec::with_capacity(primitives.len());
+        let report = self.execute(&graph, resources)?;
 
-            let mut last_geometry_handle = None;
-            let mut last_material_handle = None;
+        let mut passes = report
+            .passes
+            .into_iter()
+            .map(|(_, pass_report)| pass_report);
+        let mut render_report = passes.next().unwrap_or_else(|| RenderReport {
+            status: report.status,
+            ..Default::default()
+        });
+        for pass_report in passes {
+            render_report.merge(pass_report);
+        }
+        for (_, shadow_report) in &report.shadow_passes {
+            render_report.merge_draws(shadow_report);
+        }
+        Ok(render_report)
+    }
 
-            let mut geometry_handles = HashSet::new();
-            let mut material_handles = HashSet::new();
+    /// Adds the passes of deferred shading into `target`: a geometry pass filling a G-buffer
+    /// with the opaque primitives, a lighting pass, and a forward pass for blended primitives
+    /// that tests against the G-buffer depth and draws the background.
+    fn add_deferred_passes<'a>(
+        graph: &mut RenderGraph<'a>,
+        primitives: &'a [Primitive],
+        camera: &'a Camera,
+        target: &RenderTarget,
+        background: Option<&'a Background>,
+    ) {
+        let (width, height) = target.size();
+        let gbuffer = GBuffer::create(graph, width, height);
 
-            for primitive in primitives {
-                let geometry_handle = primitive.geometry();
-                let material_handle = primitive.material();
+        graph.add_pass(
+            GraphPass::new(format!("{} G-Buffer", target.name()), width, height)
+                .with_reverse_z(target.reverse_z())
+                .write_gbuffer(&gbuffer)
+                .draw(primitives, camera),
+        );
 
-                let mut flag = 0u8;
+        graph.add_pass(
+            GraphPass::from_target(target)
+                .with_sample_count(1)
+                .light_gbuffer(&gbuffer, camera),
+        );
 
-                if Some(geometry_handle.raw()) != last_geometry_handle {
-                    flag |= GEOMETRY_CHANGED;
-                    last_geometry_handle = Some(geometry_handle.raw());
-                    geometry_handles.insert(primitive.geometry());
-                }
-                if Some(material_handle.raw()) != last_material_handle {
-                    flag |= MATERIAL_CHANGED;
-                    last_material_handle = Some(material_handle.raw());
-                    material_handles.insert(primitive.material());
-                }
+        let mut blended = GraphPass::new(format!("{} Blended", target.name()), width, height)
+            .with_reverse_z(target.reverse_z());
+        for color_attachment in target.color_attachments() {
+            blended = blended.color_attachment(GraphColorAttachment {
+                texture: (&color_attachment.texture).into(),
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: color_attachment.ops.store,
+                },
+                blend: color_attachment.blend,
+                write_mask: color_attachment.write_mask,
+            });
+        }
+        if let Some(background) = background {
+            blended = blended.with_background(background);
+        }
+        graph.add_pass(
+            blended
+                .depth_stencil(
+                    gbuffer.depth.clone(),
+                    Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Discard,
+                    },
+                    Operations::default(),
+                )
+                .with_filter(DrawFilter::Blended)
+                .draw(primitives, camera),
+        );
+    }
 
-                change_flags.push(flag);
-            }
+    /// Runs the passes of `graph` and submits the frame.
+    ///
+    /// Passes are ordered by their texture dependencies and passes whose output is
+    /// never used are culled. Transient textures are backed by pooled GPU textures,
+    /// shared between transients whose lifetimes do not overlap. Every pass is
+    /// recorded into one command encoder and submitted once. Primitives and surfaces
+    /// are handled per pass as in [`Renderer::render`]; their materials may sample the
+    /// transients a pass reads.
+    pub fn execute(
+        &mut self,
+        graph: &RenderGraph,
+        resources: &crate::Resources,
+    ) -> Result<GraphReport, RendererError> {
+        let plan = GraphPlan::new(graph)?;
+        let passes = plan
+            .order
+            .iter()
+            .map(|&index| &graph.passes()[index])
+            .collect::<Vec<_>>();
 
-            let geometris = geometry_handles
+        let attachments = passes.iter().flat_map(|pass| {
+            pass.color_attachments()
                 .iter()
-                .map(|handle| resources.get_geometry(handle).unwrap())
-                .collect::<Vec<_>>();
+                .filter_map(move |color_attachment| match &color_attachment.texture {
+                    GraphTexture::Imported(handle) => Some((handle, pass.size())),
+                    GraphTexture::Transient(_) => None,
+                })
+        });
 
-            let materials = material_handles
-                .iter()
-                .map(|handle| resources.get_material(handle).unwrap())
-                .collect::<Vec<_>>();
+        let surface_textures = self.surfaces.get_surface_textures(
+            &self.adapter,
+            &self.device,
+            attachments,
+            resources,
+        )?;
 
-            let texture_handles = materials
+        let mut report = GraphReport {
+            status: surface_textures.status(),
+            culled_passes: plan
+                .culled
                 .iter()
-                .flat_map(|material| material.textures())
-                .collect::<HashSet<_>>();
+                .map(|&index| graph.passes()[index].name().to_string())
+                .collect(),
+            transient_textures: plan.slot_count,
+            ..Default::default()
+        };
 
-            let textures = texture_handles
-                .iter()
-                .filter_map(|handle| resources.get_texture(handle))
-                .collect::<Vec<_>>();
+        if surface_textures.status() == FrameStatus::Skipped {
+            return Ok(report);
+        }
 
-            let buffer_handles = geometris
-                .iter()
-                .flat_map(|geometry| geometry.buffers())
-                .collect::<HashSet<_>>();
+        // before preparing materials, which may sample transients
+        self.transients
+            .allocate(&self.device, graph.transients(), &plan.transient_slots);
 
-            buffer_handles.iter().for_each(|buffer_handle| {
-                self.buffers
-                    .prepare(&self.device, &self.queue, resources, buffer_handle);
-            });
+        let (shadows, shadow_passes) =
+            self.prepare_shadows(graph, resources, surface_textures.status());
 
-            texture_handles
-                .iter()
-                .zip(textures.iter())
-                .for_each(|(handle, texture)| {
-                    self.textures
-                        .prepare(&self.device, &self.queue, texture, handle);
-                });
+        let mut prepared_passes = Vec::with_capacity(passes.len());
+        for pass in passes.iter() {
+            let target_formats = self.targets.target_formats(
+                &self.adapter,
+                self.device.features(),
+                &surface_textures,
+                graph,
+                pass,
+                resources,
+            )?;
 
-            material_handles
-                .iter()
-                .zip(materials.iter())
-                .for_each(|(handle, material)| {
-                    material.samplers().for_each(|sampler| {
-                        self.samplers.prepare(&self.device, *sampler);
-                    });
+            let mut pass_report = RenderReport {
+                status: surface_textures.status(),
+                ..Default::default()
+            };
 
-                    self.materials.prepare(
-                        &self.device,
-                        &self.queue,
-                        resources,
-                        &self.textures,
-                        &self.samplers,
-                        handle,
-                    );
+            // nothing is visible through an empty viewport or scissor rectangle
+            let draw_area = !pass.viewport().is_some_and(|viewport| viewport.is_empty())
+                && !pass.scissor().is_some_and(|scissor| {
+                    let (width, height) = pass.size();
+                    scissor.clamped(width, height).is_empty()
                 });
 
-            geometry_handles
-                .iter()
-                .zip(geometris.iter())
-                .for_each(|(handle, geometry)| {
-                    self.geometries.prepare(geometry, handle);
-                });
+            let camera = pass.fitted_camera();
+            let drawables = match &camera {
+                Some(camera) if draw_area => self.collect_drawables(
+                    pass.primitives(),
+                    pass.filter(),
+                    Self::pipeline_variant(pass),
+                    camera,
+                    resources,
+                    &mut pass_report,
+                ),
+                _ => Vec::new(),
+            };
 
-            for (_, primitive) in primitives.iter().enumerate().filter(|(i, _)| {
-                let flag = change_flags[*i];
-                (flag & GEOMETRY_CHANGED) != 0 || (flag & MATERIAL_CHANGED) != 0
-            }) {
-                let geometry_handle = primitive.geometry();
-                let material_handle = primitive.material();
-                let geometry = resources.get_geometry(geometry_handle).unwrap();
-                let shader = resources.get_material(material_handle).unwrap().shader();
-                self.geometries
-                    .link_shader(geometry, geometry_handle, shader);
+            prepared_passes.push((target_formats, camera, drawables, pass_report));
+        }
+
+        let instance_count = prepared_passes
+            .iter()
+            .map(|(_, _, drawables, _)| drawables.len())
+            .chain(shadow_passes.iter().map(|(drawables, _)| drawables.len()))
+            .sum();
+        self.primitive_bind_group
+            .prepare(&self.device, instance_count);
+        self.global_bind_group
+            .prepare(
+                &self.device,
+                passes.len() + shadow_passes.len(),
+                graph.lights(),
+                &shadows.light_slots,
+            )
+            .set_shadows(
+                &self.device,
+                &shadows,
+                &self.shadow_config,
+                &self.shadow_maps,
+            );
+
+        let environment_intensity = match graph.environment() {
+            Some(environment) => {
+                self.prepare_environment(environment, resources)?;
+                environment.intensity
             }
+            None => 0.0,
+        };
+        self.global_bind_group
+            .set_environment(environment_intensity);
 
-            change_flags
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        let shadow_formats = TargetFormats {
+            color_targets: Vec::new(),
+            depth_format: Some(SHADOW_MAP_FORMAT),
+            sample_count: 1,
+            reverse_z: false,
         };
+        // shadow views render into renderer-owned layers and atlas tiles of the shadow maps,
+        // which the graph cannot name, so they run ahead of the graph's passes
+        for (shadow_view, (drawables, mut pass_report)) in shadows.views.iter().zip(shadow_passes) {
+            let (shadow_map, view) = self.shadow_maps.target(shadow_view.target);
+            let view = view.clone();
+            let viewport = shadows.viewport(shadow_view.target, shadow_map.resolution());
+            // atlas tiles share one texture, cleared by the first tile
+            let load = match shadow_view.target {
+                ShadowTarget::AtlasTile(tile) if tile > 0 => wgpu::LoadOp::Load,
+                _ => wgpu::LoadOp::Clear(1.0),
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &view,
+                    depth_ops: Some(wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_viewport(
+                viewport.x,
+                viewport.y,
+                viewport.width,
+                viewport.height,
+                0.0,
+                1.0,
+            );
+
+            let global_offset = self
+                .global_bind_group
+                .push_camera(&shadow_view.camera, &viewport);
+            self.draw(
+                &mut render_pass,
+                &drawables,
+                global_offset,
+                &shadow_formats,
+                PipelineVariant::ShadowDepth,
+                resources,
+                &mut pass_report,
+            )?;
 
+            pass_report.drawn = drawables.len();
+            report
+                .shadow_passes
+                .push((shadow_view.target.name(), pass_report));
+        }
+
+        for (pass, (target_formats, camera, drawables, mut pass_report)) in
+            passes.iter().zip(prepared_passes)
         {
             let mut render_pass = self.targets.create_render_pass(
                 &self.device,
@@ -224,81 +546,538 @@ impl Renderer {
                 &surface_textures,
                 &mut encoder,
                 &mut self.textures,
-                target,
+                &self.transients,
+                pass,
+                target_formats.sample_count,
                 resources,
-            );
+            )?;
+
+            if let Some(camera) = &camera {
+                let (width, height) = pass.size();
+                if let Some(viewport) = pass.viewport() {
+                    render_pass.set_viewport(
+                        viewport.x,
+                        viewport.y,
+                        viewport.width,
+                        viewport.height,
+                        viewport.min_depth,
+                        viewport.max_depth,
+                    );
+                }
+                if let Some(scissor) = pass.scissor() {
+                    let scissor = scissor.clamped(width, height);
+                    render_pass.set_scissor_rect(
+                        scissor.x,
+                        scissor.y,
+                        scissor.width,
+                        scissor.height,
+                    );
+                }
+
+                let viewport = pass
+                    .viewport()
+                    .unwrap_or_else(|| Viewport::new(0.0, 0.0, width as f32, height as f32));
+                let global_offset = self.global_bind_group.push_camera(camera, &viewport);
+
+                if let PassShading::Lighting(gbuffer) = pass.shading() {
+                    let views = [
+                        &gbuffer.albedo,
+                        &gbuffer.normal,
+                        &gbuffer.material,
+                        &gbuffer.depth,
+                        &gbuffer.emissive,
+                    ]
+                    .map(|texture| {
+                        Targets::attachment_view(
+                            &self.device,
+                            &self.queue,
+                            &surface_textures,
+                            &mut self.textures,
+                            &self.transients,
+                            texture,
+                            resources,
+                        )
+                    });
+                    let [albedo, normal, material, depth, emissive] = views;
+                    let gbuffer_bind_group = self.deferred.bind_group(
+                        &self.device,
+                        &[albedo?, normal?, material?, depth?, emissive?],
+                    );
+
+                    let pipeline = self.deferred.pipeline(
+                        &self.device,
+                        self.global_bind_group.gpu_layout(),
+                        &target_formats,
+                    );
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_bind_group(
+                        0,
+                        self.global_bind_group.gpu_bind_group(),
+                        &[global_offset],
+                    );
+                    render_pass.set_bind_group(1, &gbuffer_bind_group, &[]);
+                    render_pass.draw(0..3, 0..1);
+                    pass_report.pipeline_switches += 1;
+                    pass_report.bind_group_switches += 1;
+                    pass_report.draw_calls += 1;
+                } else {
+                    // the background fills what opaque primitives left at the far plane and
+                    // blended ones are drawn over it; without depth it is drawn first
+                    let background_at = match pass.background() {
+                        Some(_) if target_formats.depth_format.is_some() => drawables
+                            .partition_point(|primitive| {
+                                resources.get_material(primitive.material()).is_some_and(
+                                    |material| material.render_state().blend == BlendMode::Opaque,
+                                )
+                            }),
+                        _ => 0,
+                    };
+                    let (front, back) = drawables.split_at(background_at);
+                    self.draw(
+                        &mut render_pass,
+                        front,
+                        global_offset,
+                        &target_formats,
+                        Self::pipeline_variant(pass),
+                        resources,
+                        &mut pass_report,
+                    )?;
+                    if let Some(background) = pass
+                        .background()
+                        .filter(|_| !target_formats.color_targets.is_empty())
+                    {
+                        self.draw_background(
+                            &mut render_pass,
+                            background,
+                            global_offset,
+                            &target_formats,
+                            resources,
+                            &mut pass_report,
+                        )?;
+                    }
+                    self.draw(
+                        &mut render_pass,
+                        back,
+                        global_offset,
+                        &target_formats,
+                        Self::pipeline_variant(pass),
+                        resources,
+                        &mut pass_report,
+                    )?;
+                }
+            }
 
-            let global_bind_group = &self.global_bind_group;
-            let primitive_bind_group = self
-                .primitive_bind_group
-                .prepare(&self.device, primitives.len());
+            pass_report.drawn = drawables.len();
+            report.passes.push((pass.name().to_string(), pass_report));
+        }
 
-            render_pass.set_bind_group(0, global_bind_group.gpu_bind_group(), &[]);
-            render_pass.set_bind_group(1, primitive_bind_group.gpu_bind_group(), &[]);
+        self.targets.end_frame();
+        self.backgrounds.end_frame();
 
-            let mut batch_start = 0u32;
-            let mut indices = 0..0;
+        self.global_bind_group.flush(&self.queue);
+        self.primitive_bind_group.flush(&self.queue);
 
-            for (i, primitive) in primitives.iter().enumerate() {
-                let geometry_handle = primitive.geometry();
-                let material_handle = primitive.material();
+        self.queue.submit(Some(encoder.finish()));
 
-                let flag = change_flags[i];
-                let geometry_changed = (flag & GEOMETRY_CHANGED) != 0;
-                let material_changed = (flag & MATERIAL_CHANGED) != 0;
+        surface_textures.present();
 
-                if geometry_changed || material_changed {
-                    if i > 0 {
-                        // flush previous batch
-                        render_pass.draw_indexed(indices, 0, batch_start..(i as u32));
-                    }
+        Ok(report)
+    }
+
+    /// The pipelines `pass` draws its primitives with.
+    fn pipeline_variant(pass: &GraphPass) -> PipelineVariant {
+        match pass.shading() {
+            PassShading::GBuffer => PipelineVariant::GBuffer,
+            _ => PipelineVariant::Color,
+        }
+    }
+
+    /// Assigns shadow maps to the graph's lights with `cast_shadows` (see
+    /// [`shadows::plan_shadows`]), makes room for them and collects the opaque shadow
+    /// casters of each shadow view.
+    ///
+    /// Returns the frame's shadows and one (drawables, report) per view; no shadows when
+    /// the graph has no shadow casters.
+    #[allow(clippy::type_complexity)]
+    fn prepare_shadows<'p>(
+        &mut self,
+        graph: &RenderGraph<'p>,
+        resources: &Resources,
+        status: FrameStatus,
+    ) -> (FrameShadows, Vec<(Vec<&'p Primitive>, RenderReport)>) {
+        let Some((primitives, camera)) = graph.shadow_casters() else {
+            return (FrameShadows::default(), Vec::new());
+        };
+        if !graph.lights().iter().any(|light| light.cast_shadows) {
+            return (FrameShadows::default(), Vec::new());
+        }
+
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let mut config = self.shadow_config;
+        config.resolution = config.resolution.clamp(1, max_dimension);
+        let columns =
+            shadows::atlas_columns(config.max_spot_lights.min(shadows::MAX_SPOT_SHADOWS as u32));
+        config.local_resolution = config.local_resolution.clamp(1, max_dimension / columns);
+
+        let casters = primitives
+            .iter()
+            .filter_map(|primitive| {
+                let bounds = resources
+                    .get_geometry(primitive.geometry())?
+                    .bounds(resources)?;
+                Some(bounds.sphere.transformed(&primitive.transform()))
+            })
+            .collect::<Vec<_>>();
+        let shadows = shadows::plan_shadows(graph.lights(), Some(camera), &config, &casters);
+
+        self.shadow_maps
+            .ensure(&self.device, &shadows, config.resolution);
+
+        let shadow_passes = shadows
+            .views
+            .iter()
+            .map(|view| {
+                let mut report = RenderReport {
+                    status,
+                    ..Default::default()
+                };
+                let drawables = self.collect_drawables(
+                    primitives,
+                    DrawFilter::Opaque,
+                    PipelineVariant::ShadowDepth,
+                    &view.camera,
+                    resources,
+                    &mut report,
+                );
+                (drawables, report)
+            })
+            .collect();
+        (shadows, shadow_passes)
+    }
+
+    /// Uploads the cube map of `environment` and refilters the environment maps from it
+    /// if it changed since the last frame.
+    fn prepare_environment(
+        &mut self,
+        environment: &Environment,
+        resources: &Resources,
+    ) -> Result<(), RendererError> {
+        let texture = resources
+            .get_texture(&environment.texture)
+            .ok_or(RendererError::MissingTexture)?;
+        let TextureKind::Cube { data, .. } = texture.kind() else {
+            return Err(RendererError::NotCubeTexture);
+        };
+        let version = (texture.ver(), data.ver());
+        let source =
+            self.textures
+                .prepare(&self.device, &self.queue, texture, &environment.texture)?;
+        self.environment_maps.update(
+            &self.device,
+            &self.queue,
+            environment.texture.raw(),
+            version,
+            source.view(),
+        );
+        Ok(())
+    }
+
+    /// Records the draw of `background` into `render_pass`, uploading its texture if needed.
+    fn draw_background(
+        &mut self,
+        render_pass: &mut wgpu::RenderPass,
+        background: &Background,
+        global_offset: u32,
+        target_formats: &TargetFormats,
+        resources: &Resources,
+        report: &mut RenderReport,
+    ) -> Result<(), RendererError> {
+        let kind = BackgroundKind::of(background);
+        let view = match background {
+            Background::Skybox(handle) | Background::Panorama(handle) => {
+                let texture = resources
+                    .get_texture(handle)
+                    .ok_or(RendererError::MissingTexture)?;
+                match (kind, texture.kind()) {
+                    (BackgroundKind::Skybox, TextureKind::Cube { .. })
+                    | (BackgroundKind::Panorama, TextureKind::D2 { .. }) => {}
+                    _ => return Err(RendererError::BackgroundTextureKind),
+                }
+                let internal_texture =
+                    self.textures
+                        .prepare(&self.device, &self.queue, texture, handle)?;
+                Some(internal_texture.view().clone())
+            }
+            Background::Gradient { .. } => None,
+        };
+
+        let bind_group =
+            self.backgrounds
+                .bind_group(&self.device, background, target_formats, view.as_ref());
+        let pipeline = self.backgrounds.pipeline(
+            &self.device,
+            self.global_bind_group.gpu_layout(),
+            target_formats,
+            kind,
+        );
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, self.global_bind_group.gpu_bind_group(), &[global_offset]);
+        render_pass.set_bind_group(1, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        report.pipeline_switches += 1;
+        report.bind_group_switches += 1;
+        report.draw_calls += 1;
+        Ok(())
+    }
+
+    /// Prepares the resources of `primitives` and returns the ones visible from `camera`
+    /// and accepted by `filter`, in draw order.
+    ///
+    /// Primitives that cannot be drawn with `variant` are listed in `report.skipped`, and
+    /// primitives outside the camera frustum are counted in `report.culled`.
+    fn collect_drawables<'p>(
+        &mut self,
+        primitives: &'p [Primitive],
+        filter: DrawFilter,
+        variant: PipelineVariant,
+        camera: &Camera,
+        resources: &Resources,
+        report: &mut RenderReport,
+    ) -> Vec<&'p Primitive> {
+        let gbuffer = variant == PipelineVariant::GBuffer;
+
+        let mut geometry_errors = HashMap::new();
+        let mut material_errors = HashMap::new();
+
+        let geometry_handles = primitives
+            .iter()
+            .map(|primitive| primitive.geometry())
+            .collect::<HashSet<_>>();
+
+        let material_handles = primitives
+            .iter()
+            .map(|primitive| primitive.material())
+            .collect::<HashSet<_>>();
+
+        for handle in geometry_handles {
+            if let Err(err) = self.prepare_geometry(handle, resources) {
+                geometry_errors.insert(handle.raw(), err);
+            }
+        }
+
+        for handle in material_handles {
+            if let Err(err) = self.prepare_material(handle, resources) {
+                material_errors.insert(handle.raw(), err);
+            }
+        }
+
+        let view = camera.view();
+        let frustum = camera.frustum();
+        let mut queue = RenderQueue::new();
+        let mut last_link: Option<((ResourceKey, ResourceKey), Result<(), RendererError>)> = None;
+
+        for (index, primitive) in primitives.iter().enumerate() {
+            let pair = (primitive.geometry().raw(), primitive.material().raw());
 
-                    let internal_geometry = self.geometries.get_internal_geometry(geometry_handle);
-                    let internal_material = self.materials.get_internal_material(material_handle);
+            let linked = match &last_link {
+                Some((last_pair, linked)) if *last_pair == pair => linked.clone(),
+                _ => {
+                    let linked = match (geometry_errors.get(&pair.0), material_errors.get(&pair.1))
+                    {
+                        (Some(err), _) | (None, Some(err)) => Err(err.clone()),
+                        (None, None) => self.link_primitive(primitive, resources),
+                    };
+                    last_link = Some((pair, linked.clone()));
+                    linked
+                }
+            };
+
+            let material = linked.and_then(|()| {
+                resources
+                    .get_material(primitive.material())
+                    .ok_or(RendererError::MissingMaterial)
+            });
+
+            let material = match material {
+                Ok(material) if !filter.accepts(material.render_state().blend) => continue,
+                Ok(material) if gbuffer && material.shader().gbuffer_entry_point().is_none() => {
+                    Err(RendererError::NoGBufferEntryPoint)
+                }
+                material => material,
+            };
+
+            match material {
+                Ok(_) if !Self::is_visible(primitive, &frustum, resources) => report.culled += 1,
+                Ok(material) => queue.push(primitive, material, &view),
+                Err(err) => report.skipped.push((index, err)),
+            }
+        }
+
+        queue.sort();
+        queue.primitives().collect()
+    }
+
+    /// Records the draws of `drawables` into `render_pass`.
+    ///
+    /// Consecutive primitives sharing geometry and material are drawn as one instanced batch,
+    /// with the pipelines of `variant`.
+    #[allow(clippy::too_many_arguments)]
+    fn draw(
+        &mut self,
+        render_pass: &mut wgpu::RenderPass,
+        drawables: &[&Primitive],
+        global_offset: u32,
+        target_formats: &TargetFormats,
+        variant: PipelineVariant,
+        resources: &Resources,
+        report: &mut RenderReport,
+    ) -> Result<(), RendererError> {
+        // shadow passes render into the shadow map, so they cannot bind it
+        let (global_layout, global_bind_group) = match variant {
+            PipelineVariant::ShadowDepth => (
+                self.global_bind_group.depth_gpu_layout(),
+                self.global_bind_group.depth_gpu_bind_group(),
+            ),
+            _ => (
+                self.global_bind_group.gpu_layout(),
+                self.global_bind_group.gpu_bind_group(),
+            ),
+        };
+        let primitive_bind_group = &mut self.primitive_bind_group;
 
-                    let geometry_desc = internal_geometry
-                        .get_desc(resources.get_material(material_handle).unwrap().shader());
+        render_pass.set_bind_group(0, global_bind_group, &[global_offset]);
+        render_pass.set_bind_group(1, primitive_bind_group.gpu_bind_group(), &[]);
 
+        // instance data of earlier passes precedes this pass's in the primitive buffer
+        let first_instance = primitive_bind_group.len() as u32;
+
+        let mut batch_start = 0u32;
+        let mut indices = 0..0;
+
+        let mut last_geometry_handle = None;
+        let mut last_material_handle = None;
+        let mut last_pipeline_key: Option<PipelineKey> = None;
+        let mut last_vertex_buffers: Option<(ResourceKey, u64)> = None;
+        let mut last_stencil_reference = None;
+
+        for (i, primitive) in drawables.iter().enumerate() {
+            let geometry_handle = primitive.geometry();
+            let material_handle = primitive.material();
+
+            let mut flag = 0u8;
+
+            if Some(geometry_handle.raw()) != last_geometry_handle {
+                flag |= GEOMETRY_CHANGED;
+                last_geometry_handle = Some(geometry_handle.raw());
+            }
+            if Some(material_handle.raw()) != last_material_handle {
+                flag |= MATERIAL_CHANGED;
+                last_material_handle = Some(material_handle.raw());
+            }
+            if Some(primitive.stencil_reference()) != last_stencil_reference {
+                flag |= STENCIL_CHANGED;
+                last_stencil_reference = Some(primitive.stencil_reference());
+            }
+
+            let geometry_changed = (flag & GEOMETRY_CHANGED) != 0;
+            let material_changed = (flag & MATERIAL_CHANGED) != 0;
+            let stencil_changed = (flag & STENCIL_CHANGED) != 0;
+
+            if geometry_changed || material_changed || stencil_changed {
+                if i > 0 {
+                    // flush previous batch
+                    render_pass.draw_indexed(
+                        indices.clone(),
+                        0,
+                        first_instance + batch_start..first_instance + i as u32,
+                    );
+                    report.draw_calls += 1;
+                }
+
+                let geometry = resources
+                    .get_geometry(geometry_handle)
+                    .ok_or(RendererError::MissingGeometry)?;
+                let material = resources
+                    .get_material(material_handle)
+                    .ok_or(RendererError::MissingMaterial)?;
+
+                let internal_geometry = self
+                    .geometries
+                    .get_internal_geometry(geometry_handle)
+                    .ok_or(RendererError::MissingGeometry)?;
+                let internal_material = self
+                    .materials
+                    .get_internal_material(material_handle)
+                    .ok_or(RendererError::MissingMaterial)?;
+
+                let geometry_desc = internal_geometry
+                    .get_desc(material.shader())
+                    .ok_or(RendererError::MissingGeometry)?;
+
+                let pipeline_key = PipelineKey {
+                    shader: material.shader().id(),
+                    vertex_layout: geometry_desc.layout_hash(),
+                    targets: target_formats.clone(),
+                    render_state: *material.render_state(),
+                    variant,
+                };
+
+                if last_pipeline_key.as_ref() != Some(&pipeline_key) {
                     let pipeline = self.pipelines.set_pipeline(
                         &self.device,
+                        &pipeline_key,
                         geometry_desc,
-                        material_handle,
+                        material.shader(),
                         &[
-                            global_bind_group.gpu_layout(),
+                            global_layout,
                             primitive_bind_group.gpu_layout(),
                             &internal_material.bind_group_layout,
                         ],
-                        resources,
                     );
-
                     render_pass.set_pipeline(pipeline);
+                    report.pipeline_switches += 1;
+                    last_pipeline_key = Some(pipeline_key);
+                }
+
+                if material_changed {
+                    render_pass.set_bind_group(2, &internal_material.bind_group, &[]);
+                    report.bind_group_switches += 1;
+                }
 
-                    if material_changed {
-                        render_pass.set_bind_group(2, &internal_material.bind_group, &[]);
+                // the bound vertex buffers depend on the geometry and the shader's vertex schema
+                let vertex_buffers = (
+                    geometry_handle.raw(),
+                    material.shader().vertex_schema_hash(),
+                );
+                if last_vertex_buffers != Some(vertex_buffers) {
+                    for (slot, (buffer_key, range)) in geometry_desc.entries().iter().enumerate() {
+                        let internal_buffer = self
+                            .buffers
+                            .get_internal_buffer_by_key(*buffer_key)
+                            .ok_or(RendererError::MissingBuffer)?;
+                        render_pass.set_vertex_buffer(
+                            slot as u32,
+                            internal_buffer.wgpu_buffer().slice(range.clone()),
+                        );
                     }
+                    report.vertex_buffer_switches += 1;
+                    last_vertex_buffers = Some(vertex_buffers);
+                }
 
-                    geometry_desc
-                        .entries()
-                        .iter()
-                        .enumerate()
-                        .for_each(|(i, entry)| {
-                            render_pass.set_vertex_buffer(
-                                i as u32,
-                                self.buffers
-                                    .get_internal_buffer_by_key(entry.0)
-                                    .wgpu_buffer()
-                                    .slice(entry.1.clone()),
-                            );
-                        });
-
-                    batch_start = i as u32;
-
-                    if let Some(index_buffer) =
-                        resources.get_geometry(geometry_handle).unwrap().indices()
-                    {
+                if stencil_changed {
+                    render_pass.set_stencil_reference(primitive.stencil_reference());
+                }
+
+                batch_start = i as u32;
+
+                if geometry_changed {
+                    if let Some(index_buffer) = geometry.indices() {
+                        let internal_buffer = self
+                            .buffers
+                            .get_internal_buffer(&index_buffer.buffer_slice.buffer)
+                            .ok_or(RendererError::MissingBuffer)?;
                         render_pass.set_index_buffer(
-                            self.buffers
-                                .get_internal_buffer(&index_buffer.buffer_slice.buffer)
+                            internal_buffer
                                 .wgpu_buffer()
                                 .slice(index_buffer.buffer_slice.range_u64()),
                             index_buffer.format,
@@ -309,22 +1088,150 @@ impl Renderer {
                         indices = 0..0;
                     }
                 }
-
-                primitive_bind_group.push_data(&primitive.transform());
             }
 
-            if !primitives.is_empty() {
-                // flush last batch
-                render_pass.draw_indexed(indices, 0, batch_start..(primitives.len() as u32));
-            }
+            primitive_bind_group.push_data(&primitive.transform());
+        }
 
-            global_bind_group.upload(&self.queue, camera);
-            primitive_bind_group.flush(&self.queue);
+        if !drawables.is_empty() {
+            // flush last batch
+            render_pass.draw_indexed(
+                indices,
+                0,
+                first_instance + batch_start..first_instance + drawables.len() as u32,
+            );
+            report.draw_calls += 1;
         }
 
-        self.queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
 
-        surface_textures.present();
+    /// Tests a primitive's world-space bounds against the camera frustum.
+    /// Primitives whose geometry has no bounds are always visible.
+    fn is_visible(primitive: &Primitive, frustum: &Frustum, resources: &Resources) -> bool {
+        let Some(bounds) = resources
+            .get_geometry(primitive.geometry())
+            .and_then(|geometry| geometry.bounds(resources))
+        else {
+            return true;
+        };
+        let transform = primitive.transform();
+        // the sphere test is cheap and rejects most objects; the box test tightens the rest
+        frustum.intersects_sphere(&bounds.sphere.transformed(&transform))
+            && frustum.intersects_box(&bounds.aabb.transformed(&transform))
+    }
+
+    /// Uploads the buffers of a geometry and creates its GPU-side record.
+    fn prepare_geometry(
+        &mut self,
+        handle: &GeometryHandle,
+        resources: &Resources,
+    ) -> Result<(), RendererError> {
+        let geometry = resources
+            .get_geometry(handle)
+            .ok_or(RendererError::MissingGeometry)?;
+
+        for buffer_handle in geometry.buffers() {
+            self.buffers
+                .prepare(&self.device, &self.queue, resources, buffer_handle)?;
+        }
+
+        self.geometries.prepare(geometry, handle)?;
+
+        Ok(())
+    }
+
+    /// Uploads the textures and samplers of a material and (re)builds its bind group.
+    fn prepare_material(
+        &mut self,
+        handle: &MaterialHandle,
+        resources: &Resources,
+    ) -> Result<(), RendererError> {
+        let material = resources
+            .get_material(handle)
+            .ok_or(RendererError::MissingMaterial)?;
+
+        for texture_handle in material.textures() {
+            let texture = resources
+                .get_texture(texture_handle)
+                .ok_or(RendererError::MissingTexture)?;
+            self.textures
+                .prepare(&self.device, &self.queue, texture, texture_handle)?;
+        }
+
+        for sampler in material.samplers() {
+            self.samplers.prepare(&self.device, *sampler);
+        }
+
+        self.materials.prepare(
+            &self.device,
+            &self.queue,
+            resources,
+            &self.textures,
+            &self.transients,
+            &self.samplers,
+            handle,
+        )?;
+
+        Ok(())
+    }
+
+    /// Resolves the vertex layout of a primitive's geometry for its material's shader.
+    fn link_primitive(
+        &mut self,
+        primitive: &Primitive,
+        resources: &Resources,
+    ) -> Result<(), RendererError> {
+        let geometry_handle = primitive.geometry();
+        let geometry = resources
+            .get_geometry(geometry_handle)
+            .ok_or(RendererError::MissingGeometry)?;
+        let material = resources
+            .get_material(primitive.material())
+            .ok_or(RendererError::MissingMaterial)?;
+
+        self.geometries
+            .link_shader(geometry, geometry_handle, material.shader())?;
+
+        Ok(())
+    }
+
+    /// Copies the pixels of a texture back into CPU memory.
+    ///
+    /// Blocks until the copy has finished. The texture must have `COPY_SRC` usage,
+    /// which `Texture::render_texture` and `RenderTargetBuilder::attach_color` set.
+    pub fn read_texture(
+        &mut self,
+        texture_handle: &TextureHandle,
+        resources: &Resources,
+    ) -> Result<TexturePixels, RendererError> {
+        let texture = resources
+            .get_texture(texture_handle)
+            .ok_or(RendererError::MissingTexture)?;
+
+        if let TextureKind::Surface { .. } = texture.kind() {
+            return Err(RendererError::SurfaceReadback);
+        }
+
+        let internal_texture =
+            self.textures
+                .prepare(&self.device, &self.queue, texture, texture_handle)?;
+
+        readback::read_texture(&self.device, &self.queue, internal_texture.texture())
+    }
+
+    /// Copies the color attachment at `index` of a render target back into CPU memory.
+    pub fn read_render_target(
+        &mut self,
+        target: &RenderTarget,
+        index: usize,
+        resources: &Resources,
+    ) -> Result<TexturePixels, RendererError> {
+        let color_attachment = target
+            .color_attachments()
+            .get(index)
+            .ok_or(RendererError::NoColorAttachment)?;
+        self.read_texture(&color_attachment.texture, resources)
     }
 
     pub fn destroy_texture_gpu(&mut self, _: ResourceKey) {
@@ -369,3 +1276,293 @@ impl Renderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::PerspectiveProjection;
+    use crate::geometry::Geometry;
+    use crate::graph::TransientTexture;
+    use crate::light::Light;
+    use crate::material::Material;
+    use crate::math::{Color3, Color4, Mat4, Quat, Vec3, Vec4};
+    use crate::shader::builtins::{pbr_shader, unlit_shader};
+    use crate::symbol;
+    use crate::target::RenderTargetBuilder;
+
+    #[test]
+    fn test_depth_only_passes_are_executed() {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let mut renderer = pollster::block_on(Renderer::new_headless(&instance)).unwrap();
+        let mut resources = Resources::default();
+
+        let target = RenderTargetBuilder::new()
+            .size(16, 16)
+            .attach_color(
+                TextureKind::Render {
+                    width: 16,
+                    height: 16,
+                },
+                wgpu::TextureFormat::Rgba8Unorm,
+            )
+            .build(&mut resources);
+        let geometry = Geometry::create_unit_cube(&mut resources);
+        let geometry = resources.insert_geometry(geometry);
+        let material = resources.insert_material(Material::from_shader(unlit_shader()));
+        let primitives = [Primitive::new(geometry, material)];
+        let mut camera = Camera::default();
+        camera.set_view(Mat4::look_at_rh(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::ZERO,
+            Vec3::Y,
+        ));
+        camera.set_projection(PerspectiveProjection::new(45.0, 1.0, 0.1, 100.0));
+
+        let mut graph = RenderGraph::new();
+        let depth = graph.create_transient(TransientTexture::new(
+            "depth",
+            16,
+            16,
+            wgpu::TextureFormat::Depth24Plus,
+        ));
+        graph.add_pass(
+            GraphPass::new("prepass", 16, 16)
+                .depth_stencil(depth, Operations::default(), Operations::default())
+                .draw(&primitives, &camera),
+        );
+        graph.add_pass(
+            GraphPass::new("main", 16, 16)
+                .color(
+                    &target.color_attachments()[0].texture,
+                    Operations::default(),
+                )
+                .depth_stencil(
+                    depth,
+                    Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Discard,
+                    },
+                    Operations::default(),
+                )
+                .draw(&primitives, &camera),
+        );
+
+        let report = renderer.execute(&graph, &resources).unwrap();
+        let (name, prepass) = &report.passes[0];
+        assert_eq!(name, "prepass");
+        assert_eq!(prepass.drawn, 1);
+        assert_eq!(prepass.draw_calls, 1);
+        assert_eq!(report.passes[1].0, "main");
+    }
+
+    #[test]
+    fn test_multisampled_passes_resolve_into_the_attachment() {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let mut renderer = pollster::block_on(Renderer::new_headless(&instance)).unwrap();
+        let mut resources = Resources::default();
+
+        let target = RenderTargetBuilder::new()
+            .size(16, 16)
+            .attach_color(
+                TextureKind::Render {
+                    width: 16,
+                    height: 16,
+                },
+                wgpu::TextureFormat::Rgba8Unorm,
+            )
+            .build(&mut resources);
+        let geometry = Geometry::create_unit_quad(&mut resources);
+        let geometry = resources.insert_geometry(geometry);
+        let mut material = Material::from_shader(unlit_shader());
+        material.set_param_vec4f(symbol!("albedo_factor"), Vec4::ONE);
+        let material = resources.insert_material(material);
+        let primitives = [Primitive::new(geometry, material)];
+        let mut camera = Camera::default();
+        camera.set_view(Mat4::look_at_rh(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::ZERO,
+            Vec3::Y,
+        ));
+        camera.set_projection(PerspectiveProjection::new(45.0, 1.0, 0.1, 100.0));
+
+        // more samples than are guaranteed, so the count may fall back
+        let mut graph = RenderGraph::new();
+        graph.add_pass(
+            GraphPass::new("msaa", 16, 16)
+                .with_sample_count(8)
+                .color(
+                    &target.color_attachments()[0].texture,
+                    Operations {
+                        load: LoadOp::Clear(Color4::new(1.0, 0.0, 0.0, 1.0)),
+                        store: StoreOp::Store,
+                    },
+                )
+                .draw(&primitives, &camera),
+        );
+
+        let report = renderer.execute(&graph, &resources).unwrap();
+        assert_eq!(report.passes[0].1.drawn, 1);
+
+        let pixels = renderer.read_render_target(&target, 0, &resources).unwrap();
+        let pixel = |x: usize, y: usize| &pixels.bytes[(y * 16 + x) * 4..][..4];
+        assert_eq!(pixel(8, 8), &[255, 255, 255, 255]);
+        assert_eq!(pixel(0, 0), &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_point_shadows_fall_behind_casters_in_every_direction() {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let mut renderer = pollster::block_on(Renderer::new_headless(&instance)).unwrap();
+        let mut resources = Resources::default();
+
+        const SIZE: u32 = 64;
+        let target = RenderTargetBuilder::new()
+            .size(SIZE, SIZE)
+            .attach_color(
+                TextureKind::Render {
+                    width: SIZE,
+                    height: SIZE,
+                },
+                wgpu::TextureFormat::Rgba8Unorm,
+            )
+            .build(&mut resources);
+        let cube = Geometry::create_unit_cube(&mut resources);
+        let cube = resources.insert_geometry(cube);
+        let quad = Geometry::create_unit_quad(&mut resources);
+        let quad = resources.insert_geometry(quad);
+        let mut material = Material::from_shader(pbr_shader());
+        material
+            .set_param_vec4f(symbol!("albedo_factor"), Vec4::ONE)
+            .set_param_f(symbol!("roughness"), 1.0);
+        let material = resources.insert_material(material);
+        let lights = [Light::point(Vec3::ZERO, Color3::new(1.0, 1.0, 1.0), 20.0)
+            .with_range(20.0)
+            .with_shadows()];
+
+        for (axis, u, v) in [
+            (Vec3::X, Vec3::Y, Vec3::Z),
+            (Vec3::NEG_X, Vec3::Y, Vec3::Z),
+            (Vec3::Y, Vec3::Z, Vec3::X),
+            (Vec3::NEG_Y, Vec3::Z, Vec3::X),
+            (Vec3::Z, Vec3::X, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::X, Vec3::Y),
+        ] {
+            // a caster off the axis, so that flipped or swapped faces move its shadow
+            let mut caster = Primitive::new(cube.clone(), material.clone());
+            caster.set_transform(
+                Mat4::from_translation(axis * 1.5 + (u + v) * 0.5)
+                    * Mat4::from_scale(Vec3::splat(0.5)),
+            );
+            let mut receiver = Primitive::new(quad.clone(), material.clone());
+            receiver.set_transform(Mat4::from_scale_rotation_translation(
+                Vec3::splat(3.0),
+                Quat::from_rotation_arc(Vec3::Z, -axis),
+                axis * 4.0,
+            ));
+
+            let mut camera = Camera::default();
+            camera.set_view(Mat4::look_at_rh(
+                axis * 0.5 - (u + v) * 1.5,
+                axis * 4.0,
+                axis,
+            ));
+            camera.set_projection(PerspectiveProjection::new(90.0, 1.0, 0.1, 100.0));
+
+            renderer
+                .render(
+                    &[caster, receiver],
+                    &lights,
+                    &camera,
+                    &target,
+                    None,
+                    &resources,
+                )
+                .unwrap();
+            let pixels = renderer.read_render_target(&target, 0, &resources).unwrap();
+            let brightness = |offset: Vec3| {
+                let ndc = camera.view_projection().project_point3(axis * 4.0 + offset);
+                let x = ((ndc.x * 0.5 + 0.5) * SIZE as f32) as usize;
+                let y = ((0.5 - ndc.y * 0.5) * SIZE as f32) as usize;
+                pixels.bytes[(y * SIZE as usize + x) * 4]
+            };
+
+            let shadow = (u + v) * 4.0 / 3.0;
+            let mirrored = [-shadow, (u - v) * 4.0 / 3.0, (v - u) * 4.0 / 3.0];
+            assert!(brightness(shadow) < 8, "{axis}: {}", brightness(shadow));
+            for offset in mirrored {
+                assert!(
+                    brightness(offset) > 32,
+                    "{axis} {offset}: {}",
+                    brightness(offset)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_passes_sample_transients_of_earlier_passes() {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let mut renderer = pollster::block_on(Renderer::new_headless(&instance)).unwrap();
+        let mut resources = Resources::default();
+
+        let target = RenderTargetBuilder::new()
+            .size(16, 16)
+            .attach_color(
+                TextureKind::Render {
+                    width: 16,
+                    height: 16,
+                },
+                wgpu::TextureFormat::Rgba8Unorm,
+            )
+            .build(&mut resources);
+
+        let mut graph = RenderGraph::new();
+        let scene = graph.create_transient(TransientTexture::new(
+            "scene",
+            16,
+            16,
+            wgpu::TextureFormat::Rgba8Unorm,
+        ));
+
+        let geometry = Geometry::create_unit_quad(&mut resources);
+        let geometry = resources.insert_geometry(geometry);
+        let mut material = Material::from_shader(unlit_shader());
+        material
+            .set_param_vec4f(symbol!("albedo_factor"), Vec4::ONE)
+            .set_param_transient(symbol!("albedo_texture"), Some(scene));
+        let material = resources.insert_material(material);
+        let primitives = [Primitive::new(geometry, material)];
+        let mut camera = Camera::default();
+        camera.set_view(Mat4::look_at_rh(
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::ZERO,
+            Vec3::Y,
+        ));
+        camera.set_projection(PerspectiveProjection::new(90.0, 1.0, 0.1, 100.0));
+
+        graph.add_pass(GraphPass::new("scene", 16, 16).color(
+            scene,
+            Operations {
+                load: LoadOp::Clear(Color4::new(0.0, 1.0, 0.0, 1.0)),
+                store: StoreOp::Store,
+            },
+        ));
+        graph.add_pass(
+            GraphPass::new("post", 16, 16)
+                .color(
+                    &target.color_attachments()[0].texture,
+                    Operations::default(),
+                )
+                .read(scene)
+                .draw(&primitives, &camera),
+        );
+
+        let report = renderer.execute(&graph, &resources).unwrap();
+        assert!(report.culled_passes.is_empty());
+        assert_eq!(report.passes[1].1.drawn, 1);
+
+        let pixels = renderer.read_render_target(&target, 0, &resources).unwrap();
+        let center = ((8 * 16 + 8) * 4) as usize;
+        assert_eq!(&pixels.bytes[center..center + 4], &[0, 255, 0, 255]);
+    }
+}
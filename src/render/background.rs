@@ -0,0 +1,294 @@
+use super::pipelines::TargetFormats;
+use crate::background::Background;
+use crate::math::Color4;
+use std::collections::{HashMap, HashSet};
+use wgpu::util::DeviceExt;
+
+/// Per-draw background data, matching `BackgroundParams` in `shader/wgsl/background.wgsl`.
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct BackgroundParams {
+    top: Color4,
+    bottom: Color4,
+    far_depth: f32,
+    _padding: [f32; 3],
+}
+
+/// The kinds of [`Background`], each drawn by its own entry point of the background shader.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BackgroundKind {
+    Skybox,
+    Panorama,
+    Gradient,
+}
+
+impl BackgroundKind {
+    pub fn of(background: &Background) -> Self {
+        match background {
+            Background::Skybox(_) => BackgroundKind::Skybox,
+            Background::Panorama(_) => BackgroundKind::Panorama,
+            Background::Gradient { .. } => BackgroundKind::Gradient,
+        }
+    }
+
+    fn entry_point(&self) -> &'static str {
+        match self {
+            BackgroundKind::Skybox => "fs_skybox",
+            BackgroundKind::Panorama => "fs_panorama",
+            BackgroundKind::Gradient => "fs_gradient",
+        }
+    }
+
+    /// Binding and view dimension of the sampled texture, if any.
+    fn texture_binding(&self) -> Option<(u32, wgpu::TextureViewDimension)> {
+        match self {
+            BackgroundKind::Skybox => Some((1, wgpu::TextureViewDimension::Cube)),
+            BackgroundKind::Panorama => Some((2, wgpu::TextureViewDimension::D2)),
+            BackgroundKind::Gradient => None,
+        }
+    }
+}
+
+/// A background bind group: its kind, texture view and the bits of its params.
+type BindGroupKey = (BackgroundKind, Option<wgpu::TextureView>, [u32; 12]);
+
+const PARAMS_BINDING: u32 = 0;
+const SAMPLER_BINDING: u32 = 3;
+
+/// Full-screen backgrounds drawn at the far plane of a pass.
+///
+/// Owns the background shader, one bind group layout per [`BackgroundKind`] and one
+/// pipeline per kind and kind of target. Bind groups are cached by texture view and
+/// parameters until a frame does not draw them.
+pub struct Backgrounds {
+    layouts: HashMap<BackgroundKind, wgpu::BindGroupLayout>,
+    module: wgpu::ShaderModule,
+    sampler: wgpu::Sampler,
+    pipelines: HashMap<(TargetFormats, BackgroundKind), wgpu::RenderPipeline>,
+    bind_groups: HashMap<BindGroupKey, wgpu::BindGroup>,
+    /// Bind groups drawn since the last [`Backgrounds::end_frame`].
+    bind_groups_used: HashSet<BindGroupKey>,
+}
+
+impl Backgrounds {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layouts = [
+            BackgroundKind::Skybox,
+            BackgroundKind::Panorama,
+            BackgroundKind::Gradient,
+        ]
+        .into_iter()
+        .map(|kind| {
+            let mut entries = vec![wgpu::BindGroupLayoutEntry {
+                binding: PARAMS_BINDING,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(size_of::<BackgroundParams>() as u64),
+                },
+                count: None,
+            }];
+            if let Some((binding, view_dimension)) = kind.texture_binding() {
+                entries.push(wgpu::BindGroupLayoutEntry {
+                    binding,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension,
+                        multisampled: false,
+                    },
+                    count: None,
+                });
+                entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: SAMPLER_BINDING,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                });
+            }
+            let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("background_bind_group_layout"),
+                entries: &entries,
+            });
+            (kind, layout)
+        })
+        .collect();
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Background Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shader/wgsl/background.wgsl").into()),
+        });
+
+        // panoramas wrap around the horizon
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Background Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            layouts,
+            module,
+            sampler,
+            pipelines: HashMap::new(),
+            bind_groups: HashMap::new(),
+            bind_groups_used: HashSet::new(),
+        }
+    }
+
+    /// Bind group of `background` drawn into a pass with `targets`; `view` is the view
+    /// of its texture, if it has one.
+    pub fn bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        background: &Background,
+        targets: &TargetFormats,
+        view: Option<&wgpu::TextureView>,
+    ) -> wgpu::BindGroup {
+        let kind = BackgroundKind::of(background);
+        let (top, bottom) = match background {
+            Background::Gradient { top, bottom } => (*top, *bottom),
+            _ => Default::default(),
+        };
+        let params = BackgroundParams {
+            top,
+            bottom,
+            far_depth: if targets.reverse_z { 0.0 } else { 1.0 },
+            _padding: [0.0; 3],
+        };
+
+        let key = (kind, view.cloned(), bytemuck::cast(params));
+        self.bind_groups_used.insert(key.clone());
+        if let Some(bind_group) = self.bind_groups.get(&key) {
+            return bind_group.clone();
+        }
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Background Params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: PARAMS_BINDING,
+            resource: buffer.as_entire_binding(),
+        }];
+        if let (Some((binding, _)), Some(view)) = (kind.texture_binding(), view) {
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: SAMPLER_BINDING,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("background_bind_group"),
+            layout: &self.layouts[&kind],
+            entries: &entries,
+        });
+        self.bind_groups.insert(key, bind_group.clone());
+        bind_group
+    }
+
+    /// Drops the bind groups no pass drew this frame, e.g. of replaced textures
+    /// or of gradients whose colors changed.
+    pub fn end_frame(&mut self) {
+        self.bind_groups
+            .retain(|key, _| self.bind_groups_used.contains(key));
+        self.bind_groups_used.clear();
+    }
+
+    /// The pipeline drawing backgrounds of `kind` into a pass with `targets`.
+    ///
+    /// Only the first color attachment is written. The depth attachment, if any, is tested
+    /// but not written, so only pixels still at the far plane are covered.
+    pub fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        global_layout: &wgpu::BindGroupLayout,
+        targets: &TargetFormats,
+        kind: BackgroundKind,
+    ) -> &wgpu::RenderPipeline {
+        let key = (targets.clone(), kind);
+        if !self.pipelines.contains_key(&key) {
+            let pipeline = self.create_pipeline(device, global_layout, targets, kind);
+            self.pipelines.insert(key.clone(), pipeline);
+        }
+        &self.pipelines[&key]
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        global_layout: &wgpu::BindGroupLayout,
+        targets: &TargetFormats,
+        kind: BackgroundKind,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            bind_group_layouts: &[global_layout, &self.layouts[&kind]],
+            push_constant_ranges: &[],
+        });
+
+        let color_targets = targets
+            .color_targets
+            .iter()
+            .enumerate()
+            .map(|(index, target)| {
+                Some(wgpu::ColorTargetState {
+                    format: target.format,
+                    blend: None,
+                    write_mask: if index == 0 {
+                        target.write_mask
+                    } else {
+                        wgpu::ColorWrites::empty()
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Background Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.module,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.module,
+                entry_point: Some(kind.entry_point()),
+                compilation_options: Default::default(),
+                targets: &color_targets,
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: targets.depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: if targets.reverse_z {
+                    wgpu::CompareFunction::GreaterEqual
+                } else {
+                    wgpu::CompareFunction::LessEqual
+                },
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: targets.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        println!("Created new background pipeline");
+
+        pipeline
+    }
+}
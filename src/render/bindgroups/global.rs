@@ -1,48 +1,575 @@
-use crate::camera::Camera;
+use super::super::config::ShadowConfig;
+use super::super::environment::{EnvironmentMaps, SPECULAR_MIPS};
+use super::super::shadows::{
+    FrameShadows, LocalShadow, MAX_CASCADES, MAX_POINT_SHADOWS, MAX_SPOT_SHADOWS, ShadowMaps,
+};
+use crate::camera::{Camera, Viewport};
+use crate::light::{Light, LightKind};
+use bytemuck::Zeroable;
 
+/// Per-pass data, as declared by shaders (a prefix is enough, e.g. just `view_proj`):
+///
+/// ```wgsl
+/// struct CameraUniform {
+///     view_proj: mat4x4f,
+///     inverse_view_proj: mat4x4f,
+///     position: vec4f,
+///     viewport: vec4f, // x, y, width, height in pixels
+/// };
+/// ```
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct GlobalData {
+    view_proj: [[f32; 4]; 4],
+    inverse_view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    viewport: [f32; 4],
+}
+
+const GLOBAL_DATA_SIZE: u64 = size_of::<GlobalData>() as u64;
+
+/// One element of the light buffer, matching `Light` in `shader/wgsl/lights.wgsl`.
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct LightData {
+    position: [f32; 3],
+    kind: u32,
+    direction: [f32; 3],
+    range: f32,
+    radiance: [f32; 3],
+    inner_cone_cos: f32,
+    outer_cone_cos: f32,
+    shadow: i32,
+    _padding: [f32; 2],
+}
+
+impl From<&Light> for LightData {
+    fn from(light: &Light) -> Self {
+        let (kind, inner_cone_cos, outer_cone_cos) = match light.kind {
+            LightKind::Directional => (0, 0.0, 0.0),
+            LightKind::Point => (1, 0.0, 0.0),
+            LightKind::Spot {
+                inner_cone_deg,
+                outer_cone_deg,
+            } => (
+                2,
+                inner_cone_deg.to_radians().cos(),
+                outer_cone_deg.to_radians().cos(),
+            ),
+        };
+        let color = light.color;
+        Self {
+            position: light.position.to_array(),
+            kind,
+            direction: light.direction.normalize_or_zero().to_array(),
+            range: light.range.unwrap_or(0.0),
+            radiance: [
+                color.r * light.intensity,
+                color.g * light.intensity,
+                color.b * light.intensity,
+            ],
+            inner_cone_cos,
+            outer_cone_cos,
+            shadow: -1,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// Shadow map data, matching `ShadowUniform` in `shader/wgsl/shadows.wgsl`.
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct ShadowData {
+    cascades: [[[f32; 4]; 4]; MAX_CASCADES],
+    spot_matrices: [[[f32; 4]; 4]; MAX_SPOT_SHADOWS],
+    point_params: [[f32; 4]; MAX_POINT_SHADOWS],
+    spot_params: [[f32; 4]; MAX_SPOT_SHADOWS],
+    texel_sizes: [f32; MAX_CASCADES],
+    cascade_count: u32,
+    depth_bias: f32,
+    normal_bias: f32,
+    filter_radius: u32,
+    local_resolution: f32,
+    atlas_columns: u32,
+    _padding: [f32; 2],
+}
+
+/// Near, far and the tangent of half the field of view.
+fn local_params(shadow: &LocalShadow) -> [f32; 4] {
+    [shadow.near, shadow.far, shadow.tan_half_fov, 0.0]
+}
+
+impl ShadowData {
+    fn new(shadows: &FrameShadows, config: &ShadowConfig) -> Self {
+        let mut data = Self::zeroed();
+        data.cascade_count = shadows.cascades.len().min(MAX_CASCADES) as u32;
+        data.depth_bias = config.depth_bias;
+        data.normal_bias = config.normal_bias;
+        data.filter_radius = config.filter.radius();
+        data.local_resolution = shadows.local_resolution as f32;
+        data.atlas_columns = shadows.atlas_columns;
+        for (i, cascade) in shadows.cascades.iter().take(MAX_CASCADES).enumerate() {
+            data.cascades[i] = cascade.camera.view_projection().to_cols_array_2d();
+            data.texel_sizes[i] = cascade.texel_size;
+        }
+        for (i, point) in shadows.points.iter().take(MAX_POINT_SHADOWS).enumerate() {
+            data.point_params[i] = local_params(point);
+        }
+        for (i, spot) in shadows.spots.iter().take(MAX_SPOT_SHADOWS).enumerate() {
+            data.spot_matrices[i] = spot.view_projection.to_cols_array_2d();
+            data.spot_params[i] = local_params(spot);
+        }
+        data
+    }
+}
+
+const SHADOW_DATA_SIZE: u64 = size_of::<ShadowData>() as u64;
+
+/// Environment lighting data, matching `EnvironmentUniform` in `shader/wgsl/environment.wgsl`.
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct EnvironmentData {
+    intensity: f32,
+    specular_max_lod: f32,
+    _padding: [f32; 2],
+}
+
+const ENVIRONMENT_DATA_SIZE: u64 = size_of::<EnvironmentData>() as u64;
+
+impl EnvironmentData {
+    fn new(intensity: f32) -> Self {
+        Self {
+            intensity,
+            specular_max_lod: (SPECULAR_MIPS - 1) as f32,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// The light count precedes the lights, padded to the alignment of `Light`.
+const LIGHT_HEADER_SIZE: u64 = 16;
+const LIGHT_DATA_SIZE: u64 = size_of::<LightData>() as u64;
+
+fn create_global_buffer(device: &wgpu::Device, stride: u64, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Global Buffer"),
+        size: stride * capacity as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_light_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Light Buffer"),
+        size: LIGHT_HEADER_SIZE + LIGHT_DATA_SIZE * capacity as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn camera_layout_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size: wgpu::BufferSize::new(GLOBAL_DATA_SIZE),
+        },
+        count: None,
+    }
+}
+
+fn light_layout_entry() -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(LIGHT_HEADER_SIZE + LIGHT_DATA_SIZE),
+        },
+        count: None,
+    }
+}
+
+fn environment_layout_entry(
+    binding: u32,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn shadow_views(shadow_maps: &ShadowMaps) -> [wgpu::TextureView; 3] {
+    [
+        shadow_maps.cascades.view().clone(),
+        shadow_maps.points.view().clone(),
+        shadow_maps.atlas.view().clone(),
+    ]
+}
+
+fn camera_entry(buffer: &wgpu::Buffer) -> wgpu::BindGroupEntry<'_> {
+    wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(GLOBAL_DATA_SIZE),
+        }),
+    }
+}
+
+/// A uniform buffer with the textures and sampler it describes: the shadow maps,
+/// or the environment maps.
+struct TextureBindings {
+    buffer: wgpu::Buffer,
+    views: [wgpu::TextureView; 3],
+    sampler: wgpu::Sampler,
+}
+
+fn create_global_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    light_buffer: &wgpu::Buffer,
+    shadows: &TextureBindings,
+    environment: &TextureBindings,
+) -> wgpu::BindGroup {
+    let [cascade_view, point_view, atlas_view] = &shadows.views;
+    let [irradiance_view, specular_view, brdf_lut_view] = &environment.views;
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            camera_entry(buffer),
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: light_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: shadows.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(cascade_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(&shadows.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(point_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(atlas_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: environment.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::TextureView(irradiance_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 9,
+                resource: wgpu::BindingResource::TextureView(specular_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 10,
+                resource: wgpu::BindingResource::TextureView(brdf_lut_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 11,
+                resource: wgpu::BindingResource::Sampler(&environment.sampler),
+            },
+        ],
+        label: Some("camera_bind_group"),
+    })
+}
+
+fn create_depth_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    light_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            camera_entry(buffer),
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: light_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("depth_camera_bind_group"),
+    })
+}
+
+/// Global data: per-pass camera data, one slot per pass in a dynamic-offset uniform buffer,
+/// the frame's lights in a storage buffer, the shadow maps with their data, and the
+/// environment maps with their intensity.
+///
+/// Shadow passes render into the shadow maps, so they bind a second group holding
+/// only the camera and lights (`depth_gpu_layout`, `depth_gpu_bind_group`).
 pub struct GlobalBindGroup {
+    stride: u64,
+    capacity: usize,
+    cpu_memory: Vec<u8>,
     gpu_buffer: wgpu::Buffer,
+    light_capacity: usize,
+    light_memory: Vec<u8>,
+    light_buffer: wgpu::Buffer,
+    shadow_data: ShadowData,
+    shadows: TextureBindings,
+    shadow_generation: u64,
+    environment_data: EnvironmentData,
+    environment: TextureBindings,
     gpu_layout: wgpu::BindGroupLayout,
     gpu_bind_group: wgpu::BindGroup,
+    depth_gpu_layout: wgpu::BindGroupLayout,
+    depth_gpu_bind_group: wgpu::BindGroup,
 }
 
 impl GlobalBindGroup {
-    pub fn new(device: &wgpu::Device) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        shadow_maps: &ShadowMaps,
+        shadow_sampler: &wgpu::Sampler,
+        environment_maps: &EnvironmentMaps,
+    ) -> Self {
         let gpu_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                camera_layout_entry(),
+                light_layout_entry(),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(SHADOW_DATA_SIZE),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::CubeArray,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(ENVIRONMENT_DATA_SIZE),
+                    },
+                    count: None,
+                },
+                environment_layout_entry(8, wgpu::TextureViewDimension::Cube),
+                environment_layout_entry(9, wgpu::TextureViewDimension::Cube),
+                environment_layout_entry(10, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 11,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
             label: Some("camera_bind_group_layout"),
         });
+        let depth_gpu_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[camera_layout_entry(), light_layout_entry()],
+            label: Some("depth_camera_bind_group_layout"),
+        });
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = GLOBAL_DATA_SIZE.div_ceil(alignment) * alignment;
+        let capacity = 1;
+        let light_capacity = 1;
 
-        let gpu_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Global Buffer"),
-            size: 64_u64, // size of Mat4,
+        let gpu_buffer = create_global_buffer(device, stride, capacity);
+        let light_buffer = create_light_buffer(device, light_capacity);
+        let shadow_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Buffer"),
+            size: SHADOW_DATA_SIZE,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let gpu_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &gpu_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: gpu_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
+        let shadows = TextureBindings {
+            buffer: shadow_buffer,
+            views: shadow_views(shadow_maps),
+            sampler: shadow_sampler.clone(),
+        };
+        let environment = TextureBindings {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Environment Buffer"),
+                size: ENVIRONMENT_DATA_SIZE,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            views: [
+                environment_maps.irradiance_view().clone(),
+                environment_maps.specular_view().clone(),
+                environment_maps.brdf_lut_view().clone(),
+            ],
+            sampler: environment_maps.sampler().clone(),
+        };
+        let gpu_bind_group = create_global_bind_group(
+            device,
+            &gpu_layout,
+            &gpu_buffer,
+            &light_buffer,
+            &shadows,
+            &environment,
+        );
+        let depth_gpu_bind_group =
+            create_depth_bind_group(device, &depth_gpu_layout, &gpu_buffer, &light_buffer);
 
         Self {
-            gpu_layout,
+            stride,
+            capacity,
+            cpu_memory: Vec::new(),
             gpu_buffer,
+            light_capacity,
+            light_memory: Vec::new(),
+            light_buffer,
+            shadow_data: ShadowData::new(&FrameShadows::default(), &ShadowConfig::default()),
+            shadows,
+            shadow_generation: shadow_maps.generation(),
+            environment_data: EnvironmentData::new(0.0),
+            environment,
+            gpu_layout,
             gpu_bind_group,
+            depth_gpu_layout,
+            depth_gpu_bind_group,
+        }
+    }
+
+    fn create_bind_groups(&mut self, device: &wgpu::Device) {
+        self.gpu_bind_group = create_global_bind_group(
+            device,
+            &self.gpu_layout,
+            &self.gpu_buffer,
+            &self.light_buffer,
+            &self.shadows,
+            &self.environment,
+        );
+        self.depth_gpu_bind_group = create_depth_bind_group(
+            device,
+            &self.depth_gpu_layout,
+            &self.gpu_buffer,
+            &self.light_buffer,
+        );
+    }
+
+    /// Clears the slots, makes room for `needed` of them and stages `lights`.
+    ///
+    /// `shadow_slots` holds the shadow slot of every light, see `FrameShadows::light_slots`.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        needed: usize,
+        lights: &[Light],
+        shadow_slots: &[i32],
+    ) -> &mut Self {
+        self.cpu_memory.clear();
+
+        self.light_memory.clear();
+        self.light_memory
+            .extend_from_slice(bytemuck::bytes_of(&(lights.len() as u32)));
+        self.light_memory.resize(LIGHT_HEADER_SIZE as usize, 0);
+        for (index, light) in lights.iter().enumerate() {
+            let mut data = LightData::from(light);
+            data.shadow = shadow_slots.get(index).copied().unwrap_or(-1);
+            self.light_memory
+                .extend_from_slice(bytemuck::bytes_of(&data));
+        }
+
+        if needed <= self.capacity && lights.len() <= self.light_capacity {
+            return self;
+        }
+
+        if needed > self.capacity {
+            self.capacity = needed.next_power_of_two();
+            self.gpu_buffer = create_global_buffer(device, self.stride, self.capacity);
         }
+        if lights.len() > self.light_capacity {
+            self.light_capacity = lights.len().next_power_of_two();
+            self.light_buffer = create_light_buffer(device, self.light_capacity);
+        }
+        self.create_bind_groups(device);
+
+        self
+    }
+
+    /// Stages the `shadows` of this frame, and rebinds `shadow_maps` if one was reallocated.
+    pub fn set_shadows(
+        &mut self,
+        device: &wgpu::Device,
+        shadows: &FrameShadows,
+        config: &ShadowConfig,
+        shadow_maps: &ShadowMaps,
+    ) -> &mut Self {
+        self.shadow_data = ShadowData::new(shadows, config);
+        if shadow_maps.generation() != self.shadow_generation {
+            self.shadows.views = shadow_views(shadow_maps);
+            self.shadow_generation = shadow_maps.generation();
+            self.create_bind_groups(device);
+        }
+        self
+    }
+
+    /// Stages the intensity of the environment maps, 0 for no environment lighting.
+    pub fn set_environment(&mut self, intensity: f32) -> &mut Self {
+        self.environment_data = EnvironmentData::new(intensity);
+        self
     }
 
     pub fn gpu_layout(&self) -> &wgpu::BindGroupLayout {
@@ -53,9 +580,84 @@ impl GlobalBindGroup {
         &self.gpu_bind_group
     }
 
-    pub fn upload(&self, queue: &wgpu::Queue, camera: &Camera) -> &Self {
-        let data = camera.view_projection().to_cols_array_2d();
-        queue.write_buffer(&self.gpu_buffer, 0, bytemuck::bytes_of(&data));
-        self
+    /// Layout of the group bound by shadow passes, without the shadow map.
+    pub fn depth_gpu_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.depth_gpu_layout
+    }
+
+    pub fn depth_gpu_bind_group(&self) -> &wgpu::BindGroup {
+        &self.depth_gpu_bind_group
+    }
+
+    /// Appends the data of `camera` drawing into `viewport` and returns the dynamic offset of its slot.
+    pub fn push_camera(&mut self, camera: &Camera, viewport: &Viewport) -> u32 {
+        let offset = self.cpu_memory.len();
+        let view_projection = camera.view_projection();
+        let data = GlobalData {
+            view_proj: view_projection.to_cols_array_2d(),
+            inverse_view_proj: view_projection.inverse().to_cols_array_2d(),
+            camera_position: camera.transform().w_axis.to_array(),
+            viewport: [viewport.x, viewport.y, viewport.width, viewport.height],
+        };
+        self.cpu_memory.extend_from_slice(bytemuck::bytes_of(&data));
+        self.cpu_memory.resize(offset + self.stride as usize, 0);
+        offset as u32
+    }
+
+    pub fn flush(&self, queue: &wgpu::Queue) {
+        if !self.cpu_memory.is_empty() {
+            queue.write_buffer(&self.gpu_buffer, 0, &self.cpu_memory);
+        }
+        queue.write_buffer(&self.light_buffer, 0, &self.light_memory);
+        queue.write_buffer(
+            &self.shadows.buffer,
+            0,
+            bytemuck::bytes_of(&self.shadow_data),
+        );
+        queue.write_buffer(
+            &self.environment.buffer,
+            0,
+            bytemuck::bytes_of(&self.environment_data),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Color3, Vec3};
+
+    #[test]
+    fn test_light_data_matches_wgsl_layout() {
+        assert_eq!(LIGHT_DATA_SIZE, 64);
+
+        let light = Light::spot(
+            Vec3::ONE,
+            Vec3::new(0.0, -2.0, 0.0),
+            Color3::new(1.0, 0.5, 0.0),
+            2.0,
+            0.0,
+            60.0,
+        )
+        .with_range(5.0);
+        let data = LightData::from(&light);
+        assert_eq!(data.kind, 2);
+        assert_eq!(data.direction, [0.0, -1.0, 0.0]);
+        assert_eq!(data.radiance, [2.0, 1.0, 0.0]);
+        assert_eq!(data.range, 5.0);
+        assert_eq!(data.inner_cone_cos, 1.0);
+        assert!((data.outer_cone_cos - 0.5).abs() < 1e-6);
+        assert_eq!(data.shadow, -1);
+    }
+
+    #[test]
+    fn test_shadow_data_matches_wgsl_layout() {
+        assert_eq!(SHADOW_DATA_SIZE, 1712);
+        assert_eq!(std::mem::offset_of!(ShadowData, spot_matrices), 256);
+        assert_eq!(std::mem::offset_of!(ShadowData, point_params), 1280);
+        assert_eq!(std::mem::offset_of!(ShadowData, spot_params), 1408);
+        assert_eq!(std::mem::offset_of!(ShadowData, texel_sizes), 1664);
+        assert_eq!(std::mem::offset_of!(ShadowData, cascade_count), 1680);
+        assert_eq!(std::mem::offset_of!(ShadowData, local_resolution), 1696);
     }
 }
@@ -53,7 +53,7 @@ impl PrimitiveBindGroup {
         Self {
             capacity: initial_capacity,
             cpu_memory: Vec::with_capacity(initial_capacity),
-            gpu_buffer: gpu_buffer,
+            gpu_buffer,
             gpu_layout,
             gpu_bind_group,
         }
@@ -91,6 +91,12 @@ impl PrimitiveBindGroup {
         &self.gpu_bind_group
     }
 
+    /// Number of instances pushed since the last `prepare`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cpu_memory.len()
+    }
+
     pub fn push_data(&mut self, matrix: &Mat4) {
         self.cpu_memory.push(*matrix);
     }
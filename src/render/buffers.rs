@@ -1,3 +1,4 @@
+use super::RendererError;
 use crate::{BufferHandle, ResourceKey, Resources};
 use slotmap::SecondaryMap;
 use wgpu::util::DeviceExt;
@@ -30,15 +31,15 @@ impl Buffers {
         queue: &wgpu::Queue,
         resources: &Resources,
         handle: &BufferHandle,
-    ) -> &InternalBuffer {
+    ) -> Result<&InternalBuffer, RendererError> {
         let entry = self
             .pool
             .entry(handle.raw())
-            .expect("BufferHandle has been removed from resources.");
+            .ok_or(RendererError::MissingBuffer)?;
 
         let buffer = resources
             .get_buffer(handle)
-            .expect("BufferHandle has been removed from resources.");
+            .ok_or(RendererError::MissingBuffer)?;
 
         let internal_buffer = entry.or_insert_with(|| {
             let wgpu_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -57,15 +58,15 @@ impl Buffers {
             internal_buffer.ver = buffer.ver().as_u64();
         }
 
-        internal_buffer
+        Ok(internal_buffer)
     }
 
-    pub fn get_internal_buffer(&self, handle: &BufferHandle) -> &InternalBuffer {
-        self.pool.get(handle.raw()).unwrap()
+    pub fn get_internal_buffer(&self, handle: &BufferHandle) -> Option<&InternalBuffer> {
+        self.pool.get(handle.raw())
     }
 
-    pub fn get_internal_buffer_by_key(&self, key: ResourceKey) -> &InternalBuffer {
-        self.pool.get(key).unwrap()
+    pub fn get_internal_buffer_by_key(&self, key: ResourceKey) -> Option<&InternalBuffer> {
+        self.pool.get(key)
     }
 
     pub fn destroy_internal_buffer(&mut self, key: ResourceKey) {
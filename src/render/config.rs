@@ -0,0 +1,439 @@
+use super::RendererError;
+
+/// Which color encoding the renderer should prefer for surface textures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceFormatPreference {
+    /// An sRGB format, so shader output is gamma-encoded on write.
+    #[default]
+    Srgb,
+    /// A linear (non-sRGB) format.
+    Linear,
+    /// This exact format.
+    Exact(wgpu::TextureFormat),
+}
+
+/// How `Renderer::render` shades primitives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShadingPath {
+    /// Every primitive is drawn and shaded directly into the target.
+    #[default]
+    Forward,
+    /// Opaque primitives are drawn into a G-buffer, which a full-screen pass then lights
+    /// into the target; blended primitives are drawn forward on top.
+    ///
+    /// Opaque materials need a shader with a G-buffer entry point. MSAA is not applied,
+    /// and the target's depth attachment is left untouched: depth lives in the G-buffer.
+    Deferred,
+}
+
+/// How shadow edges are filtered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShadowFilter {
+    /// One comparison per pixel: aliased edges.
+    Hard,
+    /// Percentage-closer filtering over 3 × 3 texels.
+    #[default]
+    Pcf3x3,
+    /// Percentage-closer filtering over 5 × 5 texels: softer edges, more samples.
+    Pcf5x5,
+}
+
+impl ShadowFilter {
+    /// Texels sampled on each side of the center texel.
+    pub(super) fn radius(self) -> u32 {
+        match self {
+            ShadowFilter::Hard => 0,
+            ShadowFilter::Pcf3x3 => 1,
+            ShadowFilter::Pcf5x5 => 2,
+        }
+    }
+}
+
+/// Shadow maps of lights that cast shadows.
+///
+/// - The first directional light gets cascaded shadow maps: the camera's view depth up to
+///   `max_distance` is split into `cascade_count` slices, each covered by one layer of the
+///   shadow map. Nearer cascades cover less ground and so get sharper shadows.
+/// - Point lights render their six cube faces, spot lights one tile of a shared atlas,
+///   each `local_resolution` texels wide. Lights over the budget cast no shadows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowConfig {
+    /// Width and height of every cascade in texels.
+    pub resolution: u32,
+    /// Number of cascades, clamped to 1..=4.
+    pub cascade_count: u32,
+    /// View distance beyond which nothing is shadowed, also capped by the camera's far plane.
+    pub max_distance: f32,
+    /// Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub split_lambda: f32,
+    /// Depth offset in shadow map depth units, against acne on surfaces facing the light.
+    pub depth_bias: f32,
+    /// Offset along the surface normal in shadow map texels, against acne at grazing angles.
+    pub normal_bias: f32,
+    /// Width and height of point light cube faces and spot light atlas tiles in texels.
+    pub local_resolution: u32,
+    /// Most point lights casting shadows in a frame, clamped to 8; the first ones get them.
+    pub max_point_lights: u32,
+    /// Most spot lights casting shadows in a frame, clamped to 16; the first ones get them.
+    pub max_spot_lights: u32,
+    pub filter: ShadowFilter,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            cascade_count: 4,
+            max_distance: 100.0,
+            split_lambda: 0.75,
+            depth_bias: 0.001,
+            normal_bias: 1.5,
+            local_resolution: 512,
+            max_point_lights: 4,
+            max_spot_lights: 4,
+            filter: ShadowFilter::default(),
+        }
+    }
+}
+
+/// Features the renderer enables whenever the adapter supports them.
+///
+/// - `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`: MSAA sample counts beyond the ones
+///   every adapter guarantees.
+pub const OPTIONAL_FEATURES: wgpu::Features =
+    wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+/// Capabilities the renderer needs from the adapter.
+///
+/// - `CUBE_ARRAY_TEXTURES`: point light shadows are sampled from a depth cube array.
+pub const REQUIRED_DOWNLEVEL_FLAGS: wgpu::DownlevelFlags =
+    wgpu::DownlevelFlags::CUBE_ARRAY_TEXTURES;
+
+/// Options used when creating a `Renderer`.
+///
+/// Every option is a preference: when the adapter or surface cannot satisfy it,
+/// the renderer falls back as described on each field and prints what it changed.
+#[derive(Clone, Debug)]
+pub struct RendererConfig {
+    /// Power preference used when requesting an adapter.
+    pub power_preference: wgpu::PowerPreference,
+    /// Backends an adapter may come from.
+    ///
+    /// - Fallback: if no adapter on these backends is usable, any backend of the instance is used.
+    pub backends: wgpu::Backends,
+    /// Case-insensitive substring the adapter name must contain.
+    ///
+    /// - Fallback: if no adapter matches, the default adapter for `power_preference` is used.
+    /// - Ignored on wasm, where adapters cannot be enumerated.
+    pub adapter_name: Option<String>,
+    /// Whether the software adapter may be used when no hardware adapter is available.
+    pub allow_fallback_adapter: bool,
+    /// Device features to enable, on top of [`OPTIONAL_FEATURES`].
+    ///
+    /// - Fallback: features the adapter does not support are dropped.
+    pub required_features: wgpu::Features,
+    /// Device limits to request.
+    ///
+    /// - Fallback: if the adapter cannot meet them, the adapter's own limits are used.
+    pub required_limits: wgpu::Limits,
+    /// Color encoding of surface textures.
+    ///
+    /// - Fallback: the first format the surface reports.
+    pub surface_format: SurfaceFormatPreference,
+    /// How surface textures are presented. Use `Mailbox` or `Immediate` to uncap the frame rate.
+    ///
+    /// - Fallback: `Fifo`, which every surface supports.
+    pub present_mode: wgpu::PresentMode,
+    /// How many frames may be queued ahead of the presentation engine.
+    ///
+    /// - Clamped to at least 1; the backend may clamp it further.
+    pub desired_maximum_frame_latency: u32,
+    /// Shading path of `Renderer::render`; can be changed later with `Renderer::set_shading_path`.
+    pub shading_path: ShadingPath,
+    /// Shadow maps of lights that cast shadows; can be changed later with
+    /// `Renderer::set_shadow_config`.
+    pub shadows: ShadowConfig,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            power_preference: wgpu::PowerPreference::default(),
+            backends: wgpu::Backends::all(),
+            adapter_name: None,
+            allow_fallback_adapter: true,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+            surface_format: SurfaceFormatPreference::default(),
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            shading_path: ShadingPath::Forward,
+            shadows: ShadowConfig::default(),
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Picks an adapter that matches `backends` and `adapter_name`, falling back to the
+    /// instance's default choice for `power_preference`.
+    pub(super) async fn request_adapter(
+        &self,
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+    ) -> Result<wgpu::Adapter, wgpu::RequestAdapterError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.adapter_name.is_some() || self.backends != wgpu::Backends::all() {
+            if let Some(adapter) = self.enumerate_adapter(instance, surface) {
+                return Ok(adapter);
+            }
+            println!(
+                "No adapter matches backends {:?} and name {:?}, using the default adapter",
+                self.backends, self.adapter_name
+            );
+        }
+
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: self.power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter: false,
+        };
+
+        match instance.request_adapter(&options).await {
+            Ok(adapter) => Ok(adapter),
+            Err(_) if self.allow_fallback_adapter => {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        force_fallback_adapter: true,
+                        ..options
+                    })
+                    .await
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn enumerate_adapter(
+        &self,
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+    ) -> Option<wgpu::Adapter> {
+        let name_filter = self.adapter_name.as_deref().map(str::to_lowercase);
+        let preferred_type = match self.power_preference {
+            wgpu::PowerPreference::HighPerformance => Some(wgpu::DeviceType::DiscreteGpu),
+            wgpu::PowerPreference::LowPower => Some(wgpu::DeviceType::IntegratedGpu),
+            wgpu::PowerPreference::None => None,
+        };
+
+        let mut candidates: Vec<wgpu::Adapter> = instance
+            .enumerate_adapters(self.backends)
+            .into_iter()
+            .filter(|adapter| surface.is_none_or(|surface| adapter.is_surface_supported(surface)))
+            .filter(|adapter| {
+                name_filter
+                    .as_deref()
+                    .is_none_or(|name| adapter.get_info().name.to_lowercase().contains(name))
+            })
+            .collect();
+
+        let preferred = candidates
+            .iter()
+            .position(|adapter| Some(adapter.get_info().device_type) == preferred_type)
+            .unwrap_or(0);
+
+        (!candidates.is_empty()).then(|| candidates.swap_remove(preferred))
+    }
+
+    /// `required_features` and [`OPTIONAL_FEATURES`] minus whatever the adapter does not support.
+    pub(super) fn features_for(&self, supported: wgpu::Features) -> wgpu::Features {
+        let missing = self.required_features - supported;
+        if !missing.is_empty() {
+            println!(
+                "Adapter does not support features {:?}, disabling them",
+                missing
+            );
+        }
+        (self.required_features | OPTIONAL_FEATURES) & supported
+    }
+
+    /// `required_limits` if the adapter meets them, otherwise the adapter's limits.
+    pub(super) fn limits_for(&self, supported: wgpu::Limits) -> wgpu::Limits {
+        if self.required_limits.check_limits(&supported) {
+            self.required_limits.clone()
+        } else {
+            println!("Adapter does not meet the requested limits, using its own limits");
+            supported
+        }
+    }
+
+    /// Builds the configuration for a newly attached surface.
+    ///
+    /// Fails when the surface reports no format or alpha mode, i.e. the adapter cannot present to it.
+    pub(super) fn surface_configuration(
+        &self,
+        caps: &wgpu::SurfaceCapabilities,
+        width: u32,
+        height: u32,
+    ) -> Result<wgpu::SurfaceConfiguration, RendererError> {
+        let (Some(&first_format), Some(&alpha_mode)) =
+            (caps.formats.first(), caps.alpha_modes.first())
+        else {
+            return Err(RendererError::IncompatibleSurface);
+        };
+
+        let format = match self.surface_format {
+            SurfaceFormatPreference::Srgb => caps.formats.iter().find(|format| format.is_srgb()),
+            SurfaceFormatPreference::Linear => caps.formats.iter().find(|format| !format.is_srgb()),
+            SurfaceFormatPreference::Exact(exact) => {
+                caps.formats.iter().find(|format| **format == exact)
+            }
+        }
+        .copied()
+        .unwrap_or(first_format);
+
+        // the Auto modes pick a supported mode themselves
+        let present_mode = if matches!(
+            self.present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) || caps.present_modes.contains(&self.present_mode)
+        {
+            self.present_mode
+        } else {
+            println!(
+                "Surface does not support {:?}, falling back to Fifo",
+                self.present_mode
+            );
+            wgpu::PresentMode::Fifo
+        };
+
+        Ok(wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width,
+            height,
+            present_mode,
+            alpha_mode,
+            view_formats: vec![],
+            desired_maximum_frame_latency: self.desired_maximum_frame_latency.max(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(formats: &[wgpu::TextureFormat]) -> wgpu::SurfaceCapabilities {
+        wgpu::SurfaceCapabilities {
+            formats: formats.to_vec(),
+            present_modes: vec![wgpu::PresentMode::Fifo, wgpu::PresentMode::Immediate],
+            alpha_modes: vec![wgpu::CompositeAlphaMode::Opaque],
+            usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        }
+    }
+
+    #[test]
+    fn test_missing_features_are_dropped() {
+        let config = RendererConfig {
+            required_features: wgpu::Features::DEPTH_CLIP_CONTROL | wgpu::Features::TIMESTAMP_QUERY,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.features_for(wgpu::Features::DEPTH_CLIP_CONTROL),
+            wgpu::Features::DEPTH_CLIP_CONTROL
+        );
+    }
+
+    #[test]
+    fn test_optional_features_are_enabled_when_supported() {
+        let config = RendererConfig::default();
+        assert_eq!(
+            config.features_for(wgpu::Features::all()),
+            OPTIONAL_FEATURES
+        );
+        assert_eq!(
+            config.features_for(wgpu::Features::empty()),
+            wgpu::Features::empty()
+        );
+    }
+
+    #[test]
+    fn test_limits_fall_back_to_the_adapter() {
+        let config = RendererConfig::default();
+        let below = wgpu::Limits::downlevel_webgl2_defaults();
+        assert_eq!(config.limits_for(below.clone()), below);
+
+        let above = wgpu::Limits {
+            max_texture_dimension_2d: 16384,
+            ..Default::default()
+        };
+        assert_eq!(config.limits_for(above), wgpu::Limits::default());
+    }
+
+    #[test]
+    fn test_surface_format_preference() {
+        use wgpu::TextureFormat::{Bgra8Unorm, Bgra8UnormSrgb, Rgba16Float};
+        let caps = caps(&[Bgra8Unorm, Bgra8UnormSrgb]);
+        let format = |surface_format| {
+            RendererConfig {
+                surface_format,
+                ..Default::default()
+            }
+            .surface_configuration(&caps, 1, 1)
+            .unwrap()
+            .format
+        };
+
+        assert_eq!(format(SurfaceFormatPreference::Srgb), Bgra8UnormSrgb);
+        assert_eq!(format(SurfaceFormatPreference::Linear), Bgra8Unorm);
+        // unsupported formats fall back to the first one reported
+        assert_eq!(
+            format(SurfaceFormatPreference::Exact(Rgba16Float)),
+            Bgra8Unorm
+        );
+    }
+
+    #[test]
+    fn test_unsupported_present_mode_falls_back_to_fifo() {
+        let caps = caps(&[wgpu::TextureFormat::Bgra8UnormSrgb]);
+        let present_mode = |present_mode| {
+            RendererConfig {
+                present_mode,
+                ..Default::default()
+            }
+            .surface_configuration(&caps, 1, 1)
+            .unwrap()
+            .present_mode
+        };
+
+        assert_eq!(
+            present_mode(wgpu::PresentMode::Immediate),
+            wgpu::PresentMode::Immediate
+        );
+        assert_eq!(
+            present_mode(wgpu::PresentMode::Mailbox),
+            wgpu::PresentMode::Fifo
+        );
+        assert_eq!(
+            present_mode(wgpu::PresentMode::AutoNoVsync),
+            wgpu::PresentMode::AutoNoVsync
+        );
+    }
+
+    #[test]
+    fn test_empty_capabilities_are_an_error() {
+        let config = RendererConfig::default();
+        assert!(matches!(
+            config.surface_configuration(&caps(&[]), 1, 1),
+            Err(RendererError::IncompatibleSurface)
+        ));
+        let no_alpha_modes = wgpu::SurfaceCapabilities {
+            alpha_modes: vec![],
+            ..caps(&[wgpu::TextureFormat::Bgra8UnormSrgb])
+        };
+        assert!(matches!(
+            config.surface_configuration(&no_alpha_modes, 1, 1),
+            Err(RendererError::IncompatibleSurface)
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use super::pipelines::TargetFormats;
+use std::collections::HashMap;
+
+const GBUFFER_TEXTURE_COUNT: u32 = 5;
+
+/// Full-screen lighting of G-buffers for deferred shading.
+///
+/// Owns the lighting shader and one pipeline per kind of target it lights into.
+/// The G-buffer bind group is rebuilt for every lighting pass, since its
+/// textures are transients that may change between frames.
+pub struct Deferred {
+    layout: wgpu::BindGroupLayout,
+    module: wgpu::ShaderModule,
+    pipelines: HashMap<TargetFormats, wgpu::RenderPipeline>,
+}
+
+impl Deferred {
+    pub fn new(device: &wgpu::Device) -> Self {
+        // depth is bound as an unfilterable float texture: GLSL cannot load from depth textures
+        let entries = (0..GBUFFER_TEXTURE_COUNT)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            })
+            .collect::<Vec<_>>();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gbuffer_bind_group_layout"),
+            entries: &entries,
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Deferred Lighting Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(
+                    include_str!("../shader/wgsl/lights.wgsl"),
+                    include_str!("../shader/wgsl/shadows.wgsl"),
+                    include_str!("../shader/wgsl/brdf.wgsl"),
+                    include_str!("../shader/wgsl/environment.wgsl"),
+                    include_str!("../shader/wgsl/deferred_lighting.wgsl")
+                )
+                .into(),
+            ),
+        });
+
+        Self {
+            layout,
+            module,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Bind group of the G-buffer textures: albedo, normal, material, depth and emissive.
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        views: &[wgpu::TextureView; GBUFFER_TEXTURE_COUNT as usize],
+    ) -> wgpu::BindGroup {
+        let entries = views
+            .iter()
+            .enumerate()
+            .map(|(binding, view)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect::<Vec<_>>();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gbuffer_bind_group"),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
+
+    /// The lighting pipeline for a pass with `targets`.
+    ///
+    /// Only the first color attachment is written; a depth attachment, if any, is ignored.
+    pub fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        global_layout: &wgpu::BindGroupLayout,
+        targets: &TargetFormats,
+    ) -> &wgpu::RenderPipeline {
+        if !self.pipelines.contains_key(targets) {
+            let pipeline = self.create_pipeline(device, global_layout, targets);
+            self.pipelines.insert(targets.clone(), pipeline);
+        }
+        &self.pipelines[targets]
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        global_layout: &wgpu::BindGroupLayout,
+        targets: &TargetFormats,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Deferred Lighting Pipeline Layout"),
+            bind_group_layouts: &[global_layout, &self.layout],
+            push_constant_ranges: &[],
+        });
+
+        let color_targets = targets
+            .color_targets
+            .iter()
+            .enumerate()
+            .map(|(index, target)| {
+                Some(wgpu::ColorTargetState {
+                    format: target.format,
+                    blend: None,
+                    write_mask: if index == 0 {
+                        target.write_mask
+                    } else {
+                        wgpu::ColorWrites::empty()
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Deferred Lighting Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.module,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.module,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &color_targets,
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: targets.depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: targets.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        println!("Created new deferred lighting pipeline");
+
+        pipeline
+    }
+}
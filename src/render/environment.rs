@@ -0,0 +1,410 @@
+use crate::ResourceKey;
+use wgpu::util::DeviceExt;
+
+pub const ENVIRONMENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+const IRRADIANCE_SIZE: u32 = 32;
+const SPECULAR_SIZE: u32 = 128;
+/// Mip levels of the prefiltered specular map, from roughness 0 to 1.
+pub const SPECULAR_MIPS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 128;
+
+/// One draw of the filter shader, matching `FilterParams` in
+/// `shader/wgsl/environment_filter.wgsl`.
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct FilterParams {
+    face: u32,
+    roughness: f32,
+    size: f32,
+    _padding: f32,
+}
+
+/// Slots of the params buffer: the irradiance faces, then the faces of every specular mip,
+/// then the BRDF lookup table.
+fn filter_params() -> Vec<FilterParams> {
+    let faces = |roughness: f32, size: u32| {
+        (0..6).map(move |face| FilterParams {
+            face,
+            roughness,
+            size: size as f32,
+            _padding: 0.0,
+        })
+    };
+    let mut params = faces(0.0, IRRADIANCE_SIZE).collect::<Vec<_>>();
+    for mip in 0..SPECULAR_MIPS {
+        let roughness = mip as f32 / (SPECULAR_MIPS - 1) as f32;
+        params.extend(faces(roughness, SPECULAR_SIZE >> mip));
+    }
+    params.extend(faces(0.0, BRDF_LUT_SIZE).take(1));
+    params
+}
+
+fn irradiance_slot(face: u32) -> u32 {
+    face
+}
+
+fn specular_slot(mip: u32, face: u32) -> u32 {
+    6 + mip * 6 + face
+}
+
+fn brdf_lut_slot() -> u32 {
+    6 + SPECULAR_MIPS * 6
+}
+
+fn create_cube(
+    device: &wgpu::Device,
+    label: &str,
+    size: u32,
+    mip_level_count: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ENVIRONMENT_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    (texture, view)
+}
+
+/// View of one mip level of one face, as a render attachment.
+fn face_view(texture: &wgpu::Texture, face: u32, mip: u32) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2),
+        base_array_layer: face,
+        array_layer_count: Some(1),
+        base_mip_level: mip,
+        mip_level_count: Some(1),
+        ..Default::default()
+    })
+}
+
+/// Image-based lighting maps precomputed from an environment cube map:
+/// - a diffuse irradiance cube,
+/// - a specular cube whose mip levels are prefiltered for increasing roughness,
+/// - a BRDF lookup table of the split-sum approximation, independent of the environment.
+///
+/// The maps are allocated once, so bind groups holding their views stay valid; they are
+/// refiltered by [`EnvironmentMaps::update`] when the source cube map changes.
+pub struct EnvironmentMaps {
+    layout: wgpu::BindGroupLayout,
+    irradiance_pipeline: wgpu::RenderPipeline,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    params_stride: u64,
+    sampler: wgpu::Sampler,
+    irradiance: wgpu::Texture,
+    irradiance_view: wgpu::TextureView,
+    specular: wgpu::Texture,
+    specular_view: wgpu::TextureView,
+    brdf_lut_view: wgpu::TextureView,
+    source: Option<(ResourceKey, (u64, u64))>,
+}
+
+impl EnvironmentMaps {
+    /// Allocates the maps and renders the BRDF lookup table.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("environment_filter_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(size_of::<FilterParams>() as u64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Environment Filter Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../shader/wgsl/environment_filter.wgsl").into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Filter Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point: &str| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Environment Filter Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    targets: &[Some(ENVIRONMENT_FORMAT.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let irradiance_pipeline = create_pipeline("fs_irradiance");
+        let prefilter_pipeline = create_pipeline("fs_prefilter");
+        let brdf_lut_pipeline = create_pipeline("fs_brdf_lut");
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let params_stride = (size_of::<FilterParams>() as u64).div_ceil(alignment) * alignment;
+        let mut params_memory = Vec::new();
+        for params in filter_params() {
+            params_memory.extend_from_slice(bytemuck::bytes_of(&params));
+            params_memory.resize(
+                params_memory.len().next_multiple_of(params_stride as usize),
+                0,
+            );
+        }
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Environment Filter Params"),
+            contents: &params_memory,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let (irradiance, irradiance_view) =
+            create_cube(device, "Environment Irradiance", IRRADIANCE_SIZE, 1);
+        let (specular, specular_view) =
+            create_cube(device, "Environment Specular", SPECULAR_SIZE, SPECULAR_MIPS);
+
+        let brdf_lut = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("BRDF Lookup Table"),
+            size: wgpu::Extent3d {
+                width: BRDF_LUT_SIZE,
+                height: BRDF_LUT_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ENVIRONMENT_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let brdf_lut_view = brdf_lut.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let maps = Self {
+            layout,
+            irradiance_pipeline,
+            prefilter_pipeline,
+            params_buffer,
+            params_stride,
+            sampler,
+            irradiance,
+            irradiance_view,
+            specular,
+            specular_view,
+            brdf_lut_view,
+            source: None,
+        };
+
+        // the lookup table samples nothing, the irradiance map is bound as a stand-in source
+        let bind_group = maps.bind_group(device, &maps.irradiance_view);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("BRDF Lookup Table Encoder"),
+        });
+        maps.filter_pass(
+            &mut encoder,
+            &brdf_lut_pipeline,
+            &bind_group,
+            &maps.brdf_lut_view,
+            brdf_lut_slot(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        maps
+    }
+
+    fn bind_group(&self, device: &wgpu::Device, source: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("environment_filter_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &self.params_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(size_of::<FilterParams>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    fn filter_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        slot: u32,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Environment Filter Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[slot * self.params_stride as u32]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Refilters the maps from `source`, a cube view of the texture `key` at `version`,
+    /// unless they were last filtered from that same version.
+    ///
+    /// The filter passes are submitted right away, so the maps are only considered
+    /// filtered once their work reached the queue, whatever happens to the frame.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        key: ResourceKey,
+        version: (u64, u64),
+        source: &wgpu::TextureView,
+    ) {
+        if self.source == Some((key, version)) {
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Environment Filter Encoder"),
+        });
+        let bind_group = self.bind_group(device, source);
+        for face in 0..6 {
+            self.filter_pass(
+                &mut encoder,
+                &self.irradiance_pipeline,
+                &bind_group,
+                &face_view(&self.irradiance, face, 0),
+                irradiance_slot(face),
+            );
+        }
+        for mip in 0..SPECULAR_MIPS {
+            for face in 0..6 {
+                self.filter_pass(
+                    &mut encoder,
+                    &self.prefilter_pipeline,
+                    &bind_group,
+                    &face_view(&self.specular, face, mip),
+                    specular_slot(mip, face),
+                );
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+        self.source = Some((key, version));
+        println!("Filtered environment maps");
+    }
+
+    #[inline]
+    pub fn irradiance_view(&self) -> &wgpu::TextureView {
+        &self.irradiance_view
+    }
+
+    #[inline]
+    pub fn specular_view(&self) -> &wgpu::TextureView {
+        &self.specular_view
+    }
+
+    #[inline]
+    pub fn brdf_lut_view(&self) -> &wgpu::TextureView {
+        &self.brdf_lut_view
+    }
+
+    /// Trilinear sampler for the maps.
+    #[inline]
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_params_cover_every_slot() {
+        let params = filter_params();
+        assert_eq!(params.len(), brdf_lut_slot() as usize + 1);
+
+        let irradiance = params[irradiance_slot(5) as usize];
+        assert_eq!(
+            (irradiance.face, irradiance.size),
+            (5, IRRADIANCE_SIZE as f32)
+        );
+
+        let roughest = params[specular_slot(SPECULAR_MIPS - 1, 2) as usize];
+        assert_eq!(roughest.face, 2);
+        assert_eq!(roughest.roughness, 1.0);
+        assert_eq!(roughest.size, (SPECULAR_SIZE >> (SPECULAR_MIPS - 1)) as f32);
+        assert_eq!(params[specular_slot(0, 0) as usize].roughness, 0.0);
+
+        assert_eq!(params[brdf_lut_slot() as usize].size, BRDF_LUT_SIZE as f32);
+    }
+}
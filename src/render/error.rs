@@ -0,0 +1,123 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// Errors reported by the `Renderer`.
+///
+/// Adapter, device and surface errors are fatal for the call that produced them.
+/// Resource errors only affect the primitives that reference the bad resource;
+/// those primitives are skipped and listed in [`RenderReport::skipped`].
+///
+/// [`RenderReport::skipped`]: super::RenderReport::skipped
+#[derive(Clone, Debug)]
+pub enum RendererError {
+    /// No adapter matched the request.
+    AdapterUnavailable(wgpu::RequestAdapterError),
+    /// The adapter lacks capabilities the renderer relies on, e.g. depth cube arrays
+    /// for point light shadows.
+    MissingDownlevelFlags(wgpu::DownlevelFlags),
+    /// The adapter refused to create a device.
+    DeviceUnavailable(wgpu::RequestDeviceError),
+    /// Acquiring the next surface texture failed.
+    Surface(wgpu::SurfaceError),
+    /// The surface reports no format or alpha mode to configure it with.
+    IncompatibleSurface,
+    /// A render target references a surface that is no longer in `Resources`.
+    MissingSurface,
+    /// A texture handle is no longer in `Resources`.
+    MissingTexture,
+    /// An environment texture is not a `TextureKind::Cube`.
+    NotCubeTexture,
+    /// A skybox texture is not a `TextureKind::Cube`, or a panorama texture not a `TextureKind::D2`.
+    BackgroundTextureKind,
+    /// A geometry handle is no longer in `Resources`.
+    MissingGeometry,
+    /// A material handle is no longer in `Resources`.
+    MissingMaterial,
+    /// A buffer handle is no longer in `Resources`.
+    MissingBuffer,
+    /// A geometry lacks a vertex attribute required by the material's shader.
+    MissingAttribute { name: Box<str> },
+    /// The material's shader has no G-buffer entry point, so it cannot be drawn deferred.
+    NoGBufferEntryPoint,
+    /// A render target has no color attachment, or a graph pass has no attachment at all.
+    NoColorAttachment,
+    /// The passes of a render graph depend on each other in a cycle.
+    GraphCycle,
+    /// The texture format has no defined buffer copy layout, so it cannot be uploaded or read back.
+    UnsupportedCopyFormat(wgpu::TextureFormat),
+    /// A texture's data is shorter than its layers, faces or depth slices need.
+    TextureDataTooShort { expected: usize, actual: usize },
+    /// Surface textures are owned by the presentation engine and cannot be read back.
+    SurfaceReadback,
+    /// Mapping a readback buffer failed.
+    BufferMap(wgpu::BufferAsyncError),
+    /// Waiting on the device failed.
+    Poll(Arc<wgpu::PollError>),
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererError::AdapterUnavailable(err) => write!(f, "adapter unavailable: {}", err),
+            RendererError::MissingDownlevelFlags(flags) => {
+                write!(f, "adapter lacks required capabilities {:?}", flags)
+            }
+            RendererError::DeviceUnavailable(err) => write!(f, "device unavailable: {}", err),
+            RendererError::Surface(err) => write!(f, "surface error: {}", err),
+            RendererError::IncompatibleSurface => {
+                write!(f, "surface cannot be presented by the adapter")
+            }
+            RendererError::MissingSurface => write!(f, "surface has been removed from resources"),
+            RendererError::MissingTexture => write!(f, "texture has been removed from resources"),
+            RendererError::NotCubeTexture => write!(f, "environment texture is not a cube map"),
+            RendererError::BackgroundTextureKind => {
+                write!(f, "background texture kind does not match the background")
+            }
+            RendererError::MissingGeometry => {
+                write!(f, "geometry has been removed from resources")
+            }
+            RendererError::MissingMaterial => {
+                write!(f, "material has been removed from resources")
+            }
+            RendererError::MissingBuffer => write!(f, "buffer has been removed from resources"),
+            RendererError::MissingAttribute { name } => {
+                write!(f, "geometry is missing vertex attribute '{}'", name)
+            }
+            RendererError::NoGBufferEntryPoint => {
+                write!(f, "shader has no G-buffer entry point")
+            }
+            RendererError::NoColorAttachment => write!(f, "render target has no color attachment"),
+            RendererError::GraphCycle => write!(f, "render graph passes form a dependency cycle"),
+            RendererError::UnsupportedCopyFormat(format) => {
+                write!(
+                    f,
+                    "texture format {:?} cannot be copied to or from a buffer",
+                    format
+                )
+            }
+            RendererError::TextureDataTooShort { expected, actual } => {
+                write!(
+                    f,
+                    "texture data has {} bytes, expected at least {}",
+                    actual, expected
+                )
+            }
+            RendererError::SurfaceReadback => write!(f, "surface textures cannot be read back"),
+            RendererError::BufferMap(err) => write!(f, "failed to map buffer: {}", err),
+            RendererError::Poll(err) => write!(f, "failed to poll device: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RendererError::AdapterUnavailable(err) => Some(err),
+            RendererError::DeviceUnavailable(err) => Some(err),
+            RendererError::Surface(err) => Some(err),
+            RendererError::BufferMap(err) => Some(err),
+            RendererError::Poll(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::RendererError;
 use crate::{
     GeometryHandle, ResourceKey,
     geometry::{Geometry, VertexBuffer},
@@ -5,14 +6,18 @@ use crate::{
 };
 use slotmap::SecondaryMap;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::ops::Range;
 
+#[derive(Hash)]
 pub struct VertexBufferDesc {
     pub array_stride: wgpu::BufferAddress,
     pub step_mode: wgpu::VertexStepMode,
     pub attributes: Vec<VertexAttributeDesc>,
 }
 
+#[derive(Hash)]
 pub struct VertexAttributeDesc {
     pub format: wgpu::VertexFormat,
     pub offset: wgpu::BufferAddress,
@@ -21,11 +26,12 @@ pub struct VertexAttributeDesc {
 
 pub struct GeometryShaderDesc {
     layouts: Vec<VertexBufferDesc>,
+    layout_hash: u64,
     entries: Vec<(ResourceKey, Range<u64>)>,
 }
 
 impl GeometryShaderDesc {
-    pub fn new(geometry: &Geometry, shader: &Shader) -> Self {
+    pub fn new(geometry: &Geometry, shader: &Shader) -> Result<Self, RendererError> {
         println!("Creating GeometryShaderDesc");
 
         let mut layouts: Vec<VertexBufferDesc> = Vec::new();
@@ -34,9 +40,11 @@ impl GeometryShaderDesc {
         let mut vb_index: HashMap<VertexBuffer, usize> = HashMap::new();
 
         for entry in shader.vertex_schema().iter() {
-            let attribute = geometry
-                .get_attribute(entry.key)
-                .expect(&format!("Attribute {} not found", entry.name));
+            let attribute = geometry.get_attribute(entry.key).ok_or_else(|| {
+                RendererError::MissingAttribute {
+                    name: entry.name.clone(),
+                }
+            })?;
             let vb = &attribute.vertex_buffer;
 
             let idx = if let Some(&i) = vb_index.get(vb) {
@@ -63,7 +71,15 @@ impl GeometryShaderDesc {
             });
         }
 
-        Self { layouts, entries }
+        let mut hasher = DefaultHasher::new();
+        layouts.hash(&mut hasher);
+        let layout_hash = hasher.finish();
+
+        Ok(Self {
+            layouts,
+            layout_hash,
+            entries,
+        })
     }
 
     #[inline]
@@ -71,6 +87,12 @@ impl GeometryShaderDesc {
         &self.layouts
     }
 
+    /// Hash of the vertex buffer layouts; equal hashes produce the same pipeline vertex state.
+    #[inline]
+    pub fn layout_hash(&self) -> u64 {
+        self.layout_hash
+    }
+
     #[inline]
     pub fn entries(&self) -> &[(ResourceKey, Range<u64>)] {
         &self.entries
@@ -98,16 +120,20 @@ impl InternalGeometry {
         self
     }
 
-    pub fn link_shader(&mut self, geometry: &Geometry, shader: &Shader) -> &GeometryShaderDesc {
-        self.desc_map
-            .entry(shader.vertex_schema_hash())
-            .or_insert_with(|| GeometryShaderDesc::new(geometry, shader))
+    pub fn link_shader(
+        &mut self,
+        geometry: &Geometry,
+        shader: &Shader,
+    ) -> Result<&GeometryShaderDesc, RendererError> {
+        let desc = match self.desc_map.entry(shader.vertex_schema_hash()) {
+            Entry::Occupied(o) => o.into_mut(),
+            Entry::Vacant(v) => v.insert(GeometryShaderDesc::new(geometry, shader)?),
+        };
+        Ok(desc)
     }
 
-    pub fn get_desc(&self, shader: &Shader) -> &GeometryShaderDesc {
-        self.desc_map
-            .get(&shader.vertex_schema_hash())
-            .expect("GeometryShaderDesc not found for given shader.")
+    pub fn get_desc(&self, shader: &Shader) -> Option<&GeometryShaderDesc> {
+        self.desc_map.get(&shader.vertex_schema_hash())
     }
 }
 
@@ -126,15 +152,15 @@ impl Geometries {
         &mut self,
         geometry: &Geometry,
         geometry_handle: &GeometryHandle,
-    ) -> &InternalGeometry {
+    ) -> Result<&InternalGeometry, RendererError> {
         let entry = self
             .pool
             .entry(geometry_handle.raw())
-            .expect("GeometryHandle has been removed from pool.");
+            .ok_or(RendererError::MissingGeometry)?;
 
-        let internal_geometry = entry.or_insert_with(|| InternalGeometry::new(&geometry));
+        let internal_geometry = entry.or_insert_with(|| InternalGeometry::new(geometry));
 
-        internal_geometry.ensure(geometry)
+        Ok(internal_geometry.ensure(geometry))
     }
 
     pub fn link_shader(
@@ -142,18 +168,18 @@ impl Geometries {
         geometry: &Geometry,
         geometry_handle: &GeometryHandle,
         shader: &Shader,
-    ) -> &GeometryShaderDesc {
+    ) -> Result<&GeometryShaderDesc, RendererError> {
         let internal_geometry = self
             .pool
             .get_mut(geometry_handle.raw())
-            .expect("Internal Geometry lost.");
+            .ok_or(RendererError::MissingGeometry)?;
         internal_geometry.link_shader(geometry, shader)
     }
 
-    #[allow(dead_code)]
-    pub fn get_internal_geometry(&self, geometry_handle: &GeometryHandle) -> &InternalGeometry {
-        self.pool
-            .get(geometry_handle.raw())
-            .expect("Internal Geometry lost.")
+    pub fn get_internal_geometry(
+        &self,
+        geometry_handle: &GeometryHandle,
+    ) -> Option<&InternalGeometry> {
+        self.pool.get(geometry_handle.raw())
     }
 }
@@ -0,0 +1,440 @@
+use super::RendererError;
+use crate::ResourceKey;
+use crate::graph::{GraphPass, GraphTexture, RenderGraph};
+use crate::target::LoadOp;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Identity of a texture referenced by a graph.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureId {
+    Imported(ResourceKey),
+    Transient(usize),
+}
+
+impl From<&GraphTexture> for TextureId {
+    fn from(texture: &GraphTexture) -> Self {
+        match texture {
+            GraphTexture::Imported(handle) => Self::Imported(handle.raw()),
+            GraphTexture::Transient(id) => Self::Transient(id.0),
+        }
+    }
+}
+
+/// Textures a pass reads and writes, in declaration order without duplicates.
+struct PassAccess {
+    reads: Vec<TextureId>,
+    writes: Vec<TextureId>,
+}
+
+impl PassAccess {
+    fn new(pass: &GraphPass) -> Self {
+        let mut reads = Vec::new();
+        let mut writes = Vec::new();
+
+        let mut attach = |texture: &GraphTexture, loads: bool| {
+            let id = TextureId::from(texture);
+            if loads && !reads.contains(&id) {
+                reads.push(id);
+            }
+            if !writes.contains(&id) {
+                writes.push(id);
+            }
+        };
+
+        for color_attachment in pass.color_attachments() {
+            attach(
+                &color_attachment.texture,
+                matches!(color_attachment.ops.load, LoadOp::Load),
+            );
+        }
+        if let Some(depth_stencil_attachment) = pass.depth_stencil_attachment() {
+            attach(
+                &depth_stencil_attachment.texture,
+                matches!(depth_stencil_attachment.depth_ops.load, LoadOp::Load)
+                    || matches!(depth_stencil_attachment.stencil_ops.load, LoadOp::Load),
+            );
+        }
+
+        for texture in pass.reads() {
+            let id = TextureId::from(texture);
+            if !reads.contains(&id) {
+                reads.push(id);
+            }
+        }
+
+        Self { reads, writes }
+    }
+}
+
+/// Execution plan of a [`RenderGraph`].
+///
+/// - `order`: indices of the passes to run, in execution order; culled passes are left out.
+/// - `culled`: indices of the passes that were culled.
+/// - `transient_slots`: the physical texture each transient is placed in, `None` when no pass uses it.
+/// - `slot_count`: number of physical textures needed; each slot holds transients of one size and format.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GraphPlan {
+    pub order: Vec<usize>,
+    pub culled: Vec<usize>,
+    pub transient_slots: Vec<Option<usize>>,
+    pub slot_count: usize,
+}
+
+impl GraphPlan {
+    pub fn new(graph: &RenderGraph) -> Result<Self, RendererError> {
+        let passes = graph.passes();
+        let accesses = passes.iter().map(PassAccess::new).collect::<Vec<_>>();
+
+        for id in accesses
+            .iter()
+            .flat_map(|access| access.reads.iter().chain(access.writes.iter()))
+        {
+            if let TextureId::Transient(index) = id
+                && *index >= graph.transients().len()
+            {
+                return Err(RendererError::MissingTexture);
+            }
+        }
+
+        let (edges, data_edges) = dependencies(&accesses);
+        let sorted = topological_order(passes.len(), &edges)?;
+
+        // a pass is live when it writes an imported texture or feeds a live pass
+        let mut live = vec![false; passes.len()];
+        for &pass in sorted.iter().rev() {
+            live[pass] = accesses[pass]
+                .writes
+                .iter()
+                .any(|id| matches!(id, TextureId::Imported(_)))
+                || data_edges[pass].iter().any(|&consumer| live[consumer]);
+        }
+
+        let (order, culled): (Vec<usize>, Vec<usize>) =
+            sorted.into_iter().partition(|&pass| live[pass]);
+        let culled = {
+            let mut culled = culled;
+            culled.sort_unstable();
+            culled
+        };
+
+        // first and last position in `order` of each transient
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; graph.transients().len()];
+        for (position, &pass) in order.iter().enumerate() {
+            let access = &accesses[pass];
+            for id in access.reads.iter().chain(access.writes.iter()) {
+                if let TextureId::Transient(index) = *id {
+                    let lifetime = lifetimes[index].get_or_insert((position, position));
+                    lifetime.1 = position;
+                }
+            }
+        }
+
+        // greedy placement: reuse a slot of the same description whose occupant is already dead
+        let mut by_start = (0..lifetimes.len())
+            .filter_map(|index| lifetimes[index].map(|lifetime| (lifetime, index)))
+            .collect::<Vec<_>>();
+        by_start.sort_unstable();
+
+        let transients = graph.transients();
+        let mut slots: Vec<(usize, usize)> = Vec::new(); // (transient describing the slot, last use)
+        let mut transient_slots = vec![None; transients.len()];
+        for ((first, last), index) in by_start {
+            let desc = &transients[index];
+            let reusable = slots.iter().position(|&(occupant, end)| {
+                let other = &transients[occupant];
+                end < first
+                    && other.width == desc.width
+                    && other.height == desc.height
+                    && other.format == desc.format
+            });
+            let slot = match reusable {
+                Some(slot) => {
+                    slots[slot] = (index, last);
+                    slot
+                }
+                None => {
+                    slots.push((index, last));
+                    slots.len() - 1
+                }
+            };
+            transient_slots[index] = Some(slot);
+        }
+
+        Ok(Self {
+            order,
+            culled,
+            transient_slots,
+            slot_count: slots.len(),
+        })
+    }
+}
+
+/// Builds the dependency edges between passes, as adjacency lists from producer to consumer.
+///
+/// Returns every ordering edge (read-after-write, write-after-write, write-after-read),
+/// and separately the read-after-write edges, which are the only ones carrying data.
+fn dependencies(accesses: &[PassAccess]) -> (Vec<HashSet<usize>>, Vec<HashSet<usize>>) {
+    let mut edges = vec![HashSet::new(); accesses.len()];
+    let mut data_edges = vec![HashSet::new(); accesses.len()];
+
+    let mut last_writer: HashMap<TextureId, usize> = HashMap::new();
+    let mut first_writer: HashMap<TextureId, usize> = HashMap::new();
+    let mut readers: HashMap<TextureId, Vec<usize>> = HashMap::new();
+    let mut forward_reads = Vec::new();
+
+    for (pass, access) in accesses.iter().enumerate() {
+        for id in &access.reads {
+            match last_writer.get(id) {
+                Some(&writer) => {
+                    edges[writer].insert(pass);
+                    data_edges[writer].insert(pass);
+                    readers.entry(*id).or_default().push(pass);
+                }
+                // read before any write in declaration order: depends on the first writer, if any
+                None => forward_reads.push((*id, pass)),
+            }
+        }
+
+        for id in &access.writes {
+            if let Some(&writer) = last_writer.get(id) {
+                edges[writer].insert(pass);
+            }
+            for reader in readers.remove(id).unwrap_or_default() {
+                if reader != pass {
+                    edges[reader].insert(pass);
+                }
+            }
+            last_writer.insert(*id, pass);
+            first_writer.entry(*id).or_insert(pass);
+        }
+    }
+
+    for (id, pass) in forward_reads {
+        if let Some(&writer) = first_writer.get(&id)
+            && writer != pass
+        {
+            edges[writer].insert(pass);
+            data_edges[writer].insert(pass);
+        }
+    }
+
+    (edges, data_edges)
+}
+
+/// Kahn's algorithm, preferring declaration order among ready passes.
+fn topological_order(count: usize, edges: &[HashSet<usize>]) -> Result<Vec<usize>, RendererError> {
+    let mut in_degree = vec![0usize; count];
+    for consumers in edges {
+        for &consumer in consumers {
+            in_degree[consumer] += 1;
+        }
+    }
+
+    let mut ready = (0..count)
+        .filter(|&pass| in_degree[pass] == 0)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+
+    let mut order = Vec::with_capacity(count);
+    while let Some(Reverse(pass)) = ready.pop() {
+        order.push(pass);
+        for &consumer in &edges[pass] {
+            in_degree[consumer] -= 1;
+            if in_degree[consumer] == 0 {
+                ready.push(Reverse(consumer));
+            }
+        }
+    }
+
+    if order.len() != count {
+        return Err(RendererError::GraphCycle);
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resources;
+    use crate::camera::Camera;
+    use crate::graph::{GBuffer, GraphPass, TransientId, TransientTexture};
+    use crate::target::Operations;
+    use crate::texture::{Texture, TextureKind};
+
+    fn load<T>() -> Operations<T> {
+        Operations {
+            load: LoadOp::Load,
+            store: Default::default(),
+        }
+    }
+
+    fn transient(graph: &mut RenderGraph, name: &str) -> TransientId {
+        graph.create_transient(TransientTexture::new(
+            name,
+            16,
+            16,
+            wgpu::TextureFormat::Rgba8Unorm,
+        ))
+    }
+
+    fn output(resources: &mut Resources) -> crate::TextureHandle {
+        resources.insert_texture(Texture::new(
+            TextureKind::Render {
+                width: 16,
+                height: 16,
+            },
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        ))
+    }
+
+    #[test]
+    fn test_passes_follow_dependencies() {
+        let mut resources = Resources::default();
+        let output = output(&mut resources);
+        let mut graph = RenderGraph::new();
+        let shadow = transient(&mut graph, "shadow");
+
+        // the consumer is declared before its producer
+        graph.add_pass(
+            GraphPass::new("main", 16, 16)
+                .color(&output, Default::default())
+                .read(shadow),
+        );
+        graph.add_pass(GraphPass::new("shadow", 16, 16).color(shadow, Default::default()));
+
+        let plan = GraphPlan::new(&graph).unwrap();
+        assert_eq!(plan.order, vec![1, 0]);
+        assert!(plan.culled.is_empty());
+    }
+
+    #[test]
+    fn test_unused_passes_are_culled() {
+        let mut resources = Resources::default();
+        let output = output(&mut resources);
+        let mut graph = RenderGraph::new();
+        let unused = transient(&mut graph, "unused");
+
+        graph.add_pass(GraphPass::new("unused", 16, 16).color(unused, Default::default()));
+        graph.add_pass(GraphPass::new("main", 16, 16).color(&output, Default::default()));
+
+        let plan = GraphPlan::new(&graph).unwrap();
+        assert_eq!(plan.order, vec![1]);
+        assert_eq!(plan.culled, vec![0]);
+        assert_eq!(plan.transient_slots, vec![None]);
+        assert_eq!(plan.slot_count, 0);
+    }
+
+    #[test]
+    fn test_transients_alias_when_lifetimes_do_not_overlap() {
+        let mut resources = Resources::default();
+        let output = output(&mut resources);
+        let mut graph = RenderGraph::new();
+        let a = transient(&mut graph, "a");
+        let b = transient(&mut graph, "b");
+        let c = transient(&mut graph, "c");
+
+        // ping-pong chain: a -> b -> c -> output, so a and c can share a texture
+        graph.add_pass(GraphPass::new("0", 16, 16).color(a, Default::default()));
+        graph.add_pass(
+            GraphPass::new("1", 16, 16)
+                .color(b, Default::default())
+                .read(a),
+        );
+        graph.add_pass(
+            GraphPass::new("2", 16, 16)
+                .color(c, Default::default())
+                .read(b),
+        );
+        graph.add_pass(
+            GraphPass::new("3", 16, 16)
+                .color(&output, Default::default())
+                .read(c),
+        );
+
+        let plan = GraphPlan::new(&graph).unwrap();
+        assert_eq!(plan.order, vec![0, 1, 2, 3]);
+        assert_eq!(plan.slot_count, 2);
+        assert_eq!(plan.transient_slots[0], plan.transient_slots[2]);
+        assert_ne!(plan.transient_slots[0], plan.transient_slots[1]);
+    }
+
+    #[test]
+    fn test_loaded_attachments_are_reads() {
+        let mut resources = Resources::default();
+        let output = output(&mut resources);
+        let mut graph = RenderGraph::new();
+        let depth = graph.create_transient(TransientTexture::new(
+            "depth",
+            16,
+            16,
+            wgpu::TextureFormat::Depth24Plus,
+        ));
+
+        graph.add_pass(GraphPass::new("prepass", 16, 16).depth_stencil(
+            depth,
+            Default::default(),
+            Default::default(),
+        ));
+        graph.add_pass(
+            GraphPass::new("main", 16, 16)
+                .color(&output, Default::default())
+                .depth_stencil(depth, load(), load()),
+        );
+
+        // the main pass loads the depth written by the prepass, which keeps it alive
+        let plan = GraphPlan::new(&graph).unwrap();
+        assert_eq!(plan.order, vec![0, 1]);
+        assert!(plan.culled.is_empty());
+    }
+
+    #[test]
+    fn test_lighting_reads_the_gbuffer() {
+        let mut resources = Resources::default();
+        let output = output(&mut resources);
+        let camera = Camera::default();
+        let mut graph = RenderGraph::new();
+        let gbuffer = GBuffer::create(&mut graph, 16, 16);
+
+        graph.add_pass(
+            GraphPass::new("lighting", 16, 16)
+                .color(&output, Default::default())
+                .light_gbuffer(&gbuffer, &camera),
+        );
+        graph.add_pass(
+            GraphPass::new("geometry", 16, 16)
+                .write_gbuffer(&gbuffer)
+                .draw(&[], &camera),
+        );
+
+        let plan = GraphPlan::new(&graph).unwrap();
+        assert_eq!(plan.order, vec![1, 0]);
+        assert!(plan.culled.is_empty());
+        assert_eq!(plan.slot_count, 5);
+    }
+
+    #[test]
+    fn test_cycles_are_rejected() {
+        let mut graph = RenderGraph::new();
+        let a = transient(&mut graph, "a");
+        let b = transient(&mut graph, "b");
+
+        graph.add_pass(
+            GraphPass::new("0", 16, 16)
+                .color(a, Default::default())
+                .read(b),
+        );
+        graph.add_pass(
+            GraphPass::new("1", 16, 16)
+                .color(b, Default::default())
+                .read(a),
+        );
+
+        assert!(matches!(
+            GraphPlan::new(&graph),
+            Err(RendererError::GraphCycle)
+        ));
+    }
+}
@@ -1,5 +1,8 @@
+use super::RendererError;
 use super::samplers::Samplers;
 use super::textures::Textures;
+use super::transients::Transients;
+use crate::graph::GraphTexture;
 use crate::material::{Material, MaterialParameter};
 use crate::{MaterialHandle, ResourceKey};
 use crate::{Resources, shader::*};
@@ -14,16 +17,49 @@ pub enum BindingCache {
     Texture {
         slot: u32,
         ver: u64,
+        /// View a transient texture resolved to when the bind group was built.
+        view: Option<wgpu::TextureView>,
     },
     Sampler {
         slot: u32,
+        comparison: bool,
         ver: u64,
     },
 }
 
+/// View bound for a texture parameter: the texture in `Resources`, the backing
+/// texture of a transient, or the default texture if neither is available.
+fn texture_view<'a>(
+    textures: &'a Textures,
+    transients: &'a Transients,
+    texture: Option<&GraphTexture>,
+) -> &'a wgpu::TextureView {
+    let view = match texture {
+        Some(GraphTexture::Imported(handle)) => textures
+            .get_internal_texture(handle)
+            .map(|texture_gpu| texture_gpu.view()),
+        Some(GraphTexture::Transient(id)) => transients.view(*id),
+        None => None,
+    };
+    // textures without GPU storage (e.g. `TextureKind::Empty`) bind the default texture
+    view.unwrap_or_else(|| textures.get_default_gpu_texture().view())
+}
+
+/// Transient view a texture parameter resolves to, `None` for other textures.
+fn transient_view(
+    transients: &Transients,
+    texture: Option<&GraphTexture>,
+) -> Option<wgpu::TextureView> {
+    match texture {
+        Some(GraphTexture::Transient(id)) => transients.view(*id).cloned(),
+        _ => None,
+    }
+}
+
 fn bind_group(
     device: &wgpu::Device,
     textures: &Textures,
+    transients: &Transients,
     samplers: &Samplers,
     bindings_cache: &[BindingCache],
     bind_group_layout: &wgpu::BindGroupLayout,
@@ -41,22 +77,31 @@ fn bind_group(
                 resource: buf.as_entire_binding(),
             },
             (BindingCache::Texture { slot, .. }, MaterialParameter::Texture { val, .. }) => {
-                let texture_gpu = if let Some(texture_handle) = val {
-                    textures.get_internal_texture(texture_handle)
-                } else {
-                    textures.get_default_gpu_texture()
-                };
                 wgpu::BindGroupEntry {
                     binding: *slot,
-                    resource: wgpu::BindingResource::TextureView(&texture_gpu.view()),
+                    resource: wgpu::BindingResource::TextureView(texture_view(
+                        textures,
+                        transients,
+                        val.as_ref(),
+                    )),
                 }
             }
-            (BindingCache::Sampler { slot, .. }, MaterialParameter::Sampler { val, .. }) => {
-                let sampler = if let Some(sampler_box) = val {
-                    samplers.get_gpu_sampler(sampler_box).unwrap()
-                } else {
-                    samplers.get_default_gpu_sampler()
-                };
+            (
+                BindingCache::Sampler {
+                    slot, comparison, ..
+                },
+                MaterialParameter::Sampler { val, .. },
+            ) => {
+                let sampler = val
+                    .as_ref()
+                    .and_then(|sampler_box| samplers.get_gpu_sampler(sampler_box))
+                    .unwrap_or_else(|| {
+                        if *comparison {
+                            samplers.get_default_gpu_comparison_sampler()
+                        } else {
+                            samplers.get_default_gpu_sampler()
+                        }
+                    });
                 wgpu::BindGroupEntry {
                     binding: *slot,
                     resource: wgpu::BindingResource::Sampler(sampler),
@@ -70,7 +115,7 @@ fn bind_group(
 
     device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("Material Bind Group"),
-        layout: &bind_group_layout,
+        layout: bind_group_layout,
         entries: &entries,
     })
 }
@@ -85,6 +130,7 @@ impl InternalMaterial {
     pub fn new(
         device: &wgpu::Device,
         textures: &Textures,
+        transients: &Transients,
         samplers: &Samplers,
         material: &Material,
     ) -> Self {
@@ -110,9 +156,11 @@ impl InternalMaterial {
                 BindingType::Texture => BindingCache::Texture {
                     slot: binding_entry.slot,
                     ver: u64::MAX,
+                    view: None,
                 },
-                BindingType::Sampler => BindingCache::Sampler {
+                BindingType::Sampler { comparison } => BindingCache::Sampler {
                     slot: binding_entry.slot,
+                    comparison: *comparison,
                     ver: u64::MAX,
                 },
             })
@@ -142,10 +190,14 @@ impl InternalMaterial {
                     },
                     count: None,
                 },
-                BindingType::Sampler => wgpu::BindGroupLayoutEntry {
+                BindingType::Sampler { comparison } => wgpu::BindGroupLayoutEntry {
                     binding: binding_entry.slot,
                     visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    ty: wgpu::BindingType::Sampler(if *comparison {
+                        wgpu::SamplerBindingType::Comparison
+                    } else {
+                        wgpu::SamplerBindingType::Filtering
+                    }),
                     count: None,
                 },
             })
@@ -159,6 +211,7 @@ impl InternalMaterial {
         let bind_group = bind_group(
             device,
             textures,
+            transients,
             samplers,
             &bindings_cache,
             &bind_group_layout,
@@ -177,6 +230,7 @@ impl InternalMaterial {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         textures: &Textures,
+        transients: &Transients,
         samplers: &Samplers,
         material: &Material,
     ) -> bool {
@@ -189,11 +243,18 @@ impl InternalMaterial {
         {
             match (binding_cache, param) {
                 (
-                    BindingCache::Texture { ver: pver, .. },
-                    MaterialParameter::Texture { ver, .. },
+                    BindingCache::Texture {
+                        ver: pver,
+                        view: pview,
+                        ..
+                    },
+                    MaterialParameter::Texture { ver, val },
                 ) => {
-                    if ver.as_u64() != *pver {
+                    // transients may be backed by another texture from one frame to the next
+                    let view = transient_view(transients, val.as_ref());
+                    if ver.as_u64() != *pver || view != *pview {
                         *pver = ver.as_u64();
+                        *pview = view;
                         needs_update = true;
                     }
                 }
@@ -225,6 +286,7 @@ impl InternalMaterial {
             self.bind_group = bind_group(
                 device,
                 textures,
+                transients,
                 samplers,
                 &self.bindings_cache,
                 &self.bind_group_layout,
@@ -247,39 +309,47 @@ impl Materials {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         resources: &Resources,
         textures: &Textures,
+        transients: &Transients,
         samplers: &Samplers,
         material_handle: &MaterialHandle,
-    ) -> &InternalMaterial {
+    ) -> Result<&InternalMaterial, RendererError> {
+        let material = resources
+            .get_material(material_handle)
+            .ok_or(RendererError::MissingMaterial)?;
+
+        if material
+            .textures()
+            .any(|texture_handle| resources.get_texture(texture_handle).is_none())
+        {
+            return Err(RendererError::MissingTexture);
+        }
+
         let entry = self
             .map
             .entry(material_handle.raw())
-            .expect("MaterialHandle has been removed from resources.");
+            .ok_or(RendererError::MissingMaterial)?;
 
         let internal_material = entry.or_insert_with(|| {
-            let material = resources.get_material(material_handle).unwrap();
-            InternalMaterial::new(device, textures, samplers, material)
+            InternalMaterial::new(device, textures, transients, samplers, material)
         });
 
-        internal_material.ensure_bind_group(
-            device,
-            queue,
-            textures,
-            samplers,
-            resources.get_material(material_handle).unwrap(),
-        );
-
         internal_material
+            .ensure_bind_group(device, queue, textures, transients, samplers, material);
+
+        Ok(internal_material)
     }
 
-    pub fn get_internal_material(&self, material_handle: &MaterialHandle) -> &InternalMaterial {
-        self.map
-            .get(material_handle.raw())
-            .expect("Material GPU lost.")
+    pub fn get_internal_material(
+        &self,
+        material_handle: &MaterialHandle,
+    ) -> Option<&InternalMaterial> {
+        self.map.get(material_handle.raw())
     }
 }
@@ -1,132 +1,394 @@
 use super::geometries::GeometryShaderDesc;
-use crate::{MaterialHandle, ResourceKey};
+use crate::material::{BlendMode, RenderState};
+use crate::shader::Shader;
 use std::collections::HashMap;
 
+/// Format and output state of one color attachment.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ColorTargetFormat {
+    pub format: wgpu::TextureFormat,
+    /// Overrides the material's blend mode.
+    pub blend: Option<BlendMode>,
+    pub write_mask: wgpu::ColorWrites,
+}
+
+/// Attachment formats of the render pass a pipeline will be used in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TargetFormats {
+    pub color_targets: Vec<ColorTargetFormat>,
+    pub depth_format: Option<wgpu::TextureFormat>,
+    pub sample_count: u32,
+    /// Depth is stored reversed (1.0 near, 0.0 far): depth compares and bias are flipped.
+    pub reverse_z: bool,
+}
+
+/// Which entry points a pipeline is built from and what it writes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PipelineVariant {
+    /// The shader's `fs_main`, writing the pass's color attachments.
+    #[default]
+    Color,
+    /// The shader's G-buffer entry point, writing the G-buffer attachments.
+    GBuffer,
+    /// Vertex stage only, writing depth into a shadow map. The fragment stage is skipped,
+    /// so alpha-masked materials cast shadows of their whole geometry.
+    ShadowDepth,
+}
+
+/// Everything that distinguishes one render pipeline from another.
+///
+/// Materials sharing a shader and drawn with the same vertex layout into
+/// the same kind of target share a pipeline.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: u64,
+    pub vertex_layout: u64,
+    pub targets: TargetFormats,
+    pub render_state: RenderState,
+    pub variant: PipelineVariant,
+}
+
 pub struct Pipelines {
-    format: wgpu::TextureFormat,
-    map: HashMap<ResourceKey, wgpu::RenderPipeline>,
+    map: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    // keyed by shader and alpha cutoff: the GL backend caches linked programs per module
+    // and ignores override constants, so each cutoff needs its own module
+    modules: HashMap<(u64, Option<u32>), wgpu::ShaderModule>,
 }
 
 impl Pipelines {
-    pub fn new(format: wgpu::TextureFormat) -> Self {
+    pub fn new() -> Self {
         Self {
-            format,
             map: HashMap::new(),
+            modules: HashMap::new(),
         }
     }
 
     pub fn set_pipeline(
         &mut self,
         device: &wgpu::Device,
+        key: &PipelineKey,
         geometry_desc: &GeometryShaderDesc,
-        material_handle: &MaterialHandle,
+        shader: &Shader,
         bindgroup_layout: &[&wgpu::BindGroupLayout],
-        resources: &crate::Resources,
     ) -> &wgpu::RenderPipeline {
-        match self.map.entry(material_handle.raw()) {
-            std::collections::hash_map::Entry::Occupied(o) => o.into_mut(),
-            std::collections::hash_map::Entry::Vacant(v) => {
-                let material = resources.get_material(material_handle).unwrap();
-
-                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("Shader"),
-                    source: wgpu::ShaderSource::Wgsl(material.shader().source().into()),
+        if !self.map.contains_key(key) {
+            let module = self
+                .modules
+                .entry((key.shader, key.render_state.alpha_cutoff.map(f32::to_bits)))
+                .or_insert_with(|| {
+                    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("Shader"),
+                        source: wgpu::ShaderSource::Wgsl(shader.source().into()),
+                    })
                 });
+            let fragment_entry_point = match key.variant {
+                PipelineVariant::Color => Some("fs_main"),
+                PipelineVariant::GBuffer => Some(shader.gbuffer_entry_point().unwrap_or("fs_main")),
+                PipelineVariant::ShadowDepth => None,
+            };
+            let pipeline = create_pipeline(
+                device,
+                key,
+                geometry_desc,
+                module,
+                fragment_entry_point,
+                bindgroup_layout,
+            );
+            self.map.insert(key.clone(), pipeline);
+        }
+        &self.map[key]
+    }
+}
 
-                let _ = material.shader().vertex_schema();
-
-                let pipeline_layout =
-                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                        label: Some("Render Pipeline Layout"),
-                        bind_group_layouts: bindgroup_layout,
-                        push_constant_ranges: &[],
-                    });
-
-                let mut attributes_storage: Vec<Vec<wgpu::VertexAttribute>> = Vec::new();
-                for desc in geometry_desc.layouts().iter() {
-                    let attrs: Vec<wgpu::VertexAttribute> = desc
-                        .attributes
-                        .iter()
-                        .map(|attr| wgpu::VertexAttribute {
-                            format: attr.format,
-                            offset: attr.offset,
-                            shader_location: attr.shader_location,
-                        })
-                        .collect();
-                    attributes_storage.push(attrs);
-                }
-
-                let mut vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout> =
-                    Vec::with_capacity(attributes_storage.len());
-                for (i, desc) in geometry_desc.layouts().iter().enumerate() {
-                    let attr_ref: &[wgpu::VertexAttribute] = attributes_storage[i].as_slice();
-                    vertex_buffer_layouts.push(wgpu::VertexBufferLayout {
-                        array_stride: desc.array_stride,
-                        step_mode: desc.step_mode,
-                        attributes: attr_ref,
-                    });
-                }
-
-                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("Render Pipeline"),
-                    layout: Some(&pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        compilation_options: Default::default(),
-                        entry_point: Some("vs_main"),
-                        buffers: &vertex_buffer_layouts,
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        // 3.
-                        module: &shader,
-                        compilation_options: Default::default(),
-                        entry_point: Some("fs_main"),
-                        targets: &[Some(wgpu::ColorTargetState {
-                            // 4.
-                            format: self.format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList, // 1.
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw, // 2.
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        // Requires Features::DEPTH_CLIP_CONTROL
-                        unclipped_depth: false,
-                        // Requires Features::CONSERVATIVE_RASTERIZATION
-                        conservative: false,
-                    },
-                    depth_stencil: Some(wgpu::DepthStencilState {
-                        format: wgpu::TextureFormat::Depth24Plus,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::LessEqual,
-                        stencil: wgpu::StencilState::default(),
-                        bias: wgpu::DepthBiasState {
-                            constant: 0,
-                            slope_scale: 0.0,
-                            clamp: 0.0,
-                        },
-                    }), // 1.
-                    multisample: wgpu::MultisampleState {
-                        count: 1,                         // 2.
-                        mask: !0,                         // 3.
-                        alpha_to_coverage_enabled: false, // 4.
-                    },
-                    multiview: None, // 5.
-                    cache: None,
-                });
+/// The compare function testing the same relation on reversed depth.
+fn reverse_compare(compare: wgpu::CompareFunction) -> wgpu::CompareFunction {
+    use wgpu::CompareFunction::*;
+    match compare {
+        Less => Greater,
+        LessEqual => GreaterEqual,
+        Greater => Less,
+        GreaterEqual => LessEqual,
+        other => other,
+    }
+}
 
-                println!("Created new pipeline");
+/// The depth bias pushing depth the same way on reversed depth.
+fn reverse_depth_bias(bias: wgpu::DepthBiasState) -> wgpu::DepthBiasState {
+    wgpu::DepthBiasState {
+        constant: -bias.constant,
+        slope_scale: -bias.slope_scale,
+        clamp: bias.clamp,
+    }
+}
 
-                v.insert(pipeline)
-            }
+/// Depth and stencil state of `render_state` drawn into a `format` attachment.
+fn depth_stencil_state(
+    format: wgpu::TextureFormat,
+    render_state: &RenderState,
+    reverse_z: bool,
+) -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format,
+        depth_write_enabled: render_state.depth_write,
+        depth_compare: if reverse_z {
+            reverse_compare(render_state.depth_compare)
+        } else {
+            render_state.depth_compare
+        },
+        // formats without a stencil aspect reject an enabled stencil test
+        stencil: if format.has_stencil_aspect() {
+            render_state.stencil.to_wgpu()
+        } else {
+            wgpu::StencilState::default()
+        },
+        bias: if reverse_z {
+            reverse_depth_bias(render_state.depth_bias)
+        } else {
+            render_state.depth_bias
+        },
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    key: &PipelineKey,
+    geometry_desc: &GeometryShaderDesc,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: Option<&str>,
+    bindgroup_layout: &[&wgpu::BindGroupLayout],
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Render Pipeline Layout"),
+        bind_group_layouts: bindgroup_layout,
+        push_constant_ranges: &[],
+    });
+
+    let mut attributes_storage: Vec<Vec<wgpu::VertexAttribute>> = Vec::new();
+    for desc in geometry_desc.layouts().iter() {
+        let attrs: Vec<wgpu::VertexAttribute> = desc
+            .attributes
+            .iter()
+            .map(|attr| wgpu::VertexAttribute {
+                format: attr.format,
+                offset: attr.offset,
+                shader_location: attr.shader_location,
+            })
+            .collect();
+        attributes_storage.push(attrs);
+    }
+
+    let mut vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout> =
+        Vec::with_capacity(attributes_storage.len());
+    for (i, desc) in geometry_desc.layouts().iter().enumerate() {
+        let attr_ref: &[wgpu::VertexAttribute] = attributes_storage[i].as_slice();
+        vertex_buffer_layouts.push(wgpu::VertexBufferLayout {
+            array_stride: desc.array_stride,
+            step_mode: desc.step_mode,
+            attributes: attr_ref,
+        });
+    }
+
+    let render_state = &key.render_state;
+
+    let constants = match render_state.alpha_cutoff {
+        Some(cutoff) => vec![("alpha_cutoff", cutoff as f64)],
+        None => vec![],
+    };
+
+    let color_targets = key
+        .targets
+        .color_targets
+        .iter()
+        .map(|target| {
+            // integer and some float formats reject any blend state
+            let blendable = target
+                .format
+                .guaranteed_format_features(device.features())
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE);
+            let blend = target.blend.unwrap_or(render_state.blend);
+            Some(wgpu::ColorTargetState {
+                format: target.format,
+                blend: blendable.then(|| blend.blend_state()),
+                write_mask: target.write_mask,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            compilation_options: Default::default(),
+            entry_point: Some("vs_main"),
+            buffers: &vertex_buffer_layouts,
+        },
+        fragment: fragment_entry_point.map(|entry_point| wgpu::FragmentState {
+            // 3.
+            module: shader,
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants: &constants,
+                ..Default::default()
+            },
+            entry_point: Some(entry_point),
+            targets: &color_targets,
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: render_state.topology,
+            strip_index_format: None,
+            front_face: render_state.front_face,
+            cull_mode: render_state.cull_mode,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: key.targets.depth_format.map(|format| match key.variant {
+            // shadow maps keep the nearest depth, whatever the material's depth state
+            PipelineVariant::ShadowDepth => wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            },
+            _ => depth_stencil_state(format, render_state, key.targets.reverse_z),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: key.targets.sample_count,  // 2.
+            mask: !0,                         // 3.
+            alpha_to_coverage_enabled: false, // 4.
+        },
+        multiview: None, // 5.
+        cache: None,
+    });
+
+    println!("Created new pipeline");
+
+    pipeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn key(render_state: RenderState) -> PipelineKey {
+        PipelineKey {
+            shader: 1,
+            vertex_layout: 2,
+            targets: TargetFormats {
+                color_targets: vec![ColorTargetFormat {
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+                depth_format: Some(wgpu::TextureFormat::Depth24Plus),
+                sample_count: 1,
+                reverse_z: false,
+            },
+            render_state,
+            variant: PipelineVariant::Color,
         }
     }
 
-    // pub fn get_pipeline(&self) -> &wgpu::RenderPipeline {
-    //     &self.pipeline
-    // }
+    #[test]
+    fn test_reverse_compare() {
+        use wgpu::CompareFunction::*;
+        assert_eq!(reverse_compare(Less), Greater);
+        assert_eq!(reverse_compare(LessEqual), GreaterEqual);
+        assert_eq!(reverse_compare(Greater), Less);
+        assert_eq!(reverse_compare(Always), Always);
+    }
+
+    #[test]
+    fn test_reverse_depth_bias() {
+        let bias = wgpu::DepthBiasState {
+            constant: 2,
+            slope_scale: 1.5,
+            clamp: 0.01,
+        };
+        assert_eq!(
+            reverse_depth_bias(bias),
+            wgpu::DepthBiasState {
+                constant: -2,
+                slope_scale: -1.5,
+                clamp: 0.01,
+            }
+        );
+        assert_eq!(reverse_depth_bias(reverse_depth_bias(bias)), bias);
+    }
+
+    #[test]
+    fn test_reverse_z_depth_stencil_state() {
+        let render_state = RenderState {
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            depth_bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 1.5,
+                clamp: 0.0,
+            },
+            ..RenderState::default()
+        };
+        let format = wgpu::TextureFormat::Depth32Float;
+
+        let state = depth_stencil_state(format, &render_state, false);
+        assert_eq!(state.depth_compare, wgpu::CompareFunction::LessEqual);
+        assert_eq!(state.bias, render_state.depth_bias);
+
+        let state = depth_stencil_state(format, &render_state, true);
+        assert_eq!(state.depth_compare, wgpu::CompareFunction::GreaterEqual);
+        assert_eq!(state.bias, reverse_depth_bias(render_state.depth_bias));
+    }
+
+    #[test]
+    fn test_alpha_cutoffs_key_separate_pipelines() {
+        let keys: HashSet<_> = [
+            RenderState::masked(0.5),
+            RenderState::masked(0.5),
+            RenderState::masked(0.25),
+            RenderState::default(),
+        ]
+        .into_iter()
+        .map(key)
+        .collect();
+        assert_eq!(keys.len(), 3);
+    }
+
+    #[test]
+    fn test_presets_map_to_wgpu_states() {
+        let format = wgpu::TextureFormat::Depth24Plus;
+
+        let opaque = RenderState::default();
+        let depth = depth_stencil_state(format, &opaque, false);
+        assert_eq!(opaque.blend.blend_state(), wgpu::BlendState::REPLACE);
+        assert_eq!(depth.depth_compare, wgpu::CompareFunction::LessEqual);
+        assert!(depth.depth_write_enabled);
+
+        let transparent = RenderState::transparent();
+        let depth = depth_stencil_state(format, &transparent, false);
+        assert_eq!(
+            transparent.blend.blend_state(),
+            wgpu::BlendState::ALPHA_BLENDING
+        );
+        assert_eq!(depth.depth_compare, wgpu::CompareFunction::LessEqual);
+        assert!(!depth.depth_write_enabled);
+
+        let masked = RenderState::masked(0.5);
+        let depth = depth_stencil_state(format, &masked, false);
+        assert_eq!(masked.blend.blend_state(), wgpu::BlendState::REPLACE);
+        assert_eq!(masked.cull_mode, None);
+        assert!(depth.depth_write_enabled);
+
+        let overlay = RenderState::overlay();
+        let depth = depth_stencil_state(format, &overlay, false);
+        assert_eq!(
+            overlay.blend.blend_state(),
+            wgpu::BlendState::ALPHA_BLENDING
+        );
+        assert_eq!(depth.depth_compare, wgpu::CompareFunction::Always);
+        assert!(!depth.depth_write_enabled);
+    }
 }
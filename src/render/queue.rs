@@ -0,0 +1,300 @@
+use crate::ResourceKey;
+use crate::material::{BlendMode, Material, RenderState};
+use crate::math::Mat4;
+use crate::primitive::Primitive;
+use std::collections::HashMap;
+
+const PASS_OPAQUE: u64 = 0;
+const PASS_TRANSPARENT: u64 = 1;
+
+const PASS_BITS: u32 = 2;
+const PIPELINE_BITS: u32 = 14;
+const MATERIAL_BITS: u32 = 16;
+const GEOMETRY_BITS: u32 = 16;
+const DEPTH_BITS: u32 = 16;
+
+struct QueueItem<'a> {
+    primitive: &'a Primitive,
+    pass: u64,
+    pipeline: u32,
+    material: u32,
+    geometry: u32,
+    depth: f32,
+}
+
+/// Draw order for one `Renderer::render` call.
+///
+/// Every primitive gets a packed 64-bit sort key, and the keys are radix sorted:
+/// - Opaque (`BlendMode::Opaque`, alpha-masked included): `pass | pipeline | material | geometry | depth`,
+///   so state changes are minimized and equal geometry/material pairs end up adjacent for instancing,
+///   with front-to-back order inside each group.
+/// - Transparent (every other blend mode): `pass | far-to-near depth | pipeline | material | geometry`,
+///   so blending composites correctly.
+///
+/// Pipeline, material and geometry are dense per-frame indices. When a frame has more distinct
+/// values than a field can hold the indices wrap, which only costs batching, not correctness.
+pub struct RenderQueue<'a> {
+    items: Vec<QueueItem<'a>>,
+    order: Vec<u32>,
+    pipelines: HashMap<(u64, RenderState), u32>,
+    materials: HashMap<ResourceKey, u32>,
+    geometries: HashMap<ResourceKey, u32>,
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            order: Vec::new(),
+            pipelines: HashMap::new(),
+            materials: HashMap::new(),
+            geometries: HashMap::new(),
+        }
+    }
+
+    /// Queues `primitive`; `view` is the camera's world-to-view matrix.
+    pub fn push(&mut self, primitive: &'a Primitive, material: &Material, view: &Mat4) {
+        // distance in front of the camera along the view direction
+        let depth = -view
+            .transform_point3(primitive.transform().w_axis.truncate())
+            .z;
+
+        let render_state = material.render_state();
+        let pass = if render_state.blend == BlendMode::Opaque {
+            PASS_OPAQUE
+        } else {
+            PASS_TRANSPARENT
+        };
+
+        let pipeline = dense_index(&mut self.pipelines, (material.shader().id(), *render_state));
+        let material = dense_index(&mut self.materials, primitive.material().raw());
+        let geometry = dense_index(&mut self.geometries, primitive.geometry().raw());
+
+        self.items.push(QueueItem {
+            primitive,
+            pass,
+            pipeline,
+            material,
+            geometry,
+            depth,
+        });
+    }
+
+    /// Builds the sort keys and sorts the queue into draw order.
+    pub fn sort(&mut self) {
+        let (near, far) = self
+            .items
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(near, far), item| {
+                (near.min(item.depth), far.max(item.depth))
+            });
+
+        let mut keys = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let depth = quantize_depth(item.depth, near, far);
+                let key = if item.pass == PASS_OPAQUE {
+                    pack_key(&[
+                        (item.pass, PASS_BITS),
+                        (item.pipeline as u64, PIPELINE_BITS),
+                        (item.material as u64, MATERIAL_BITS),
+                        (item.geometry as u64, GEOMETRY_BITS),
+                        (depth, DEPTH_BITS),
+                    ])
+                } else {
+                    pack_key(&[
+                        (item.pass, PASS_BITS),
+                        (!depth, DEPTH_BITS),
+                        (item.pipeline as u64, PIPELINE_BITS),
+                        (item.material as u64, MATERIAL_BITS),
+                        (item.geometry as u64, GEOMETRY_BITS),
+                    ])
+                };
+                (key, index as u32)
+            })
+            .collect::<Vec<_>>();
+
+        radix_sort(&mut keys);
+
+        self.order = keys.into_iter().map(|(_, index)| index).collect();
+    }
+
+    /// Queued primitives in draw order. Call [`RenderQueue::sort`] first.
+    pub fn primitives(&self) -> impl Iterator<Item = &'a Primitive> + '_ {
+        self.order
+            .iter()
+            .map(|index| self.items[*index as usize].primitive)
+    }
+}
+
+fn dense_index<K: std::hash::Hash + Eq>(map: &mut HashMap<K, u32>, key: K) -> u32 {
+    let next = map.len() as u32;
+    *map.entry(key).or_insert(next)
+}
+
+/// Maps `depth` in `[near, far]` onto the full 16-bit range.
+fn quantize_depth(depth: f32, near: f32, far: f32) -> u64 {
+    let range = far - near;
+    if !range.is_finite() || range <= 0.0 {
+        return 0;
+    }
+    let t = ((depth - near) / range).clamp(0.0, 1.0);
+    (t * ((1u32 << DEPTH_BITS) - 1) as f32) as u64
+}
+
+/// Packs `(value, bits)` fields from most to least significant; values are truncated to their width.
+fn pack_key(fields: &[(u64, u32)]) -> u64 {
+    fields.iter().fold(0u64, |key, (value, bits)| {
+        (key << bits) | (value & ((1u64 << bits) - 1))
+    })
+}
+
+/// Stable LSD radix sort on the 64-bit key, one byte per pass.
+/// Passes where every key has the same byte are skipped.
+fn radix_sort(items: &mut Vec<(u64, u32)>) {
+    let mut scratch = vec![(0u64, 0u32); items.len()];
+
+    for shift in (0..64).step_by(8) {
+        let mut counts = [0usize; 256];
+        for (key, _) in items.iter() {
+            counts[((key >> shift) & 0xff) as usize] += 1;
+        }
+        if counts.contains(&items.len()) {
+            continue;
+        }
+
+        let mut offset = 0;
+        for count in counts.iter_mut() {
+            let c = *count;
+            *count = offset;
+            offset += c;
+        }
+
+        for item in items.iter() {
+            let digit = ((item.0 >> shift) & 0xff) as usize;
+            scratch[counts[digit]] = *item;
+            counts[digit] += 1;
+        }
+
+        std::mem::swap(items, &mut scratch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resources;
+    use crate::geometry::Geometry;
+    use crate::math::Vec3;
+    use crate::shader::builtins::unlit_shader;
+
+    /// Distances from a camera at the origin looking down -Z, in draw order.
+    fn draw_order(primitives: &[Primitive], resources: &Resources) -> Vec<f32> {
+        let mut queue = RenderQueue::new();
+        for primitive in primitives {
+            let material = resources.get_material(primitive.material()).unwrap();
+            queue.push(primitive, material, &Mat4::IDENTITY);
+        }
+        queue.sort();
+        queue
+            .primitives()
+            .map(|primitive| -primitive.transform().w_axis.z)
+            .collect()
+    }
+
+    fn primitives_at(
+        resources: &mut Resources,
+        render_state: RenderState,
+        distances: &[f32],
+    ) -> Vec<Primitive> {
+        let geometry = resources.insert_geometry(Geometry::new());
+        let mut material = Material::from_shader(unlit_shader());
+        material.set_render_state(render_state);
+        let material = resources.insert_material(material);
+        distances
+            .iter()
+            .map(|distance| {
+                let mut primitive = Primitive::new(geometry.clone(), material.clone());
+                primitive.set_transform(Mat4::from_translation(Vec3::new(0.0, 0.0, -distance)));
+                primitive
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_opaque_primitives_draw_front_to_back() {
+        let mut resources = Resources::default();
+        let primitives = primitives_at(
+            &mut resources,
+            RenderState::default(),
+            &[5.0, 1.0, 3.0, 2.0],
+        );
+        assert_eq!(
+            draw_order(&primitives, &resources),
+            vec![1.0, 2.0, 3.0, 5.0]
+        );
+    }
+
+    #[test]
+    fn test_transparent_primitives_draw_back_to_front_after_opaque() {
+        let mut resources = Resources::default();
+        let mut primitives =
+            primitives_at(&mut resources, RenderState::transparent(), &[2.0, 6.0, 4.0]);
+        primitives.extend(primitives_at(
+            &mut resources,
+            RenderState::default(),
+            &[7.0, 3.0],
+        ));
+        // alpha-masked materials are opaque
+        primitives.extend(primitives_at(
+            &mut resources,
+            RenderState::masked(0.5),
+            &[1.0],
+        ));
+
+        let order = draw_order(&primitives, &resources);
+        let (opaque, transparent) = order.split_at(3);
+        // opaque primitives are grouped by state before depth
+        let mut opaque = opaque.to_vec();
+        opaque.sort_by(f32::total_cmp);
+        assert_eq!(opaque, vec![1.0, 3.0, 7.0]);
+        assert_eq!(transparent, &[6.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn test_radix_sort_is_stable() {
+        let mut items = vec![
+            (3u64 << 40, 0u32),
+            (1, 1),
+            (u64::MAX, 2),
+            (1, 3),
+            (0, 4),
+            (3 << 40, 5),
+        ];
+        radix_sort(&mut items);
+
+        let order = items.iter().map(|(_, index)| *index).collect::<Vec<_>>();
+        assert_eq!(order, vec![4, 1, 3, 0, 5, 2]);
+    }
+
+    #[test]
+    fn test_pack_key_orders_fields() {
+        let a = pack_key(&[(0, PASS_BITS), (1, PIPELINE_BITS), (0, DEPTH_BITS)]);
+        let b = pack_key(&[(0, PASS_BITS), (0, PIPELINE_BITS), (0xffff, DEPTH_BITS)]);
+        let c = pack_key(&[(1, PASS_BITS), (0, PIPELINE_BITS), (0, DEPTH_BITS)]);
+        assert!(b < a && a < c);
+
+        // values wider than their field are truncated instead of spilling into the next one
+        let d = pack_key(&[(0, PASS_BITS), (1 << PIPELINE_BITS, PIPELINE_BITS)]);
+        assert_eq!(d, 0);
+    }
+
+    #[test]
+    fn test_quantize_depth() {
+        assert_eq!(quantize_depth(1.0, 1.0, 5.0), 0);
+        assert_eq!(quantize_depth(5.0, 1.0, 5.0), 0xffff);
+        assert_eq!(quantize_depth(3.0, 3.0, 3.0), 0);
+    }
+}
@@ -0,0 +1,139 @@
+use super::RendererError;
+use super::textures::bytes_layout_for_level;
+use std::sync::Arc;
+
+/// Pixels copied back from a GPU texture.
+///
+/// `bytes` is tightly packed: rows are `width` texels wide with no row-pitch
+/// padding, starting at the top-left texel.
+#[derive(Clone, Debug)]
+pub struct TexturePixels {
+    pub bytes: Vec<u8>,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Copies mip level 0 / layer 0 of `texture` into a staging buffer and maps it.
+///
+/// Blocks until the GPU has finished the copy. The texture must have been
+/// created with `COPY_SRC` usage.
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<TexturePixels, RendererError> {
+    let format = texture.format();
+    let width = texture.width();
+    let height = texture.height();
+
+    let (bytes_per_row, rows_per_image) = bytes_layout_for_level(format, width, height)
+        .ok_or(RendererError::UnsupportedCopyFormat(format))?;
+
+    // copies into buffers need each row aligned to COPY_BYTES_PER_ROW_ALIGNMENT
+    let padded_bytes_per_row = bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * rows_per_image) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &staging_buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(rows_per_image),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    let submission_index = queue.submit(Some(encoder.finish()));
+
+    let buffer_slice = staging_buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+
+    device
+        .poll(wgpu::PollType::wait_for(submission_index))
+        .map_err(|err| RendererError::Poll(Arc::new(err)))?;
+    receiver
+        .recv()
+        .map_err(|_| RendererError::BufferMap(wgpu::BufferAsyncError))?
+        .map_err(RendererError::BufferMap)?;
+
+    let bytes = strip_row_padding(
+        &buffer_slice.get_mapped_range(),
+        bytes_per_row,
+        padded_bytes_per_row,
+    );
+    staging_buffer.unmap();
+
+    Ok(TexturePixels {
+        bytes,
+        format,
+        width,
+        height,
+    })
+}
+
+/// Drops the padding at the end of every `padded_bytes_per_row` row, keeping `bytes_per_row`.
+fn strip_row_padding(padded: &[u8], bytes_per_row: u32, padded_bytes_per_row: u32) -> Vec<u8> {
+    let rows = padded.len() / padded_bytes_per_row as usize;
+    let mut bytes = Vec::with_capacity(rows * bytes_per_row as usize);
+    for row in padded.chunks(padded_bytes_per_row as usize) {
+        bytes.extend_from_slice(&row[..bytes_per_row as usize]);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_row_padding() {
+        // 3 rows of 100 Rgba8 texels: 400 bytes padded to 512
+        let bytes_per_row: u32 = 400;
+        let padded_bytes_per_row =
+            bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        assert_eq!(padded_bytes_per_row, 512);
+
+        let padded: Vec<u8> = (0..3u8)
+            .flat_map(|row| {
+                let mut bytes = vec![row; bytes_per_row as usize];
+                bytes.resize(padded_bytes_per_row as usize, 0xff);
+                bytes
+            })
+            .collect();
+
+        let bytes = strip_row_padding(&padded, bytes_per_row, padded_bytes_per_row);
+        assert_eq!(bytes.len(), 3 * 400);
+        assert!(
+            bytes
+                .chunks(400)
+                .enumerate()
+                .all(|(row, bytes)| bytes.iter().all(|byte| *byte == row as u8))
+        );
+    }
+}
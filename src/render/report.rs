@@ -0,0 +1,65 @@
+use super::{FrameStatus, RendererError};
+
+/// Summary of one `Renderer::render` call.
+#[derive(Debug, Default)]
+pub struct RenderReport {
+    /// Whether the frame was rendered, rendered after a surface recovery, or skipped.
+    pub status: FrameStatus,
+    /// Number of primitives that were drawn.
+    pub drawn: usize,
+    /// Number of primitives outside the camera frustum that were not drawn.
+    pub culled: usize,
+    /// Number of instanced draw calls issued.
+    pub draw_calls: usize,
+    /// Number of times a different pipeline was bound.
+    pub pipeline_switches: usize,
+    /// Number of times a different material bind group was bound.
+    pub bind_group_switches: usize,
+    /// Number of times the vertex buffers were rebound.
+    pub vertex_buffer_switches: usize,
+    /// Primitives that were skipped, as (index into the input slice, reason).
+    pub skipped: Vec<(usize, RendererError)>,
+}
+
+impl RenderReport {
+    /// Adds the counts of `other`, a report of another pass of the same frame.
+    /// Primitives skipped by both are listed once.
+    pub(super) fn merge(&mut self, other: RenderReport) {
+        self.drawn += other.drawn;
+        self.culled += other.culled;
+        self.draw_calls += other.draw_calls;
+        self.pipeline_switches += other.pipeline_switches;
+        self.bind_group_switches += other.bind_group_switches;
+        self.vertex_buffer_switches += other.vertex_buffer_switches;
+        for (index, err) in other.skipped {
+            if !self.skipped.iter().any(|(skipped, _)| *skipped == index) {
+                self.skipped.push((index, err));
+            }
+        }
+        self.skipped.sort_by_key(|(index, _)| *index);
+    }
+
+    /// Adds the draw calls and state switches of `other`, a pass of the same frame that
+    /// redraws primitives already counted, such as a shadow map pass.
+    pub(super) fn merge_draws(&mut self, other: &RenderReport) {
+        self.draw_calls += other.draw_calls;
+        self.pipeline_switches += other.pipeline_switches;
+        self.bind_group_switches += other.bind_group_switches;
+        self.vertex_buffer_switches += other.vertex_buffer_switches;
+    }
+}
+
+/// Summary of one `Renderer::execute` call.
+#[derive(Debug, Default)]
+pub struct GraphReport {
+    /// Whether the frame was rendered, rendered after a surface recovery, or skipped.
+    pub status: FrameStatus,
+    /// Name and report of every shadow map pass, which run before `passes`.
+    pub shadow_passes: Vec<(String, RenderReport)>,
+    /// Name and report of every pass that ran, in execution order.
+    pub passes: Vec<(String, RenderReport)>,
+    /// Names of the passes that were culled because nothing uses their output.
+    pub culled_passes: Vec<String>,
+    /// Number of GPU textures backing the graph's transient textures.
+    pub transient_textures: usize,
+}
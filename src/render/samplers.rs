@@ -3,6 +3,7 @@ use std::collections::HashMap;
 
 pub struct Samplers {
     default_sampler: Sampler,
+    default_comparison_sampler: Sampler,
     hash_map: HashMap<Sampler, wgpu::Sampler>,
 }
 
@@ -10,9 +11,15 @@ impl Samplers {
     pub fn new(device: &wgpu::Device) -> Self {
         let mut samplers = Self {
             default_sampler: Sampler::default(),
+            // linear filtering blends the comparison results of neighboring texels
+            default_comparison_sampler: Sampler {
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                ..Default::default()
+            },
             hash_map: HashMap::new(),
         };
         samplers.prepare(device, samplers.default_sampler);
+        samplers.prepare(device, samplers.default_comparison_sampler);
         samplers
     }
 
@@ -42,4 +49,9 @@ impl Samplers {
     pub fn get_default_gpu_sampler(&self) -> &wgpu::Sampler {
         self.hash_map.get(&self.default_sampler).unwrap()
     }
+
+    /// Bound to comparison sampler bindings without a sampler, and used for shadow maps.
+    pub fn get_default_gpu_comparison_sampler(&self) -> &wgpu::Sampler {
+        self.hash_map.get(&self.default_comparison_sampler).unwrap()
+    }
 }
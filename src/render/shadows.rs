@@ -0,0 +1,702 @@
+use super::config::ShadowConfig;
+use super::textures::{array_layers_to_allocate, cube_array_layers_to_allocate};
+use crate::camera::{Camera, PerspectiveProjection, Projection, Viewport};
+use crate::geometry::BoundingSphere;
+use crate::light::{Light, LightKind};
+use crate::math::{Mat4, Vec3};
+
+/// Most cascades a directional light can have, the array length of `ShadowUniform::cascades`.
+pub const MAX_CASCADES: usize = 4;
+/// Most point lights with shadows, the array length of `ShadowUniform::point_params`.
+pub const MAX_POINT_SHADOWS: usize = 8;
+/// Most spot lights with shadows, the array length of `ShadowUniform::spot_matrices`.
+pub const MAX_SPOT_SHADOWS: usize = 16;
+
+pub const SHADOW_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Near plane distance of point and spot light shadow cameras.
+const LOCAL_NEAR: f32 = 0.05;
+
+/// Forward and up directions of the cameras rendering the cube faces, in layer order.
+///
+/// The cube map face layout follows GL, which stores rendered images bottom row first;
+/// wgpu stores them top row first, so faces rendered by these cameras come out flipped
+/// vertically. Instead of mirroring the cameras, `shader/wgsl/shadows.wgsl` samples with y negated; that swaps
+/// the ±Y faces, so layer 2 (+Y) looks down and layer 3 (-Y) looks up.
+pub const CUBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::NEG_Y),
+    (Vec3::NEG_X, Vec3::NEG_Y),
+    (Vec3::NEG_Y, Vec3::NEG_Z),
+    (Vec3::Y, Vec3::Z),
+    (Vec3::Z, Vec3::NEG_Y),
+    (Vec3::NEG_Z, Vec3::NEG_Y),
+];
+
+/// One cascade of a directional light's shadow map.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cascade {
+    /// Orthographic light-space camera the cascade is rendered from.
+    pub camera: Camera,
+    /// World-space size of one shadow map texel.
+    pub texel_size: f32,
+}
+
+/// View depths splitting `near..far` into `count` slices, `count + 1` values from `near` to `far`.
+///
+/// Blends logarithmic splits, which keep the texel density per view distance constant,
+/// with uniform ones by `lambda`.
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    // the logarithmic scheme needs a positive near distance
+    let log_near = near.max(far * 1e-4).max(f32::EPSILON);
+    (0..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let logarithmic = log_near * (far / log_near).powf(t);
+            let uniform = near + (far - near) * t;
+            if i == 0 {
+                near
+            } else if i == count {
+                far
+            } else {
+                uniform + (logarithmic - uniform) * lambda.clamp(0.0, 1.0)
+            }
+        })
+        .collect()
+}
+
+/// Fits the cascades of a light travelling along `direction` to the view of `camera`.
+///
+/// Every cascade covers the bounding sphere of its slice of the camera frustum, so its size
+/// does not change when the camera turns, and moves in whole texels, so shadow edges do not
+/// shimmer when the camera moves. The light-space near plane is pulled back to include
+/// `casters` outside the slice that may still shadow it.
+pub fn fit_cascades(
+    camera: &Camera,
+    direction: Vec3,
+    config: &ShadowConfig,
+    casters: &[BoundingSphere],
+) -> Vec<Cascade> {
+    let count = (config.cascade_count as usize).clamp(1, MAX_CASCADES);
+    let resolution = config.resolution.max(1) as f32;
+
+    // view depths of the near and far planes; infinite projections have no finite far plane
+    let inverse_projection = camera.projection().inverse();
+    let unproject = |x, y, z| inverse_projection.project_point3(Vec3::new(x, y, z));
+    let plane_depths = [0.0, 1.0].map(|z| -unproject(0.0, 0.0, z).z);
+    let near = plane_depths
+        .into_iter()
+        .filter(|depth| depth.is_finite())
+        .fold(f32::INFINITY, f32::min)
+        .max(0.0);
+    let far = if plane_depths.iter().all(|depth| depth.is_finite()) {
+        plane_depths[0].max(plane_depths[1])
+    } else {
+        f32::INFINITY
+    }
+    .min(config.max_distance);
+    if !near.is_finite() || far <= near {
+        return Vec::new();
+    }
+
+    // two points on each corner ray, interpolated by view depth (works for any projection)
+    let rays = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .map(|(x, y)| (unproject(x, y, 0.25), unproject(x, y, 0.75)));
+    let corner = |(a, b): (Vec3, Vec3), depth: f32| {
+        let t = (depth + a.z) / (a.z - b.z);
+        camera.transform().transform_point3(a + (b - a) * t)
+    };
+
+    let direction = direction.normalize_or(Vec3::NEG_Y);
+    let light_view = Mat4::look_to_rh(Vec3::ZERO, direction, up_for(direction));
+    let caster_near = casters
+        .iter()
+        .map(|caster| -light_view.transform_point3(caster.center).z - caster.radius)
+        .fold(f32::INFINITY, f32::min);
+
+    let splits = cascade_splits(near, far, count, config.split_lambda);
+    splits
+        .windows(2)
+        .map(|slice| {
+            let corners = rays
+                .iter()
+                .flat_map(|ray| [corner(*ray, slice[0]), corner(*ray, slice[1])])
+                .collect::<Vec<_>>();
+            let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(0.0f32, f32::max);
+            // rounded up so float noise does not change the texel size from frame to frame
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let texel_size = 2.0 * radius / resolution;
+            let light_center = light_view.transform_point3(center);
+            let x = (light_center.x / texel_size).floor() * texel_size;
+            let y = (light_center.y / texel_size).floor() * texel_size;
+            let depth = -light_center.z;
+
+            let projection = Mat4::orthographic_rh(
+                x - radius,
+                x + radius,
+                y - radius,
+                y + radius,
+                (depth - radius).min(caster_near),
+                depth + radius,
+            );
+            Cascade {
+                camera: Camera::new(light_view.inverse(), Projection::Custom(projection)),
+                texel_size,
+            }
+        })
+        .collect()
+}
+
+/// An up vector for cameras looking along `direction`, which must not be parallel to it.
+fn up_for(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+/// Depth range and field of view of a point or spot light's shadow camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LocalShadow {
+    /// World to clip space; for point lights, each face has its own (see [`CUBE_FACES`]).
+    pub view_projection: Mat4,
+    pub near: f32,
+    pub far: f32,
+    /// Tangent of half the field of view, 1 for cube faces.
+    pub tan_half_fov: f32,
+}
+
+/// Where a shadow view renders into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShadowTarget {
+    /// A layer of the cascade map.
+    Cascade(u32),
+    /// A layer of the point light map, six per light (one cube).
+    CubeFace(u32),
+    /// A tile of the spot light atlas.
+    AtlasTile(u32),
+}
+
+impl ShadowTarget {
+    pub fn name(&self) -> String {
+        match self {
+            ShadowTarget::Cascade(layer) => format!("Shadow Cascade {layer}"),
+            ShadowTarget::CubeFace(layer) => {
+                format!("Point Shadow {} Face {}", layer / 6, layer % 6)
+            }
+            ShadowTarget::AtlasTile(tile) => format!("Spot Shadow {tile}"),
+        }
+    }
+}
+
+/// A camera rendering one layer or tile of a shadow map.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowView {
+    pub camera: Camera,
+    pub target: ShadowTarget,
+}
+
+/// Shadows of the lights of a frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameShadows {
+    /// Shadow slot of every light, -1 for none: 0 for the cascades, the cube of a point
+    /// light, or the atlas tile of a spot light.
+    pub light_slots: Vec<i32>,
+    pub cascades: Vec<Cascade>,
+    /// By cube.
+    pub points: Vec<LocalShadow>,
+    /// By atlas tile.
+    pub spots: Vec<LocalShadow>,
+    /// Every view to render, cascades first.
+    pub views: Vec<ShadowView>,
+    /// Width and height of cube faces and atlas tiles.
+    pub local_resolution: u32,
+    /// Tiles per row and column of the spot light atlas.
+    pub atlas_columns: u32,
+}
+
+impl FrameShadows {
+    /// Viewport of `target` inside its shadow map.
+    pub fn viewport(&self, target: ShadowTarget, resolution: u32) -> Viewport {
+        match target {
+            ShadowTarget::Cascade(_) => {
+                Viewport::new(0.0, 0.0, resolution as f32, resolution as f32)
+            }
+            ShadowTarget::CubeFace(_) => {
+                let size = self.local_resolution as f32;
+                Viewport::new(0.0, 0.0, size, size)
+            }
+            ShadowTarget::AtlasTile(tile) => {
+                let size = self.local_resolution as f32;
+                let column = (tile % self.atlas_columns) as f32;
+                let row = (tile / self.atlas_columns) as f32;
+                Viewport::new(column * size, row * size, size, size)
+            }
+        }
+    }
+}
+
+/// Tiles per row and column of an atlas holding `count` tiles.
+pub fn atlas_columns(count: u32) -> u32 {
+    (count.max(1) as f32).sqrt().ceil() as u32
+}
+
+/// Assigns shadow maps to the `lights` that cast shadows, within the budgets of `config`,
+/// and lists the views to render.
+///
+/// The first directional light gets cascades fitted to `camera` (none without a camera),
+/// see [`fit_cascades`]. Point and spot light shadows reach to their range, or to
+/// `max_distance` for unlimited lights.
+pub fn plan_shadows(
+    lights: &[Light],
+    camera: Option<&Camera>,
+    config: &ShadowConfig,
+    casters: &[BoundingSphere],
+) -> FrameShadows {
+    let max_points = (config.max_point_lights as usize).min(MAX_POINT_SHADOWS);
+    let max_spots = (config.max_spot_lights as usize).min(MAX_SPOT_SHADOWS);
+    let mut shadows = FrameShadows {
+        light_slots: vec![-1; lights.len()],
+        local_resolution: config.local_resolution.max(1),
+        atlas_columns: atlas_columns(max_spots as u32),
+        ..Default::default()
+    };
+    let mut directional = false;
+
+    for (index, light) in lights.iter().enumerate() {
+        if !light.cast_shadows {
+            continue;
+        }
+        let far = light
+            .range
+            .unwrap_or(config.max_distance)
+            .max(LOCAL_NEAR * 2.0);
+        match light.kind {
+            LightKind::Directional if !directional => {
+                directional = true;
+                let Some(camera) = camera else {
+                    continue;
+                };
+                shadows.cascades = fit_cascades(camera, light.direction, config, casters);
+                if shadows.cascades.is_empty() {
+                    continue;
+                }
+                shadows.light_slots[index] = 0;
+                for (layer, cascade) in shadows.cascades.iter().enumerate() {
+                    shadows.views.push(ShadowView {
+                        camera: cascade.camera,
+                        target: ShadowTarget::Cascade(layer as u32),
+                    });
+                }
+            }
+            LightKind::Point if shadows.points.len() < max_points => {
+                let cube = shadows.points.len();
+                shadows.light_slots[index] = cube as i32;
+                shadows.points.push(LocalShadow {
+                    view_projection: Mat4::IDENTITY,
+                    near: LOCAL_NEAR,
+                    far,
+                    tan_half_fov: 1.0,
+                });
+                for (face, (forward, up)) in CUBE_FACES.iter().enumerate() {
+                    shadows.views.push(ShadowView {
+                        camera: Camera::new(
+                            Mat4::look_to_rh(light.position, *forward, *up).inverse(),
+                            PerspectiveProjection::new(90.0, 1.0, LOCAL_NEAR, far),
+                        ),
+                        target: ShadowTarget::CubeFace((cube * 6 + face) as u32),
+                    });
+                }
+            }
+            LightKind::Spot { outer_cone_deg, .. } if shadows.spots.len() < max_spots => {
+                let tile = shadows.spots.len();
+                let fov_deg = (outer_cone_deg * 2.0).clamp(1.0, 170.0);
+                let direction = light.direction.normalize_or(Vec3::NEG_Z);
+                let camera = Camera::new(
+                    Mat4::look_to_rh(light.position, direction, up_for(direction)).inverse(),
+                    PerspectiveProjection::new(fov_deg, 1.0, LOCAL_NEAR, far),
+                );
+                shadows.light_slots[index] = tile as i32;
+                shadows.spots.push(LocalShadow {
+                    view_projection: camera.view_projection(),
+                    near: LOCAL_NEAR,
+                    far,
+                    tan_half_fov: (fov_deg * 0.5).to_radians().tan(),
+                });
+                shadows.views.push(ShadowView {
+                    camera,
+                    target: ShadowTarget::AtlasTile(tile as u32),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    shadows
+}
+
+/// The shadow maps sampled by shaders: the cascades of the directional light,
+/// the point light cubes and the spot light atlas.
+///
+/// Point shadows are a `texture_depth_cube_array` with one cube per light, whose faces
+/// are rendered as layers in [`CUBE_FACES`] order.
+pub struct ShadowMaps {
+    pub cascades: ShadowMap,
+    /// Six layers per point light.
+    pub points: ShadowMap,
+    pub atlas: ShadowMap,
+}
+
+impl ShadowMaps {
+    pub fn new(device: &wgpu::Device, backend: wgpu::Backend) -> Self {
+        use wgpu::TextureViewDimension::{CubeArray, D2, D2Array};
+        Self {
+            cascades: ShadowMap::new(device, backend, "Cascade Shadow Map", D2Array),
+            points: ShadowMap::new(device, backend, "Point Shadow Map", CubeArray),
+            atlas: ShadowMap::new(device, backend, "Spot Shadow Atlas", D2),
+        }
+    }
+
+    /// Makes room for every view of `shadows`, with cascades of `resolution` texels.
+    pub fn ensure(&mut self, device: &wgpu::Device, shadows: &FrameShadows, resolution: u32) {
+        if !shadows.cascades.is_empty() {
+            self.cascades
+                .ensure(device, resolution, shadows.cascades.len() as u32);
+        }
+        if !shadows.points.is_empty() {
+            self.points.ensure(
+                device,
+                shadows.local_resolution,
+                shadows.points.len() as u32 * 6,
+            );
+        }
+        if !shadows.spots.is_empty() {
+            self.atlas
+                .ensure(device, shadows.local_resolution * shadows.atlas_columns, 1);
+        }
+    }
+
+    /// The map `target` renders into, and the view of its layer.
+    pub fn target(&self, target: ShadowTarget) -> (&ShadowMap, &wgpu::TextureView) {
+        match target {
+            ShadowTarget::Cascade(layer) => {
+                (&self.cascades, self.cascades.layer_view(layer as usize))
+            }
+            ShadowTarget::CubeFace(layer) => (&self.points, self.points.layer_view(layer as usize)),
+            ShadowTarget::AtlasTile(_) => (&self.atlas, self.atlas.layer_view(0)),
+        }
+    }
+
+    /// Changes whenever one of the maps is reallocated.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.cascades.generation() + self.points.generation() + self.atlas.generation()
+    }
+}
+
+/// Depth texture holding one layer per cascade or point light face, or a single-layer atlas.
+///
+/// Starts as a placeholder so shaders always have a texture bound, and is
+/// reallocated by [`ShadowMap::ensure`] when the resolution or layer count grows.
+pub struct ShadowMap {
+    backend: wgpu::Backend,
+    label: &'static str,
+    dimension: wgpu::TextureViewDimension,
+    resolution: u32,
+    layers: u32,
+    view: wgpu::TextureView,
+    layer_views: Vec<wgpu::TextureView>,
+    generation: u64,
+}
+
+impl ShadowMap {
+    /// `dimension` is the view shaders sample: `D2`, `D2Array` or `CubeArray`,
+    /// whose layers come in multiples of six.
+    pub fn new(
+        device: &wgpu::Device,
+        backend: wgpu::Backend,
+        label: &'static str,
+        dimension: wgpu::TextureViewDimension,
+    ) -> Self {
+        let layers = placeholder_layers(dimension);
+        let (view, layer_views) = create_shadow_map(device, backend, label, dimension, 1, layers);
+        Self {
+            backend,
+            label,
+            dimension,
+            resolution: 1,
+            layers,
+            view,
+            layer_views,
+            generation: 0,
+        }
+    }
+
+    /// Makes room for `layers` layers of `resolution` × `resolution` texels,
+    /// reallocating the texture (and so changing its views) if needed.
+    pub fn ensure(&mut self, device: &wgpu::Device, resolution: u32, layers: u32) {
+        if self.resolution == resolution && self.layers >= layers {
+            return;
+        }
+        (self.view, self.layer_views) = create_shadow_map(
+            device,
+            self.backend,
+            self.label,
+            self.dimension,
+            resolution,
+            layers,
+        );
+        self.resolution = resolution;
+        self.layers = layers;
+        self.generation += 1;
+    }
+
+    #[inline]
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// The whole texture, as sampled by shaders.
+    #[inline]
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// One layer, as a depth attachment.
+    #[inline]
+    pub fn layer_view(&self, layer: usize) -> &wgpu::TextureView {
+        &self.layer_views[layer]
+    }
+
+    /// Increases every time the texture is reallocated.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+/// Layers of the texture a shadow map starts with.
+fn placeholder_layers(dimension: wgpu::TextureViewDimension) -> u32 {
+    match dimension {
+        wgpu::TextureViewDimension::CubeArray => 6,
+        _ => 1,
+    }
+}
+
+fn create_shadow_map(
+    device: &wgpu::Device,
+    backend: wgpu::Backend,
+    label: &str,
+    dimension: wgpu::TextureViewDimension,
+    resolution: u32,
+    layers: u32,
+) -> (wgpu::TextureView, Vec<wgpu::TextureView>) {
+    let (layers, allocated_layers) = match dimension {
+        wgpu::TextureViewDimension::D2Array => (
+            layers,
+            array_layers_to_allocate(backend, resolution, resolution, layers),
+        ),
+        wgpu::TextureViewDimension::CubeArray => {
+            (layers, cube_array_layers_to_allocate(backend, layers))
+        }
+        _ => (1, 1),
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: resolution,
+            height: resolution,
+            depth_or_array_layers: allocated_layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: SHADOW_MAP_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some(label),
+        dimension: Some(dimension),
+        array_layer_count: Some(layers),
+        ..Default::default()
+    });
+    let layer_views = (0..layers)
+        .map(|layer| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some(label),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+    (view, layer_views)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Color3;
+
+    fn config() -> ShadowConfig {
+        ShadowConfig {
+            max_distance: 50.0,
+            ..Default::default()
+        }
+    }
+
+    fn camera() -> Camera {
+        Camera::new(
+            Mat4::look_at_rh(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO, Vec3::Y).inverse(),
+            PerspectiveProjection::new(60.0, 16.0 / 9.0, 0.1, 1000.0),
+        )
+    }
+
+    #[test]
+    fn test_cascade_splits() {
+        let splits = cascade_splits(0.1, 100.0, 4, 0.75);
+        assert_eq!(splits.len(), 5);
+        assert_eq!(splits[0], 0.1);
+        assert_eq!(splits[4], 100.0);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let uniform = cascade_splits(0.0, 100.0, 4, 0.0);
+        assert_eq!(uniform, vec![0.0, 25.0, 50.0, 75.0, 100.0]);
+    }
+
+    #[test]
+    fn test_cascades_cover_their_slices() {
+        let camera = camera();
+        let config = config();
+        let cascades = fit_cascades(&camera, Vec3::new(-1.0, -2.0, -1.0), &config, &[]);
+        assert_eq!(cascades.len(), 4);
+
+        let splits = cascade_splits(0.1, 50.0, 4, config.split_lambda);
+        let half_height = 30.0f32.to_radians().tan();
+        let half_width = half_height * 16.0 / 9.0;
+        for (cascade, slice) in cascades.iter().zip(splits.windows(2)) {
+            // the center and a corner of both ends of the slice
+            for depth in slice {
+                for point in [
+                    Vec3::new(0.0, 0.0, -depth),
+                    Vec3::new(half_width, -half_height, -1.0) * *depth,
+                ] {
+                    let point = camera.transform().transform_point3(point);
+                    let clip = cascade.camera.view_projection() * point.extend(1.0);
+                    assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0);
+                    assert!((0.0..=1.0).contains(&clip.z));
+                }
+            }
+        }
+
+        // nearer cascades get smaller texels
+        assert!(
+            cascades
+                .windows(2)
+                .all(|pair| pair[0].texel_size < pair[1].texel_size)
+        );
+    }
+
+    #[test]
+    fn test_casters_outside_the_view_extend_the_depth_range() {
+        let camera = camera();
+        let direction = Vec3::NEG_Y;
+        // high above the first cascade, between it and the light
+        let caster = BoundingSphere::new(Vec3::new(0.0, 100.0, 4.0), 1.0);
+
+        let without = fit_cascades(&camera, direction, &config(), &[]);
+        let with = fit_cascades(&camera, direction, &config(), &[caster]);
+
+        let depth = |cascade: &Cascade| {
+            let clip = cascade.camera.view_projection() * caster.center.extend(1.0);
+            clip.z
+        };
+        assert!(depth(&without[0]) < 0.0);
+        assert!((0.0..=1.0).contains(&depth(&with[0])));
+    }
+
+    #[test]
+    fn test_local_shadows_respect_the_budget() {
+        let point = Light::point(Vec3::ZERO, Color3::new(1.0, 1.0, 1.0), 1.0).with_shadows();
+        let spot = Light::spot(
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Color3::new(1.0, 1.0, 1.0),
+            1.0,
+            20.0,
+            30.0,
+        )
+        .with_shadows();
+        let unshadowed = Light::point(Vec3::ZERO, Color3::new(1.0, 1.0, 1.0), 1.0);
+        let lights = [point, unshadowed, spot, point, point, spot];
+        let config = ShadowConfig {
+            max_point_lights: 2,
+            max_spot_lights: 1,
+            ..config()
+        };
+
+        let shadows = plan_shadows(&lights, None, &config, &[]);
+        assert_eq!(shadows.light_slots, vec![0, -1, 0, 1, -1, -1]);
+        assert_eq!(shadows.points.len(), 2);
+        assert_eq!(shadows.spots.len(), 1);
+        assert_eq!(shadows.views.len(), 2 * 6 + 1);
+        // views follow the lights: the first cube, the spot tile, then the second cube
+        assert_eq!(shadows.views[6].target, ShadowTarget::AtlasTile(0));
+        assert_eq!(shadows.views[7].target, ShadowTarget::CubeFace(6));
+    }
+
+    #[test]
+    fn test_cube_faces_look_along_their_axis() {
+        let light = Light::point(Vec3::new(1.0, 2.0, 3.0), Color3::new(1.0, 1.0, 1.0), 1.0)
+            .with_range(10.0)
+            .with_shadows();
+        let shadows = plan_shadows(&[light], None, &config(), &[]);
+        for (view, (forward, _)) in shadows.views.iter().zip(CUBE_FACES) {
+            let point = light.position + forward * 5.0;
+            let clip = view.camera.view_projection() * point.extend(1.0);
+            let ndc = clip.truncate() / clip.w;
+            assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5);
+            assert!((0.0..=1.0).contains(&ndc.z));
+        }
+    }
+
+    #[test]
+    fn test_spot_tiles_are_laid_out_in_rows() {
+        let shadows = FrameShadows {
+            local_resolution: 256,
+            atlas_columns: atlas_columns(5),
+            ..Default::default()
+        };
+        assert_eq!(shadows.atlas_columns, 3);
+        assert_eq!(
+            shadows.viewport(ShadowTarget::AtlasTile(4), 0),
+            Viewport::new(256.0, 256.0, 256.0, 256.0)
+        );
+    }
+
+    #[test]
+    fn test_cascades_move_in_whole_texels() {
+        let config = config();
+        let direction = Vec3::new(0.3, -1.0, 0.2);
+        let mut camera = camera();
+        let before = fit_cascades(&camera, direction, &config, &[]);
+        camera
+            .set_transform(Mat4::from_translation(Vec3::new(0.013, 0.0, 0.0)) * camera.transform());
+        let after = fit_cascades(&camera, direction, &config, &[]);
+
+        for (before, after) in before.iter().zip(&after) {
+            assert_eq!(before.texel_size, after.texel_size);
+            let shift = after.camera.projection().w_axis - before.camera.projection().w_axis;
+            // the projection's translation moves by whole texels, 2 / resolution in clip space
+            let texels = shift.x * config.resolution as f32 / 2.0;
+            assert!((texels - texels.round()).abs() < 1e-2, "{texels}");
+        }
+    }
+}
@@ -1,80 +1,158 @@
-use crate::target::RenderTarget;
+use super::{RendererConfig, RendererError};
 use crate::texture::TextureKind;
-use crate::{Resources, SurfaceKey};
+use crate::{Resources, SurfaceKey, TextureHandle};
 use slotmap::SecondaryMap;
 use std::collections::HashMap;
 
-pub struct ActiveSurfaceTextures(HashMap<SurfaceKey, wgpu::SurfaceTexture>);
+/// How the surfaces of a render target were acquired for a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// Every surface texture was acquired on the first try (or the target has no surface).
+    #[default]
+    Rendered,
+    /// A surface was lost or outdated; it was reconfigured and the frame was rendered.
+    Recovered,
+    /// A surface timed out or has a zero-sized extent; nothing was rendered.
+    Skipped,
+}
+
+pub struct ActiveSurfaceTextures {
+    textures: HashMap<SurfaceKey, wgpu::SurfaceTexture>,
+    status: FrameStatus,
+}
 
 impl ActiveSurfaceTextures {
-    pub fn get_surface_texture(&self, surface_key: SurfaceKey) -> &wgpu::SurfaceTexture {
-        self.0.get(&surface_key).unwrap()
+    pub fn get_surface_texture(&self, surface_key: SurfaceKey) -> Option<&wgpu::SurfaceTexture> {
+        self.textures.get(&surface_key)
+    }
+
+    #[inline]
+    pub fn status(&self) -> FrameStatus {
+        self.status
     }
 
     pub fn present(self) {
-        self.0.into_values().for_each(|st| {
+        self.textures.into_values().for_each(|st| {
             st.present();
         });
     }
 }
 
+/// Acquires the next texture of `surface`, reconfiguring it once if it was lost or outdated.
+///
+/// Returns `Ok(None)` when acquisition timed out and the frame should be skipped.
+fn acquire_surface_texture(
+    surface: &wgpu::Surface,
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    status: &mut FrameStatus,
+) -> Result<Option<wgpu::SurfaceTexture>, RendererError> {
+    match surface.get_current_texture() {
+        Ok(surface_texture) => Ok(Some(surface_texture)),
+        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+            surface.configure(device, config);
+            *status = FrameStatus::Recovered;
+
+            match surface.get_current_texture() {
+                Ok(surface_texture) => Ok(Some(surface_texture)),
+                Err(wgpu::SurfaceError::Timeout) => Ok(None),
+                Err(err) => Err(RendererError::Surface(err)),
+            }
+        }
+        Err(wgpu::SurfaceError::Timeout) => Ok(None),
+        Err(err) => Err(RendererError::Surface(err)),
+    }
+}
+
 pub struct Surfaces {
     map: SecondaryMap<SurfaceKey, wgpu::SurfaceConfiguration>,
+    config: RendererConfig,
 }
 
 impl Surfaces {
-    pub fn new() -> Self {
+    pub fn new(config: RendererConfig) -> Self {
         Self {
             map: SecondaryMap::new(),
+            config,
         }
     }
 
-    pub fn get_surface_textures(
+    /// Configures (or resizes) every surface among `attachments` and acquires its next texture.
+    ///
+    /// `attachments` are the attached textures with the size of the pass they are attached to.
+    /// Lost and outdated surfaces are reconfigured from their stored configuration and
+    /// acquired again. If any surface times out or is attached with a zero-sized extent,
+    /// no texture is kept and the returned status is [`FrameStatus::Skipped`].
+    pub fn get_surface_textures<'a>(
         &mut self,
         adapter: &wgpu::Adapter,
         device: &wgpu::Device,
-        target: &RenderTarget,
+        attachments: impl IntoIterator<Item = (&'a TextureHandle, (u32, u32))>,
         resources: &Resources,
-    ) -> ActiveSurfaceTextures {
-        let mut surface_textures = ActiveSurfaceTextures(HashMap::new());
+    ) -> Result<ActiveSurfaceTextures, RendererError> {
+        let mut surface_textures = ActiveSurfaceTextures {
+            textures: HashMap::new(),
+            status: FrameStatus::Rendered,
+        };
 
-        let (width, height) = target.size();
-
-        for color_attachment in target.color_attachments().iter() {
-            let texture_handle = &color_attachment.texture;
-            let texture = resources.get_texture(texture_handle).unwrap();
+        for (texture_handle, (width, height)) in attachments {
+            let texture = resources
+                .get_texture(texture_handle)
+                .ok_or(RendererError::MissingTexture)?;
             if let TextureKind::Surface { surface_key, .. } = texture.kind() {
-                let surface = resources.get_surface(*surface_key).unwrap();
+                // a surface attached to several passes is acquired once
+                if surface_textures.textures.contains_key(surface_key) {
+                    continue;
+                }
+
+                let surface = resources
+                    .get_surface(*surface_key)
+                    .ok_or(RendererError::MissingSurface)?;
+
+                // a minimized window reports a zero-sized extent, which cannot be configured
+                if width == 0 || height == 0 {
+                    surface_textures.textures.clear();
+                    surface_textures.status = FrameStatus::Skipped;
+                    return Ok(surface_textures);
+                }
+
                 let internal_surface = self.map.get_mut(*surface_key);
 
                 if let Some(config) = internal_surface {
                     if config.width != width || config.height != height {
                         config.width = width;
                         config.height = height;
-                        surface.configure(&device, &config);
+                        surface.configure(device, config);
                     }
                 } else {
-                    let caps = surface.get_capabilities(&adapter);
-                    let config = wgpu::SurfaceConfiguration {
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                        format: caps.formats[0],
-                        width,
-                        height,
-                        present_mode: wgpu::PresentMode::Fifo,
-                        alpha_mode: caps.alpha_modes[0],
-                        view_formats: vec![],
-                        desired_maximum_frame_latency: 2,
-                    };
-                    surface.configure(&device, &config);
+                    let caps = surface.get_capabilities(adapter);
+                    let config = self.config.surface_configuration(&caps, width, height)?;
+                    surface.configure(device, &config);
                     self.map.insert(*surface_key, config);
                 }
 
-                surface_textures
-                    .0
-                    .insert(*surface_key, surface.get_current_texture().unwrap());
+                let config = &self.map[*surface_key];
+
+                match acquire_surface_texture(
+                    surface,
+                    device,
+                    config,
+                    &mut surface_textures.status,
+                )? {
+                    Some(surface_texture) => {
+                        surface_textures
+                            .textures
+                            .insert(*surface_key, surface_texture);
+                    }
+                    None => {
+                        surface_textures.textures.clear();
+                        surface_textures.status = FrameStatus::Skipped;
+                        return Ok(surface_textures);
+                    }
+                }
             }
         }
 
-        surface_textures
+        Ok(surface_textures)
     }
 }
@@ -1,17 +1,265 @@
+use super::RendererError;
+use super::graph::TextureId;
+use super::pipelines::{ColorTargetFormat, TargetFormats};
 use super::surfaces::ActiveSurfaceTextures;
 use super::textures::Textures;
+use super::transients::Transients;
 use crate::{
-    target::{LoadOp, RenderTarget, StoreOp},
+    graph::{GraphPass, GraphTexture, RenderGraph},
+    target::{LoadOp, StoreOp},
     texture::TextureKind,
 };
+use std::collections::{HashMap, HashSet};
 
-pub struct Targets {}
+/// Features of `format` on a device with `features`.
+///
+/// Adapters may support more than the guaranteed features of a format, but
+/// a device can only use them with `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`.
+fn format_features(
+    adapter: &wgpu::Adapter,
+    features: wgpu::Features,
+    format: wgpu::TextureFormat,
+) -> wgpu::TextureFormatFeatures {
+    if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+        adapter.get_texture_format_features(format)
+    } else {
+        format.guaranteed_format_features(features)
+    }
+}
+
+/// Largest sample count up to `requested` that every format in `formats` supports.
+fn supported_sample_count(
+    requested: u32,
+    formats: &[wgpu::TextureFormat],
+    format_features: impl Fn(wgpu::TextureFormat) -> wgpu::TextureFormatFeatures,
+) -> u32 {
+    [16, 8, 4, 2]
+        .into_iter()
+        .filter(|count| *count <= requested)
+        .find(|count| {
+            formats.iter().all(|format| {
+                format_features(*format)
+                    .flags
+                    .sample_count_supported(*count)
+            })
+        })
+        .unwrap_or(1)
+}
+
+/// Depth clear value of a reverse-Z pass for `value`, a conventional depth where 1.0 is far.
+fn reverse_clear_depth(value: f32) -> f32 {
+    1.0 - value
+}
+
+/// Multisampled texture drawn into in place of a single-sampled attachment.
+struct MultisampledTexture {
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    view: wgpu::TextureView,
+}
+
+pub struct Targets {
+    /// Keyed by the attachment each texture stands in for, so contents
+    /// carry over to later passes that load the same attachment.
+    multisampled: HashMap<TextureId, MultisampledTexture>,
+    /// Attachments drawn with MSAA since the last [`Targets::end_frame`].
+    multisampled_used: HashSet<TextureId>,
+}
 
 impl Targets {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            multisampled: HashMap::new(),
+            multisampled_used: HashSet::new(),
+        }
+    }
+
+    /// Returns a multisampled view matching `resolved`, the view of `texture`,
+    /// recreating it when the size, format or sample count changed.
+    fn multisampled_view(
+        &mut self,
+        device: &wgpu::Device,
+        texture: &GraphTexture,
+        resolved: &wgpu::TextureView,
+        sample_count: u32,
+    ) -> wgpu::TextureView {
+        let size = resolved.texture().size();
+        let format = resolved.texture().format();
+
+        let key = TextureId::from(texture);
+        self.multisampled_used.insert(key);
+        let stale = self.multisampled.get(&key).is_none_or(|multisampled| {
+            multisampled.size != size
+                || multisampled.format != format
+                || multisampled.sample_count != sample_count
+        });
+
+        if stale {
+            let gpu_texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Multisampled Attachment"),
+                size,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            self.multisampled.insert(
+                key,
+                MultisampledTexture {
+                    size,
+                    format,
+                    sample_count,
+                    view: gpu_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                },
+            );
+        }
+
+        self.multisampled[&key].view.clone()
+    }
+
+    /// Frees the multisampled textures of attachments no pass drew into this frame,
+    /// e.g. of deleted textures or transients that are no longer used.
+    pub fn end_frame(&mut self) {
+        self.multisampled
+            .retain(|key, _| self.multisampled_used.contains(key));
+        self.multisampled_used.clear();
+    }
+
+    /// Returns the format `texture` will be bound with in a pass.
+    ///
+    /// Surface attachments report the format the surface was configured with,
+    /// which may differ from the format stored on the `Texture`.
+    fn attachment_format(
+        surface_textures: &ActiveSurfaceTextures,
+        graph: &RenderGraph,
+        texture: &GraphTexture,
+        resources: &crate::Resources,
+    ) -> Result<wgpu::TextureFormat, RendererError> {
+        match texture {
+            GraphTexture::Imported(handle) => {
+                let texture = resources
+                    .get_texture(handle)
+                    .ok_or(RendererError::MissingTexture)?;
+
+                match texture.kind() {
+                    TextureKind::Surface { surface_key, .. } => surface_textures
+                        .get_surface_texture(*surface_key)
+                        .map(|surface_texture| surface_texture.texture.format())
+                        .ok_or(RendererError::MissingSurface),
+                    _ => Ok(texture.format()),
+                }
+            }
+            GraphTexture::Transient(id) => graph
+                .transient(*id)
+                .map(|transient| transient.format)
+                .ok_or(RendererError::MissingTexture),
+        }
+    }
+
+    /// Returns a view of `texture` to attach to a pass.
+    #[allow(clippy::too_many_arguments)]
+    pub fn attachment_view(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_textures: &ActiveSurfaceTextures,
+        textures: &mut Textures,
+        transients: &Transients,
+        texture: &GraphTexture,
+        resources: &crate::Resources,
+    ) -> Result<wgpu::TextureView, RendererError> {
+        match texture {
+            GraphTexture::Imported(handle) => {
+                let texture = resources
+                    .get_texture(handle)
+                    .ok_or(RendererError::MissingTexture)?;
+
+                match texture.kind() {
+                    TextureKind::Surface { surface_key, .. } => Ok(surface_textures
+                        .get_surface_texture(*surface_key)
+                        .ok_or(RendererError::MissingSurface)?
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default())),
+                    _ => Ok(textures
+                        .prepare(device, queue, texture, handle)?
+                        .view()
+                        .clone()),
+                }
+            }
+            GraphTexture::Transient(id) => transients
+                .view(*id)
+                .cloned()
+                .ok_or(RendererError::MissingTexture),
+        }
+    }
+
+    /// Returns the attachment formats and sample count of `pass` as they will be bound.
+    ///
+    /// Depth-only passes have no color targets. The sample count falls back to the
+    /// largest count up to the requested one that all attachment formats support on a
+    /// device with `features` created from `adapter`.
+    pub fn target_formats(
+        &self,
+        adapter: &wgpu::Adapter,
+        features: wgpu::Features,
+        surface_textures: &ActiveSurfaceTextures,
+        graph: &RenderGraph,
+        pass: &GraphPass,
+        resources: &crate::Resources,
+    ) -> Result<TargetFormats, RendererError> {
+        if pass.color_attachments().is_empty() && pass.depth_stencil_attachment().is_none() {
+            return Err(RendererError::NoColorAttachment);
+        }
+
+        let color_targets = pass
+            .color_attachments()
+            .iter()
+            .map(|color_attachment| {
+                Ok(ColorTargetFormat {
+                    format: Self::attachment_format(
+                        surface_textures,
+                        graph,
+                        &color_attachment.texture,
+                        resources,
+                    )?,
+                    blend: color_attachment.blend,
+                    write_mask: color_attachment.write_mask,
+                })
+            })
+            .collect::<Result<Vec<_>, RendererError>>()?;
+
+        let depth_format = pass
+            .depth_stencil_attachment()
+            .map(|depth_stencil_attachment| {
+                Self::attachment_format(
+                    surface_textures,
+                    graph,
+                    &depth_stencil_attachment.texture,
+                    resources,
+                )
+            })
+            .transpose()?;
+
+        let formats = color_targets
+            .iter()
+            .map(|target| target.format)
+            .chain(depth_format)
+            .collect::<Vec<_>>();
+        let sample_count = supported_sample_count(pass.sample_count(), &formats, |format| {
+            format_features(adapter, features, format)
+        });
+
+        Ok(TargetFormats {
+            color_targets,
+            depth_format,
+            sample_count,
+            reverse_z: pass.reverse_z(),
+        })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_render_pass<'a>(
         &mut self,
         device: &wgpu::Device,
@@ -19,37 +267,55 @@ impl Targets {
         surface_textures: &ActiveSurfaceTextures,
         encoder: &'a mut wgpu::CommandEncoder,
         textures: &mut Textures,
-        target: &RenderTarget,
+        transients: &Transients,
+        pass: &GraphPass,
+        sample_count: u32,
         resources: &crate::Resources,
-    ) -> wgpu::RenderPass<'a> {
-        let views: Vec<wgpu::TextureView> = target
+    ) -> Result<wgpu::RenderPass<'a>, RendererError> {
+        let resolved_views = pass
             .color_attachments()
             .iter()
             .map(|color_attachment| {
-                let texture_handle = &color_attachment.texture;
-                let texture = resources.get_texture(texture_handle).unwrap();
-
-                let gpu_texture = match texture.kind() {
-                    TextureKind::Surface { surface_key, .. } => {
-                        &surface_textures.get_surface_texture(*surface_key).texture
-                    }
-                    _ => &textures
-                        .prepare(device, queue, &*texture, texture_handle)
-                        .texture(),
-                };
+                Self::attachment_view(
+                    device,
+                    queue,
+                    surface_textures,
+                    textures,
+                    transients,
+                    &color_attachment.texture,
+                    resources,
+                )
+            })
+            .collect::<Result<Vec<_>, RendererError>>()?;
 
-                gpu_texture.create_view(&wgpu::TextureViewDescriptor::default())
+        // with MSAA, draw into renderer-owned multisampled textures that resolve into the attachments
+        let views = pass
+            .color_attachments()
+            .iter()
+            .zip(resolved_views.iter())
+            .map(|(color_attachment, resolved)| {
+                if sample_count > 1 {
+                    let view = self.multisampled_view(
+                        device,
+                        &color_attachment.texture,
+                        resolved,
+                        sample_count,
+                    );
+                    (view, Some(resolved))
+                } else {
+                    (resolved.clone(), None)
+                }
             })
-            .collect();
+            .collect::<Vec<_>>();
 
-        let color_attachments = target
+        let color_attachments = pass
             .color_attachments()
             .iter()
             .zip(views.iter())
-            .map(|(color_attachment, view)| {
+            .map(|(color_attachment, (view, resolve_target))| {
                 Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view,
+                    resolve_target: *resolve_target,
                     depth_slice: None,
                     ops: wgpu::Operations {
                         load: match color_attachment.ops.load {
@@ -70,15 +336,48 @@ impl Targets {
             })
             .collect::<Vec<_>>();
 
-        let depth_stencil_attachment = match &target.depth_stencil_attachment() {
-            Some(depth_stencil_attachment) => {
-                let texture_handle = &depth_stencil_attachment.texture;
-                let texture = resources.get_texture(texture_handle).unwrap();
-                let gpu_texture = textures.prepare(device, queue, &*texture, texture_handle);
-                Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &gpu_texture.view(),
-                    depth_ops: Some(wgpu::Operations {
+        let depth_view = pass
+            .depth_stencil_attachment()
+            .map(|depth_stencil_attachment| {
+                Self::attachment_view(
+                    device,
+                    queue,
+                    surface_textures,
+                    textures,
+                    transients,
+                    &depth_stencil_attachment.texture,
+                    resources,
+                )
+                .map(|view| {
+                    // depth cannot be resolved, so the multisampled depth replaces the attachment
+                    if sample_count > 1 {
+                        self.multisampled_view(
+                            device,
+                            &depth_stencil_attachment.texture,
+                            &view,
+                            sample_count,
+                        )
+                    } else {
+                        view
+                    }
+                })
+            })
+            .transpose()?;
+
+        // ops are only allowed for the aspects the format has
+        let depth_stencil_attachment = pass
+            .depth_stencil_attachment()
+            .zip(depth_view.as_ref())
+            .map(|(depth_stencil_attachment, view)| {
+                let format = view.texture().format();
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: format.has_depth_aspect().then_some(wgpu::Operations {
                         load: match depth_stencil_attachment.depth_ops.load {
+                            // clear values are conventional depth, 1.0 being far
+                            LoadOp::Clear(value) if pass.reverse_z() => {
+                                wgpu::LoadOp::Clear(reverse_clear_depth(value))
+                            }
                             LoadOp::Clear(value) => wgpu::LoadOp::Clear(value),
                             LoadOp::Load => wgpu::LoadOp::Load,
                         },
@@ -87,27 +386,71 @@ impl Targets {
                             StoreOp::Discard => wgpu::StoreOp::Discard,
                         },
                     }),
-                    // stencil_ops: Some(wgpu::Operations {
-                    //     load: match depth_stencil_attachment.stencil_ops.load {
-                    //         LoadOp::Clear(value) => wgpu::LoadOp::Clear(value),
-                    //         LoadOp::Load => wgpu::LoadOp::Load,
-                    //     },
-                    //     store: match depth_stencil_attachment.stencil_ops.store {
-                    //         StoreOp::Store => wgpu::StoreOp::Store,
-                    //         StoreOp::Discard => wgpu::StoreOp::Discard,
-                    //     },
-                    // }),
-                    stencil_ops: None,
-                })
-            }
-            None => None,
-        };
+                    stencil_ops: format.has_stencil_aspect().then_some(wgpu::Operations {
+                        load: match depth_stencil_attachment.stencil_ops.load {
+                            LoadOp::Clear(value) => wgpu::LoadOp::Clear(value),
+                            LoadOp::Load => wgpu::LoadOp::Load,
+                        },
+                        store: match depth_stencil_attachment.stencil_ops.store {
+                            StoreOp::Store => wgpu::StoreOp::Store,
+                            StoreOp::Discard => wgpu::StoreOp::Discard,
+                        },
+                    }),
+                }
+            });
 
-        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some(target.name()),
+        Ok(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(pass.name()),
             color_attachments: &color_attachments,
             depth_stencil_attachment,
             ..Default::default()
-        })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guaranteed(format: wgpu::TextureFormat) -> wgpu::TextureFormatFeatures {
+        format.guaranteed_format_features(wgpu::Features::empty())
+    }
+
+    #[test]
+    fn test_reverse_clear_depth() {
+        assert_eq!(reverse_clear_depth(1.0), 0.0);
+        assert_eq!(reverse_clear_depth(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_sample_count_falls_back_to_guaranteed_counts() {
+        let formats = [
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureFormat::Depth32Float,
+        ];
+        assert_eq!(supported_sample_count(8, &formats, guaranteed), 4);
+        assert_eq!(supported_sample_count(4, &formats, guaranteed), 4);
+        assert_eq!(supported_sample_count(1, &formats, guaranteed), 1);
+    }
+
+    #[test]
+    fn test_sample_count_is_supported_by_every_format() {
+        // not multisampled without adapter-specific format features
+        let formats = [
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureFormat::Rgba32Float,
+        ];
+        assert_eq!(supported_sample_count(4, &formats, guaranteed), 1);
+
+        // adapter-specific counts are used when the device can use them
+        let adapter_specific = |format: wgpu::TextureFormat| {
+            let mut features = guaranteed(format);
+            features.flags |= wgpu::TextureFormatFeatureFlags::MULTISAMPLE_X8;
+            features
+        };
+        assert_eq!(
+            supported_sample_count(16, &formats[..1], adapter_specific),
+            8
+        );
     }
 }
@@ -1,24 +1,70 @@
+use super::RendererError;
 use crate::texture::{Texture, TextureData, TextureKind};
 use crate::{ResourceKey, TextureHandle};
 use slotmap::SecondaryMap;
-use std::u64;
 
-fn create_texture(device: &wgpu::Device, texture: &Texture) -> (wgpu::Texture, wgpu::TextureView) {
-    let (width, height, depth_or_array_layers) = texture.kind().dimensions();
+/// Number of layers to allocate for a 2D texture of `layers` layers that is viewed as an array.
+///
+/// The GL backend creates single-layer textures as plain 2D textures and square ones with a
+/// multiple of 6 layers as cube maps, neither of which can be viewed as arrays. There such
+/// textures get a spare last layer, which their array views leave out.
+pub(super) fn array_layers_to_allocate(
+    backend: wgpu::Backend,
+    width: u32,
+    height: u32,
+    layers: u32,
+) -> u32 {
+    let spare = backend == wgpu::Backend::Gl
+        && (layers == 1 || (width == height && layers.is_multiple_of(6)));
+    layers + spare as u32
+}
+
+/// Number of layers to allocate for a texture of `layers` layers, a multiple of 6,
+/// that is viewed as a cube array.
+///
+/// The GL backend creates square textures with exactly 6 layers as single cube maps,
+/// which cannot be viewed as cube arrays. There such textures get a spare cube.
+pub(super) fn cube_array_layers_to_allocate(backend: wgpu::Backend, layers: u32) -> u32 {
+    if backend == wgpu::Backend::Gl && layers == 6 {
+        12
+    } else {
+        layers
+    }
+}
 
-    let dimension = {
+/// Creates the GPU texture and view of `texture`.
+///
+/// The GPU texture may have a spare layer (see [`array_layers_to_allocate`]);
+/// `TextureKind::dimensions` stays the size of the data and of the view.
+fn create_texture(
+    device: &wgpu::Device,
+    backend: wgpu::Backend,
+    texture: &Texture,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let (width, height, layers) = texture.kind().dimensions();
+
+    let (dimension, view_dimension) = {
         use TextureKind::*;
+        use wgpu::{TextureDimension as Dim, TextureViewDimension as ViewDim};
         match texture.kind() {
             Empty => panic!("Cannot create texture for Empty kind"),
-            D1 { .. } => wgpu::TextureDimension::D1,
-            D2 { .. } => wgpu::TextureDimension::D2,
-            D3 { .. } => wgpu::TextureDimension::D3,
-            Cube { .. } => wgpu::TextureDimension::D2,
-            Surface { .. } => wgpu::TextureDimension::D2,
-            Render { .. } => wgpu::TextureDimension::D2,
+            D1 { .. } => (Dim::D1, ViewDim::D1),
+            D2 { .. } => (Dim::D2, ViewDim::D2),
+            D2Array { .. } => (Dim::D2, ViewDim::D2Array),
+            D3 { .. } => (Dim::D3, ViewDim::D3),
+            Cube { .. } => (Dim::D2, ViewDim::Cube),
+            Surface { .. } => (Dim::D2, ViewDim::D2),
+            Render { .. } => (Dim::D2, ViewDim::D2),
         }
     };
 
+    let depth_or_array_layers = match view_dimension {
+        wgpu::TextureViewDimension::D2Array => {
+            array_layers_to_allocate(backend, width, height, layers)
+        }
+        _ => layers,
+    };
+
     let descriptor = wgpu::TextureDescriptor {
         label: texture.name(),
         size: wgpu::Extent3d {
@@ -36,16 +82,17 @@ fn create_texture(device: &wgpu::Device, texture: &Texture) -> (wgpu::Texture, w
 
     let gpu_texture = device.create_texture(&descriptor);
 
-    let view = gpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let view = gpu_texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(view_dimension),
+        array_layer_count: (view_dimension == wgpu::TextureViewDimension::D2Array)
+            .then_some(layers),
+        ..Default::default()
+    });
 
     (gpu_texture, view)
 }
 
-fn div_ceil(a: u32, b: u32) -> u32 {
-    (a + b - 1) / b
-}
-
-fn bytes_layout_for_level(
+pub(super) fn bytes_layout_for_level(
     format: wgpu::TextureFormat,
     width: u32,
     height: u32,
@@ -53,8 +100,8 @@ fn bytes_layout_for_level(
     // returns (bytes_per_row, bytes_per_image, total_bytes) or None if format needs special-case
     let block_size = format.block_copy_size(None)?;
     let (bw, bh) = format.block_dimensions();
-    let blocks_w = div_ceil(width, bw);
-    let blocks_h = div_ceil(height, bh);
+    let blocks_w = width.div_ceil(bw);
+    let blocks_h = height.div_ceil(bh);
     let bytes_per_row = block_size * blocks_w;
     let rows_per_image = blocks_h;
     Some((bytes_per_row, rows_per_image))
@@ -68,8 +115,8 @@ pub struct InternalTexture {
 }
 
 impl InternalTexture {
-    pub fn new(device: &wgpu::Device, texture: &Texture) -> Self {
-        let (gpu_texture, view) = create_texture(device, texture);
+    pub fn new(device: &wgpu::Device, backend: wgpu::Backend, texture: &Texture) -> Self {
+        let (gpu_texture, view) = create_texture(device, backend, texture);
 
         Self {
             texture: gpu_texture,
@@ -89,12 +136,17 @@ impl InternalTexture {
         &self.view
     }
 
-    pub fn ensure_gpu_texture(&mut self, device: &wgpu::Device, texture: &Texture) -> &mut Self {
+    pub fn ensure_gpu_texture(
+        &mut self,
+        device: &wgpu::Device,
+        backend: wgpu::Backend,
+        texture: &Texture,
+    ) -> &mut Self {
         if self.texture_ver == texture.ver() {
             return self;
         }
 
-        let (gpu_texture, view) = create_texture(device, texture);
+        let (gpu_texture, view) = create_texture(device, backend, texture);
 
         self.texture = gpu_texture;
         self.view = view;
@@ -104,56 +156,95 @@ impl InternalTexture {
         self
     }
 
-    pub fn upload_if_dirty(&mut self, queue: &wgpu::Queue, texture: &Texture) -> &mut Self {
-        match texture.kind() {
-            TextureKind::D2 {
-                data,
-                width,
-                height,
-            } => {
-                let current_data_ver = data.ver();
-                if self.data_ver == Some(current_data_ver) {
-                    return self;
-                } else {
-                    self.data_ver = Some(current_data_ver);
-                }
-
-                let layout = bytes_layout_for_level(texture.format(), *width, *height);
-
-                queue.write_texture(
-                    wgpu::TexelCopyTextureInfo {
-                        texture: &self.texture,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::ZERO,
-                        aspect: wgpu::TextureAspect::All,
-                    },
-                    data.bytes(),
-                    wgpu::TexelCopyBufferLayout {
-                        offset: 0,
-                        bytes_per_row: layout.and_then(|(b, _)| Some(b)),
-                        rows_per_image: layout.and_then(|(_, r)| Some(r)),
-                    },
-                    wgpu::Extent3d {
-                        width: *width,
-                        height: *height,
-                        depth_or_array_layers: 1,
+    /// Uploads the texture's data if it changed since the last upload.
+    ///
+    /// The data holds the array layers, cube faces or depth slices one after another;
+    /// each is written separately, see [`split_layers`].
+    pub fn upload_if_dirty(
+        &mut self,
+        queue: &wgpu::Queue,
+        texture: &Texture,
+    ) -> Result<&mut Self, RendererError> {
+        let Some(data) = texture.kind().data() else {
+            return Ok(self);
+        };
+
+        let current_data_ver = data.ver();
+        if self.data_ver == Some(current_data_ver) {
+            return Ok(self);
+        }
+
+        let (width, height, _) = texture.kind().dimensions();
+        let (bytes_per_row, rows_per_image, layers) = split_layers(texture, data.bytes())?;
+
+        for (layer, bytes) in layers.enumerate() {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
                     },
-                );
-            }
-            _ => (),
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytes,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(rows_per_image),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
         }
+        self.data_ver = Some(current_data_ver);
 
-        self
+        Ok(self)
+    }
+}
+
+/// Splits the data of `texture` into its layers, cube faces or depth slices,
+/// returning the bytes per row and rows per image of each.
+///
+/// Bytes past the last layer are ignored.
+fn split_layers<'a>(
+    texture: &Texture,
+    bytes: &'a [u8],
+) -> Result<(u32, u32, impl Iterator<Item = &'a [u8]>), RendererError> {
+    let (width, height, layers) = texture.kind().dimensions();
+    let (bytes_per_row, rows_per_image) =
+        bytes_layout_for_level(texture.format(), width, height)
+            .ok_or(RendererError::UnsupportedCopyFormat(texture.format()))?;
+
+    let layer_size = (bytes_per_row * rows_per_image) as usize;
+    let expected = layer_size * layers as usize;
+    if bytes.len() < expected {
+        return Err(RendererError::TextureDataTooShort {
+            expected,
+            actual: bytes.len(),
+        });
     }
+
+    Ok((
+        bytes_per_row,
+        rows_per_image,
+        bytes[..expected].chunks_exact(layer_size),
+    ))
 }
 
 pub struct Textures {
+    backend: wgpu::Backend,
     default_gpu_texture: InternalTexture,
     pool: SecondaryMap<ResourceKey, InternalTexture>,
 }
 
 impl Textures {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, backend: wgpu::Backend) -> Self {
         let default_texture = Texture::new(
             TextureKind::D2 {
                 data: TextureData::from_bytes(vec![255, 255, 255, 255]),
@@ -164,10 +255,13 @@ impl Textures {
             wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         );
 
-        let mut default_gpu_texture = InternalTexture::new(device, &default_texture);
-        default_gpu_texture.upload_if_dirty(queue, &default_texture);
+        let mut default_gpu_texture = InternalTexture::new(device, backend, &default_texture);
+        default_gpu_texture
+            .upload_if_dirty(queue, &default_texture)
+            .expect("Default texture data matches its size");
 
         Self {
+            backend,
             default_gpu_texture,
             pool: SecondaryMap::new(),
         }
@@ -184,22 +278,127 @@ impl Textures {
         queue: &wgpu::Queue,
         texture: &Texture,
         texture_handle: &TextureHandle,
-    ) -> &InternalTexture {
+    ) -> Result<&InternalTexture, RendererError> {
         if let TextureKind::Empty = texture.kind() {
-            return &self.default_gpu_texture;
+            return Ok(&self.default_gpu_texture);
         }
 
-        let internal_texture = match self.pool.entry(texture_handle.raw()) {
-            Some(entry) => entry.or_insert_with(|| InternalTexture::new(device, texture)),
-            None => panic!("Texture has been removed from pool."),
-        };
+        let internal_texture = self
+            .pool
+            .entry(texture_handle.raw())
+            .ok_or(RendererError::MissingTexture)?
+            .or_insert_with(|| InternalTexture::new(device, self.backend, texture));
+
+        Ok(internal_texture
+            .ensure_gpu_texture(device, self.backend, texture)
+            .upload_if_dirty(queue, texture)?)
+    }
+
+    pub fn get_internal_texture(&self, texture_handle: &TextureHandle) -> Option<&InternalTexture> {
+        self.pool.get(texture_handle.raw())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(kind: TextureKind) -> Texture {
+        Texture::new(
+            kind,
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        )
+    }
+
+    fn layer_starts(texture: &Texture, bytes: &[u8]) -> Vec<u8> {
+        let (_, _, layers) = split_layers(texture, bytes).unwrap();
+        layers.map(|layer| layer[0]).collect()
+    }
+
+    #[test]
+    fn test_gl_array_textures_get_spare_layers() {
+        use wgpu::Backend::{Gl, Vulkan};
+        assert_eq!(array_layers_to_allocate(Gl, 4, 4, 1), 2);
+        assert_eq!(array_layers_to_allocate(Gl, 4, 4, 6), 7);
+        assert_eq!(array_layers_to_allocate(Gl, 4, 2, 6), 6);
+        assert_eq!(array_layers_to_allocate(Vulkan, 4, 4, 6), 6);
+
+        assert_eq!(cube_array_layers_to_allocate(Gl, 6), 12);
+        assert_eq!(cube_array_layers_to_allocate(Gl, 12), 12);
+        assert_eq!(cube_array_layers_to_allocate(Vulkan, 6), 6);
+    }
 
-        internal_texture
-            .ensure_gpu_texture(device, texture)
-            .upload_if_dirty(queue, texture)
+    #[test]
+    fn test_split_layers() {
+        // 2 × 2 Rgba8 texels per layer, every byte numbered by its layer
+        let bytes: Vec<u8> = (0..7).flat_map(|layer| [layer; 16]).collect();
+
+        let d3 = texture(TextureKind::D3 {
+            data: TextureData::from_bytes(bytes.clone()),
+            width: 2,
+            height: 2,
+            depth: 3,
+        });
+        assert_eq!(layer_starts(&d3, &bytes), vec![0, 1, 2]);
+
+        let cube = texture(TextureKind::Cube {
+            data: TextureData::from_bytes(bytes.clone()),
+            size: 2,
+        });
+        assert_eq!(layer_starts(&cube, &bytes), vec![0, 1, 2, 3, 4, 5]);
+
+        let array = texture(TextureKind::D2Array {
+            data: TextureData::from_bytes(bytes.clone()),
+            width: 2,
+            height: 2,
+            layers: 7,
+        });
+        let (bytes_per_row, rows_per_image, layers) = split_layers(&array, &bytes).unwrap();
+        assert_eq!((bytes_per_row, rows_per_image), (8, 2));
+        let layers: Vec<_> = layers.collect();
+        assert_eq!(layers.len(), 7);
+        assert!(
+            layers
+                .iter()
+                .enumerate()
+                .all(|(i, layer)| *layer == [i as u8; 16])
+        );
     }
 
-    pub fn get_internal_texture(&self, texture_handle: &TextureHandle) -> &InternalTexture {
-        self.pool.get(texture_handle.raw()).unwrap()
+    #[test]
+    fn test_split_layers_rejects_short_data() {
+        let bytes = vec![0; 16 * 5];
+        let cube = texture(TextureKind::Cube {
+            data: TextureData::from_bytes(bytes.clone()),
+            size: 2,
+        });
+        assert!(matches!(
+            split_layers(&cube, &bytes),
+            Err(RendererError::TextureDataTooShort {
+                expected: 96,
+                actual: 80
+            })
+        ));
+    }
+
+    #[test]
+    fn test_split_layers_rejects_uncopyable_formats() {
+        let bytes = vec![0; 4];
+        let depth = Texture::new(
+            TextureKind::D2 {
+                data: TextureData::from_bytes(bytes.clone()),
+                width: 1,
+                height: 1,
+            },
+            wgpu::TextureFormat::Depth24Plus,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        assert!(matches!(
+            split_layers(&depth, &bytes),
+            Err(RendererError::UnsupportedCopyFormat(
+                wgpu::TextureFormat::Depth24Plus
+            ))
+        ));
     }
 }
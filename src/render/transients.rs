@@ -0,0 +1,92 @@
+use crate::graph::{TransientId, TransientTexture};
+use std::collections::HashMap;
+
+type TransientKey = (u32, u32, wgpu::TextureFormat);
+
+/// GPU textures backing the transient textures of render graphs.
+///
+/// Textures are pooled by size and format and reused across frames;
+/// textures no graph asked for during a frame are released.
+pub struct Transients {
+    active: Vec<(TransientKey, wgpu::Texture, wgpu::TextureView)>,
+    slots: Vec<Option<usize>>,
+}
+
+impl Transients {
+    pub fn new() -> Self {
+        Self {
+            active: Vec::new(),
+            slots: Vec::new(),
+        }
+    }
+
+    /// Backs every transient with a texture, `slots[i]` being the slot of `transients[i]`.
+    ///
+    /// Transients in the same slot share one texture. Textures are taken from those
+    /// of the previous frame when possible; the ones not taken are dropped.
+    pub fn allocate(
+        &mut self,
+        device: &wgpu::Device,
+        transients: &[TransientTexture],
+        slots: &[Option<usize>],
+    ) {
+        let mut pool: HashMap<TransientKey, Vec<_>> = HashMap::new();
+        for (key, texture, view) in self.active.drain(..) {
+            pool.entry(key).or_default().push((texture, view));
+        }
+
+        // slots are numbered densely from 0; each is described by its first transient
+        let slot_count = slots
+            .iter()
+            .flatten()
+            .map(|slot| slot + 1)
+            .max()
+            .unwrap_or(0);
+        let mut descs = vec![None; slot_count];
+        for (desc, slot) in transients.iter().zip(slots) {
+            if let Some(slot) = slot {
+                descs[*slot].get_or_insert(desc);
+            }
+        }
+
+        for desc in descs.into_iter().flatten() {
+            let key = (desc.width, desc.height, desc.format);
+            let (texture, view) = pool
+                .get_mut(&key)
+                .and_then(|textures| textures.pop())
+                .unwrap_or_else(|| create_transient(device, desc));
+            self.active.push((key, texture, view));
+        }
+
+        self.slots = slots.to_vec();
+    }
+
+    /// View of the texture backing `id`, after [`Transients::allocate`].
+    #[inline]
+    pub fn view(&self, id: TransientId) -> Option<&wgpu::TextureView> {
+        let slot = (*self.slots.get(id.0)?)?;
+        self.active.get(slot).map(|(_, _, view)| view)
+    }
+}
+
+fn create_transient(
+    device: &wgpu::Device,
+    desc: &TransientTexture,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(&desc.name),
+        size: wgpu::Extent3d {
+            width: desc.width,
+            height: desc.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: desc.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
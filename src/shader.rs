@@ -1,5 +1,6 @@
 mod builder;
 pub mod builtins;
+pub mod chunks;
 mod schema;
 mod types;
 
@@ -48,17 +49,21 @@ pub type ShaderRc = Rc<Shader>;
 /// Holds WGSL source and the immutable shader interface schema:
 /// - binding schema: uniform buffers and textures (for bind group layout generation and material writes)
 /// - vertex schema: vertex attributes required by the shader (for pipeline vertex state)
+/// - G-buffer entry point: optional fragment entry point used when drawing into a G-buffer
 ///
 /// The schema is metadata used to:
 /// - allocate and address material uniform storage (offset/size),
 /// - build fast lookup tables (symbol → locations),
 /// - help the backend create wgpu layouts and pipelines.
+///
 /// This type does not compile or own any GPU objects by itself.
 pub struct Shader {
     source: Cow<'static, str>,
     binding_schema: Box<[BindingEntry]>,
     vertex_schema: Box<[VertexEntry]>,
+    gbuffer_entry_point: Option<Box<str>>,
     vertex_schema_hash: OnceLock<u64>,
+    id: OnceLock<u64>,
     uniform_lut: OnceLock<HashMap<Symbol, UniformFieldMeta>>,
     texture_lut: OnceLock<HashMap<Symbol, TextureMeta>>,
     sampler_lut: OnceLock<HashMap<Symbol, SamplerMeta>>,
@@ -72,18 +77,33 @@ impl Shader {
         source: Cow<'static, str>,
         binding_schema: Box<[BindingEntry]>,
         vertex_schema: Box<[VertexEntry]>,
+        gbuffer_entry_point: Option<Box<str>>,
     ) -> Self {
         Shader {
             source,
             binding_schema,
             vertex_schema,
+            gbuffer_entry_point,
             vertex_schema_hash: OnceLock::new(),
+            id: OnceLock::new(),
             uniform_lut: OnceLock::new(),
             texture_lut: OnceLock::new(),
             sampler_lut: OnceLock::new(),
         }
     }
 
+    /// Identity of this shader: a hash of its source and schemas.
+    /// Equal shaders share GPU modules and pipelines, even when built separately.
+    #[inline]
+    pub(crate) fn id(&self) -> u64 {
+        *self.id.get_or_init(|| {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = DefaultHasher::new();
+            self.hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+
     /// Returns the shader source as `&str` (typically WGSL).
     #[inline]
     pub(crate) fn source(&self) -> &str {
@@ -102,15 +122,19 @@ impl Shader {
         &self.vertex_schema
     }
 
+    /// Fragment entry point writing the G-buffer, if the shader supports deferred shading.
+    #[inline]
+    pub(crate) fn gbuffer_entry_point(&self) -> Option<&str> {
+        self.gbuffer_entry_point.as_deref()
+    }
+
     pub(crate) fn vertex_schema_hash(&self) -> u64 {
-        self.vertex_schema_hash
-            .get_or_init(|| {
-                use std::hash::{Hash, Hasher};
-                let mut hasher = DefaultHasher::new();
-                self.vertex_schema.hash(&mut hasher);
-                hasher.finish()
-            })
-            .clone()
+        *self.vertex_schema_hash.get_or_init(|| {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = DefaultHasher::new();
+            self.vertex_schema.hash(&mut hasher);
+            hasher.finish()
+        })
     }
 
     /// Wraps this Shader in `Rc` for shared ownership across materials.
@@ -152,10 +176,10 @@ impl Shader {
         self.texture_lut.get_or_init(|| {
             let mut map = HashMap::new();
             for (i, entry) in self.binding_schema.iter().enumerate() {
-                if let BindingType::Texture = entry.ty {
-                    if map.insert(entry.key, TextureMeta { index: i }).is_some() {
-                        panic!("duplicate texture key: {:?}", entry.key);
-                    }
+                if let BindingType::Texture = entry.ty
+                    && map.insert(entry.key, TextureMeta { index: i }).is_some()
+                {
+                    panic!("duplicate texture key: {:?}", entry.key);
                 }
             }
             map
@@ -167,10 +191,10 @@ impl Shader {
         self.sampler_lut.get_or_init(|| {
             let mut map = HashMap::new();
             for (i, entry) in self.binding_schema.iter().enumerate() {
-                if let BindingType::Sampler = entry.ty {
-                    if map.insert(entry.key, SamplerMeta { index: i }).is_some() {
-                        panic!("duplicate sampler key: {:?}", entry.key);
-                    }
+                if let BindingType::Sampler { .. } = entry.ty
+                    && map.insert(entry.key, SamplerMeta { index: i }).is_some()
+                {
+                    panic!("duplicate sampler key: {:?}", entry.key);
                 }
             }
             map
@@ -208,6 +232,7 @@ impl fmt::Debug for Shader {
             .field("source_preview", &preview)
             .field("bindings_len", &self.binding_schema.len())
             .field("vertex_attrs_len", &self.vertex_schema.len())
+            .field("gbuffer_entry_point", &self.gbuffer_entry_point)
             .field("uniform_cache_init", &self.uniform_lut.get().is_some())
             .field("texture_cache_init", &self.texture_lut.get().is_some())
             .field("sampler_cache_init", &self.sampler_lut.get().is_some());
@@ -227,8 +252,10 @@ impl Clone for Shader {
             source: self.source.clone(),
             binding_schema: self.binding_schema.clone(),
             vertex_schema: self.vertex_schema.clone(),
+            gbuffer_entry_point: self.gbuffer_entry_point.clone(),
             // Do not copy caches, reinitialize
             vertex_schema_hash: OnceLock::new(),
+            id: OnceLock::new(),
             uniform_lut: OnceLock::new(),
             texture_lut: OnceLock::new(),
             sampler_lut: OnceLock::new(),
@@ -241,6 +268,7 @@ impl PartialEq for Shader {
         self.source.as_ref() == other.source.as_ref()
             && self.binding_schema == other.binding_schema
             && self.vertex_schema == other.vertex_schema
+            && self.gbuffer_entry_point == other.gbuffer_entry_point
     }
 }
 impl Eq for Shader {}
@@ -250,5 +278,6 @@ impl std::hash::Hash for Shader {
         self.source.as_ref().hash(state);
         self.binding_schema.hash(state);
         self.vertex_schema.hash(state);
+        self.gbuffer_entry_point.hash(state);
     }
 }
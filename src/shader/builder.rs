@@ -121,6 +121,7 @@ impl UniformBufferBuilder {
 /// - call `source(...)` to set WGSL,
 /// - add one or more bindings via `uniform_buffer(...).finish()` and `texture(...)`,
 /// - add vertex attributes via `vertex_attr(...)`,
+/// - optionally name the G-buffer entry point via `gbuffer_entry_point(...)`,
 /// - call `build()` to validate and produce an immutable `Shader`.
 pub struct ShaderBuilder {
     /// WGSL source code (borrowed static or owned)
@@ -129,6 +130,14 @@ pub struct ShaderBuilder {
     binding_schema: Vec<BindingEntry>,
     /// Required vertex attributes (locations only; no buffer layout).
     vertex_schema: Vec<VertexEntry>,
+    /// Fragment entry point writing the G-buffer, if any.
+    gbuffer_entry_point: Option<Box<str>>,
+}
+
+impl Default for ShaderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ShaderBuilder {
@@ -138,6 +147,7 @@ impl ShaderBuilder {
             source: Cow::Borrowed(""),
             binding_schema: Vec::new(),
             vertex_schema: Vec::new(),
+            gbuffer_entry_point: None,
         }
     }
 
@@ -176,7 +186,19 @@ impl ShaderBuilder {
             key: symbol!(name),
             name: name.into(),
             slot,
-            ty: BindingType::Sampler,
+            ty: BindingType::Sampler { comparison: false },
+        });
+        self
+    }
+
+    /// Adds a comparison sampler binding (`sampler_comparison`) at the given WGSL `@binding(slot)`.
+    /// Samplers assigned to it must have a `compare` function.
+    pub fn comparison_sampler(mut self, name: &str, slot: u32) -> Self {
+        self.binding_schema.push(BindingEntry {
+            key: symbol!(name),
+            name: name.into(),
+            slot,
+            ty: BindingType::Sampler { comparison: true },
         });
         self
     }
@@ -200,6 +222,14 @@ impl ShaderBuilder {
         self
     }
 
+    /// Names the fragment entry point used when the shader draws into a G-buffer
+    /// (deferred shading). It must write `GBuffer` attachments 0 to 3: albedo,
+    /// world normal with `w = 1`, (roughness, metallic, occlusion, lit) and emissive.
+    pub fn gbuffer_entry_point(mut self, entry_point: &str) -> Self {
+        self.gbuffer_entry_point = Some(entry_point.into());
+        self
+    }
+
     /// Validates the accumulated schemas and returns an immutable `Shader`.
     /// Panics on invalid schemas. Consider a `try_build` variant for fallible use.
     pub fn build(self) -> Shader {
@@ -209,6 +239,7 @@ impl ShaderBuilder {
             self.source,
             self.binding_schema.into_boxed_slice(),
             self.vertex_schema.into_boxed_slice(),
+            self.gbuffer_entry_point,
         )
     }
 }
@@ -219,6 +250,7 @@ mod validate {
     //! Scope:
     //! - This module is private to `builder.rs` and not exported; only items
     //!   marked `pub(super)` are visible to the parent module.
+    //!
     //! Responsibilities:
     //! - Ensure uniqueness of binding keys and slots across the binding schema.
     //! - Ensure uniqueness of uniform member keys within each uniform buffer.
@@ -236,6 +268,7 @@ mod validate {
     /// Enumerates all schema validation failures we detect.
     /// Each variant carries enough context for a helpful error message.
     #[derive(Debug)]
+    #[allow(clippy::enum_variant_names)]
     pub(super) enum SchemaError {
         /// Duplicate binding name/key detected across all bindings.
         DuplicateBindingKey { name: Box<str> },
@@ -543,4 +576,20 @@ mod tests {
             _ => panic!("expected UniformBuffer"),
         }
     }
+
+    #[test]
+    fn test_shader_id_follows_content() {
+        let build = |source: &'static str| {
+            ShaderBuilder::new()
+                .source(source)
+                .texture("albedo_texture", 0)
+                .vertex_attr("position", 0, Float32x3, Vertex)
+                .build()
+        };
+
+        let a = build("a");
+        assert_eq!(a.id(), build("a").id());
+        assert_eq!(a.id(), a.clone().id());
+        assert_ne!(a.id(), build("b").id());
+    }
 }
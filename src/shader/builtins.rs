@@ -13,23 +13,73 @@ pub fn unlit_shader() -> ShaderRc {
         .vertex_attr("positions", 0, Float32x3, Vertex)
         .vertex_attr("tex_coords", 1, Float32x2, Vertex)
         .vertex_attr("colors", 2, Float32x4, Vertex)
+        .gbuffer_entry_point("fs_gbuffer")
         .build()
         .into_rc()
 }
 
+/// Metallic-roughness shading following the glTF 2.0 core material model, lit by the
+/// lights passed to `Renderer::render` (shadowed by their shadow maps) and by the renderer's
+/// environment.
+///
+/// Requires `normals` and `tangents` (xyz along increasing u, w = ±1 handedness) besides
+/// the unlit attributes. Parameters start zeroed and unassigned textures sample white, so:
+/// - `metallic` and `roughness` multiply the b and g channels of `metallic_roughness_texture`,
+/// - `normal_scale` must be set (1 in glTF) for `normal_texture` to have an effect,
+/// - `occlusion_strength` blends in `occlusion_texture` (r channel),
+/// - `emissive_factor` multiplies `emissive_texture`.
 pub fn pbr_shader() -> ShaderRc {
     ShaderBuilder::new()
-        .source(include_str!("wgsl/pbr.wgsl"))
+        .source(concat!(
+            include_str!("wgsl/lights.wgsl"),
+            include_str!("wgsl/shadows.wgsl"),
+            include_str!("wgsl/brdf.wgsl"),
+            include_str!("wgsl/environment.wgsl"),
+            include_str!("wgsl/pbr.wgsl")
+        ))
         .uniform_buffer("uniforms", 0)
         .vec4f("albedo_factor")
         .float("metallic")
         .float("roughness")
+        .float("normal_scale")
+        .float("occlusion_strength")
+        .vec3f("emissive_factor")
         .finish()
         .texture("albedo_texture", 1)
         .sampler("albedo_sampler", 2)
+        .texture("normal_texture", 3)
+        .sampler("normal_sampler", 4)
+        .texture("metallic_roughness_texture", 5)
+        .sampler("metallic_roughness_sampler", 6)
+        .texture("occlusion_texture", 7)
+        .sampler("occlusion_sampler", 8)
+        .texture("emissive_texture", 9)
+        .sampler("emissive_sampler", 10)
         .vertex_attr("positions", 0, Float32x3, Vertex)
         .vertex_attr("tex_coords", 1, Float32x2, Vertex)
         .vertex_attr("colors", 2, Float32x4, Vertex)
+        .vertex_attr("normals", 3, Float32x3, Vertex)
+        .vertex_attr("tangents", 4, Float32x4, Vertex)
+        .gbuffer_entry_point("fs_gbuffer")
         .build()
         .into_rc()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbol;
+
+    #[test]
+    fn test_pbr_uniforms_match_wgsl_layout() {
+        let shader = pbr_shader();
+        let offset = |name: &str| shader.uniform_field_meta(symbol!(name)).unwrap().offset;
+
+        assert_eq!(offset("albedo_factor"), 0);
+        assert_eq!(offset("metallic"), 16);
+        assert_eq!(offset("roughness"), 20);
+        assert_eq!(offset("normal_scale"), 24);
+        assert_eq!(offset("occlusion_strength"), 28);
+        assert_eq!(offset("emissive_factor"), 32);
+    }
+}
@@ -0,0 +1,17 @@
+//! WGSL chunks shared by the built-in shaders, to prepend to custom shader sources.
+
+/// Light structs, the global light buffer (`@group(0) @binding(1)`) and `light_incident`,
+/// which returns the direction and radiance of a light arriving at a world position.
+pub const LIGHTS: &str = include_str!("wgsl/lights.wgsl");
+
+/// The shadow maps (`@group(0)`, bindings 2 to 6) and `light_shadow`, which returns
+/// how much of a light reaches a world position past the shadow casters. Needs [`LIGHTS`].
+pub const SHADOWS: &str = include_str!("wgsl/shadows.wgsl");
+
+/// The Cook-Torrance metallic-roughness BRDF of glTF 2.0 (`brdf_direct`), as used by the
+/// built-in PBR shader and deferred lighting.
+pub const BRDF: &str = include_str!("wgsl/brdf.wgsl");
+
+/// The environment maps (`@group(0)`, bindings 7 to 11) and `environment_light`, which returns
+/// the image-based lighting reflected by a surface.
+pub const ENVIRONMENT: &str = include_str!("wgsl/environment.wgsl");
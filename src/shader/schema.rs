@@ -27,11 +27,14 @@ pub(crate) enum BindingType {
     /// Texture binding (currently a generic 2D texture slot).
     /// Future extension ideas:
     /// - Distinguish between 2D, Cube, Array, 3D, and Storage textures.
+    ///
     /// Note: Samplers are now a separate binding type; future extensions here should focus solely on texture type distinctions.
     Texture,
 
     /// Sampler binding.
-    Sampler,
+    /// comparison: binds as a comparison sampler (`sampler_comparison` in WGSL), e.g. for
+    /// depth textures sampled with `textureSampleCompare`; otherwise as a filtering sampler.
+    Sampler { comparison: bool },
 }
 
 /// A single binding entry in the shader's binding schema.
@@ -5,6 +5,7 @@
 /// - vec3: align 16, size 12 (next member starts at 16-aligned offset)
 /// - vec4: align 16, size 16
 /// - mat4x4<f32>: align 16, size 64 (4 columns of vec4<f32>)
+///
 /// Bool intentionally omitted; prefer Uint (0/1) for stability across backends.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum UniformValueType {
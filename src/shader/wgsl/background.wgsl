@@ -0,0 +1,76 @@
+// Backgrounds drawn behind a pass's primitives with one full-screen triangle at the far
+// plane. Each kind of background has its own fragment entry point and only binds what it reads.
+
+const PI: f32 = 3.141592653589793;
+
+struct CameraUniform {
+    view_proj: mat4x4f,
+    inverse_view_proj: mat4x4f,
+    position: vec4f,
+    viewport: vec4f,
+};
+
+struct BackgroundParams {
+    top: vec4f,
+    bottom: vec4f,
+    // depth of the far plane: 1.0, or 0.0 with reversed depth
+    far_depth: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<uniform> params: BackgroundParams;
+@group(1) @binding(1)
+var skybox: texture_cube<f32>;
+@group(1) @binding(2)
+var panorama: texture_2d<f32>;
+@group(1) @binding(3)
+var background_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) ndc: vec2f,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // one triangle covering the viewport
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.position = vec4f(ndc, params.far_depth, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+// World direction seen through `ndc`: the difference of two points on the pixel's ray,
+// so the camera position cancels out and only its rotation remains.
+fn view_direction(ndc: vec2f) -> vec3f {
+    let near = camera.inverse_view_proj * vec4f(ndc, 1.0 - params.far_depth, 1.0);
+    let middle = camera.inverse_view_proj * vec4f(ndc, 0.5, 1.0);
+    return normalize(middle.xyz / middle.w - near.xyz / near.w);
+}
+
+@fragment
+fn fs_skybox(in: VertexOutput) -> @location(0) vec4f {
+    let direction = view_direction(in.ndc);
+    return vec4f(textureSampleLevel(skybox, background_sampler, direction, 0.0).rgb, 1.0);
+}
+
+@fragment
+fn fs_panorama(in: VertexOutput) -> @location(0) vec4f {
+    let direction = view_direction(in.ndc);
+    let uv = vec2f(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+    return vec4f(textureSampleLevel(panorama, background_sampler, uv, 0.0).rgb, 1.0);
+}
+
+@fragment
+fn fs_gradient(in: VertexOutput) -> @location(0) vec4f {
+    let direction = view_direction(in.ndc);
+    return mix(params.bottom, params.top, direction.y * 0.5 + 0.5);
+}
@@ -0,0 +1,49 @@
+// Cook-Torrance metallic-roughness BRDF of the glTF 2.0 core material model:
+// GGX distribution, height-correlated Smith visibility and Schlick Fresnel.
+//
+// color += brdf_direct(base_color, metallic, roughness, n, v, incident.direction) * incident.radiance;
+
+const PI: f32 = 3.141592653589793;
+
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (alpha_squared - 1.0) + 1.0;
+    return alpha_squared / (PI * d * d);
+}
+
+fn visibility_smith_ggx(n_dot_l: f32, n_dot_v: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha_squared) + alpha_squared);
+    let ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha_squared) + alpha_squared);
+    let ggx = ggx_v + ggx_l;
+    if ggx > 0.0 {
+        return 0.5 / ggx;
+    }
+    return 0.0;
+}
+
+fn fresnel_schlick(f0: vec3f, v_dot_h: f32) -> vec3f {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - v_dot_h, 0.0, 1.0), 5.0);
+}
+
+// Reflected fraction of the light arriving from `l`, times the cosine of its incidence.
+// `n`, `v` and `l` are unit vectors; `v` and `l` point away from the surface.
+fn brdf_direct(base_color: vec3f, metallic: f32, roughness: f32, n: vec3f, v: vec3f, l: vec3f) -> vec3f {
+    let n_dot_l = dot(n, l);
+    if n_dot_l <= 0.0 {
+        return vec3f(0.0);
+    }
+    let h = normalize(l + v);
+    let n_dot_v = max(dot(n, v), 1e-4);
+    let n_dot_h = max(dot(n, h), 0.0);
+    let v_dot_h = max(dot(v, h), 0.0);
+
+    // perceptual roughness squared, clamped to keep highlights finite
+    let alpha = max(roughness * roughness, 2e-3);
+    let f0 = mix(vec3f(0.04), base_color, metallic);
+    let fresnel = fresnel_schlick(f0, v_dot_h);
+
+    let diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_color / PI;
+    let specular = fresnel * distribution_ggx(n_dot_h, alpha) * visibility_smith_ggx(n_dot_l, n_dot_v, alpha);
+    return (diffuse + specular) * n_dot_l;
+}
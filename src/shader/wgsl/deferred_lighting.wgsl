@@ -0,0 +1,65 @@
+struct CameraUniform {
+    view_proj: mat4x4f,
+    inverse_view_proj: mat4x4f,
+    position: vec4f,
+    viewport: vec4f,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var gbuffer_albedo: texture_2d<f32>;
+@group(1) @binding(1)
+var gbuffer_normal: texture_2d<f32>;
+@group(1) @binding(2)
+var gbuffer_material: texture_2d<f32>;
+@group(1) @binding(3)
+var gbuffer_depth: texture_2d<f32>;
+@group(1) @binding(4)
+var gbuffer_emissive: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    // one triangle covering the viewport
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) frag_position: vec4f) -> @location(0) vec4f {
+    let pixel = vec2i(frag_position.xy);
+    let normal = textureLoad(gbuffer_normal, pixel, 0);
+    if normal.w == 0.0 {
+        // nothing was drawn here
+        discard;
+    }
+    let albedo = textureLoad(gbuffer_albedo, pixel, 0).rgb;
+    let material = textureLoad(gbuffer_material, pixel, 0);
+    if material.a == 0.0 {
+        return vec4f(albedo, 1.0);
+    }
+    let roughness = material.r;
+    let metallic = material.g;
+
+    // world position from the depth and the pixel's position inside the viewport
+    let depth = textureLoad(gbuffer_depth, pixel, 0).r;
+    let uv = (frag_position.xy - camera.viewport.xy) / camera.viewport.zw;
+    let ndc = vec4f(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world = camera.inverse_view_proj * ndc;
+    let position = world.xyz / world.w;
+
+    let n = normalize(normal.xyz);
+    let v = normalize(camera.position.xyz - position);
+
+    let occlusion = material.b;
+    var color = textureLoad(gbuffer_emissive, pixel, 0).rgb
+        + environment_light(albedo, metallic, roughness, n, v) * occlusion;
+    for (var i = 0u; i < light_buffer.count; i++) {
+        let light = light_buffer.lights[i];
+        let incident = light_incident(light, position);
+        let shadow = light_shadow(light, position, n);
+        color += brdf_direct(albedo, metallic, roughness, n, v, incident.direction) * incident.radiance * shadow;
+    }
+    return vec4f(color, 1.0);
+}
@@ -0,0 +1,46 @@
+// Image-based lighting from the renderer's environment, in the global bind group: a diffuse
+// irradiance cube (divided by PI), a specular cube prefiltered per roughness along its mip
+// levels and the BRDF lookup table of the split-sum approximation. Without an environment,
+// it adds nothing.
+//
+// color += environment_light(base_color, metallic, roughness, n, v) * occlusion;
+
+struct EnvironmentUniform {
+    // 0 without an environment
+    intensity: f32,
+    // mip level of the specular map prefiltered for roughness 1
+    specular_max_lod: f32,
+};
+
+@group(0) @binding(7)
+var<uniform> environment: EnvironmentUniform;
+@group(0) @binding(8)
+var environment_irradiance: texture_cube<f32>;
+@group(0) @binding(9)
+var environment_specular: texture_cube<f32>;
+// scale (r) and bias (g) of F0, by n·v (u) and roughness (v)
+@group(0) @binding(10)
+var environment_brdf_lut: texture_2d<f32>;
+@group(0) @binding(11)
+var environment_sampler: sampler;
+
+// Light reflected towards `v` from the whole environment, diffuse and specular.
+// `n` and `v` are unit vectors, `v` points away from the surface.
+fn environment_light(base_color: vec3f, metallic: f32, roughness: f32, n: vec3f, v: vec3f) -> vec3f {
+    if environment.intensity <= 0.0 {
+        return vec3f(0.0);
+    }
+    let n_dot_v = clamp(dot(n, v), 1e-4, 1.0);
+    let f0 = mix(vec3f(0.04), base_color, metallic);
+    // Fresnel at n·v, damped on rough surfaces whose lobes also catch grazing light
+    let fresnel = f0 + (max(vec3f(1.0 - roughness), f0) - f0) * pow(1.0 - n_dot_v, 5.0);
+
+    let brdf = textureSampleLevel(environment_brdf_lut, environment_sampler, vec2f(n_dot_v, roughness), 0.0).rg;
+    let r = reflect(-v, n);
+    let prefiltered = textureSampleLevel(environment_specular, environment_sampler, r, roughness * environment.specular_max_lod).rgb;
+    let specular = prefiltered * (f0 * brdf.x + brdf.y);
+
+    let irradiance = textureSampleLevel(environment_irradiance, environment_sampler, n, 0.0).rgb;
+    let diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_color * irradiance;
+    return (diffuse + specular) * environment.intensity;
+}
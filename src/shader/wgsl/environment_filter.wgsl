@@ -0,0 +1,154 @@
+// Precomputes the image-based lighting maps of an environment cube map: the diffuse
+// irradiance cube, the specular cube prefiltered per roughness mip and the BRDF lookup table.
+// Each draw renders one face of one mip level with a full-screen triangle.
+
+const PI: f32 = 3.141592653589793;
+const SPECULAR_SAMPLES: u32 = 128u;
+const BRDF_SAMPLES: u32 = 256u;
+// angle between the hemisphere samples of the irradiance convolution, in radians
+const IRRADIANCE_STEP: f32 = 0.05;
+
+struct FilterParams {
+    // +X, -X, +Y, -Y, +Z, -Z
+    face: u32,
+    roughness: f32,
+    // width and height of the face being rendered
+    size: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: FilterParams;
+@group(0) @binding(1)
+var source: texture_cube<f32>;
+@group(0) @binding(2)
+var source_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4f {
+    // one triangle covering the viewport
+    let uv = vec2f(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Direction through the texel at `uv` of `face`, with v pointing down the face.
+fn face_direction(face: u32, uv: vec2f) -> vec3f {
+    let s = uv.x * 2.0 - 1.0;
+    let t = uv.y * 2.0 - 1.0;
+    switch face {
+        case 0u: { return normalize(vec3f(1.0, -t, -s)); }
+        case 1u: { return normalize(vec3f(-1.0, -t, s)); }
+        case 2u: { return normalize(vec3f(s, 1.0, t)); }
+        case 3u: { return normalize(vec3f(s, -1.0, -t)); }
+        case 4u: { return normalize(vec3f(s, -t, 1.0)); }
+        default: { return normalize(vec3f(-s, -t, -1.0)); }
+    }
+}
+
+// Tangent and bitangent completing the unit vector `n` to an orthonormal basis.
+fn tangent_frame(n: vec3f) -> mat3x3f {
+    let up = select(vec3f(1.0, 0.0, 0.0), vec3f(0.0, 0.0, 1.0), abs(n.z) < 0.999);
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+    return mat3x3f(tangent, bitangent, n);
+}
+
+// The Hammersley point `i` of `count`; the radical inverse is computed by hand since
+// WebGL has no bit reversal.
+fn hammersley(i: u32, count: u32) -> vec2f {
+    var bits = i;
+    var inverse = 0.0;
+    var weight = 0.5;
+    for (var bit = 0u; bit < 32u && bits != 0u; bit++) {
+        inverse += f32(bits & 1u) * weight;
+        bits = bits >> 1u;
+        weight *= 0.5;
+    }
+    return vec2f(f32(i) / f32(count), inverse);
+}
+
+// A half vector around `n` distributed like the GGX normal distribution of `alpha`.
+fn importance_sample_ggx(xi: vec2f, n: vec3f, alpha: f32) -> vec3f {
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    let h = vec3f(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    return normalize(tangent_frame(n) * h);
+}
+
+// Irradiance from the hemisphere around the texel's direction, divided by PI: the radiance
+// reflected by a white Lambertian surface facing that way.
+@fragment
+fn fs_irradiance(@builtin(position) frag_position: vec4f) -> @location(0) vec4f {
+    let n = face_direction(params.face, frag_position.xy / params.size);
+    let frame = tangent_frame(n);
+    var irradiance = vec3f(0.0);
+    var count = 0.0;
+    for (var phi = 0.0; phi < 2.0 * PI; phi += IRRADIANCE_STEP) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += IRRADIANCE_STEP) {
+            let local = vec3f(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let radiance = textureSampleLevel(source, source_sampler, frame * local, 0.0).rgb;
+            irradiance += radiance * cos(theta) * sin(theta);
+            count += 1.0;
+        }
+    }
+    return vec4f(PI * irradiance / count, 1.0);
+}
+
+// Radiance reflected around the texel's direction by a GGX lobe of `params.roughness`,
+// assuming the view direction equals the normal.
+@fragment
+fn fs_prefilter(@builtin(position) frag_position: vec4f) -> @location(0) vec4f {
+    let n = face_direction(params.face, frag_position.xy / params.size);
+    if params.roughness == 0.0 {
+        return vec4f(textureSampleLevel(source, source_sampler, n, 0.0).rgb, 1.0);
+    }
+    let alpha = params.roughness * params.roughness;
+    var color = vec3f(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < SPECULAR_SAMPLES; i++) {
+        let h = importance_sample_ggx(hammersley(i, SPECULAR_SAMPLES), n, alpha);
+        let l = normalize(2.0 * dot(n, h) * h - n);
+        let n_dot_l = dot(n, l);
+        if n_dot_l > 0.0 {
+            color += textureSampleLevel(source, source_sampler, l, 0.0).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    return vec4f(color / max(weight, 1e-4), 1.0);
+}
+
+fn visibility_smith_ggx(n_dot_l: f32, n_dot_v: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha_squared) + alpha_squared);
+    let ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha_squared) + alpha_squared);
+    return 0.5 / (ggx_v + ggx_l);
+}
+
+// Scale (r) and bias (g) of F0 in the specular term, by n·v (u) and roughness (v).
+@fragment
+fn fs_brdf_lut(@builtin(position) frag_position: vec4f) -> @location(0) vec4f {
+    let uv = frag_position.xy / params.size;
+    let n_dot_v = max(uv.x, 1e-3);
+    let roughness = uv.y;
+    let alpha = max(roughness * roughness, 2e-3);
+    let v = vec3f(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let n = vec3f(0.0, 0.0, 1.0);
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < BRDF_SAMPLES; i++) {
+        let h = importance_sample_ggx(hammersley(i, BRDF_SAMPLES), n, alpha);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+        let n_dot_l = l.z;
+        if n_dot_l > 0.0 {
+            let n_dot_h = max(h.z, 0.0);
+            let v_dot_h = max(dot(v, h), 0.0);
+            // sample weight of the split-sum integral: V * 4 * (v·h) * (n·l) / (n·h)
+            let g = visibility_smith_ggx(n_dot_l, n_dot_v, alpha) * 4.0 * v_dot_h * n_dot_l / max(n_dot_h, 1e-4);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * g;
+            bias += fresnel * g;
+        }
+    }
+    return vec4f(scale / f32(BRDF_SAMPLES), bias / f32(BRDF_SAMPLES), 0.0, 1.0);
+}
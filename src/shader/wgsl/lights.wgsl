@@ -0,0 +1,66 @@
+// Lights passed to `Renderer::render`, in the global bind group.
+//
+// for (var i = 0u; i < light_buffer.count; i++) {
+//     let incident = light_incident(light_buffer.lights[i], world_position);
+//     color += brdf(n, incident.direction, v) * incident.radiance * max(dot(n, incident.direction), 0.0);
+// }
+
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    position: vec3f,
+    kind: u32,
+    // direction the light travels
+    direction: vec3f,
+    // 0 for no limit
+    range: f32,
+    // color * intensity
+    radiance: vec3f,
+    inner_cone_cos: f32,
+    outer_cone_cos: f32,
+    // shadow map slot (see light_shadow), -1 when the light casts no shadows
+    shadow: i32,
+};
+
+struct LightBuffer {
+    count: u32,
+    lights: array<Light>,
+};
+
+@group(0) @binding(1)
+var<storage, read> light_buffer: LightBuffer;
+
+struct LightIncident {
+    // unit vector from the surface towards the light
+    direction: vec3f,
+    // radiance arriving at the surface
+    radiance: vec3f,
+};
+
+fn light_incident(light: Light, world_position: vec3f) -> LightIncident {
+    var incident: LightIncident;
+    if light.kind == LIGHT_DIRECTIONAL {
+        incident.direction = -light.direction;
+        incident.radiance = light.radiance;
+        return incident;
+    }
+
+    let to_light = light.position - world_position;
+    let distance_squared = max(dot(to_light, to_light), 1e-4);
+    incident.direction = to_light * inverseSqrt(distance_squared);
+
+    var attenuation = 1.0 / distance_squared;
+    if light.range > 0.0 {
+        let ratio = distance_squared / (light.range * light.range);
+        let window = clamp(1.0 - ratio * ratio, 0.0, 1.0);
+        attenuation *= window * window;
+    }
+    if light.kind == LIGHT_SPOT {
+        let cos_angle = dot(light.direction, -incident.direction);
+        attenuation *= smoothstep(light.outer_cone_cos, light.inner_cone_cos, cos_angle);
+    }
+    incident.radiance = light.radiance * attenuation;
+    return incident;
+}
@@ -2,16 +2,24 @@ struct VertexInput {
     @location(0) position: vec3f,
     @location(1) tex_coord: vec2f,
     @location(2) color: vec3f,
+    @location(3) normal: vec3f,
+    // xyz along increasing u, w is the handedness of the bitangent
+    @location(4) tangent: vec4f,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
     @location(0) tex_coord: vec2f,
     @location(1) color: vec3f,
+    @location(2) world_position: vec3f,
+    @location(3) normal: vec3f,
+    @location(4) tangent: vec4f,
 };
 
 struct CameraUniform {
     view_proj: mat4x4f,
+    inverse_view_proj: mat4x4f,
+    position: vec4f,
 };
 
 @group(0) @binding(0)
@@ -24,6 +32,9 @@ struct MaterialUniforms {
     albedo_factor: vec4<f32>,
     metallic: f32,
     roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    emissive_factor: vec3<f32>,
 }
 
 @group(2) @binding(0)
@@ -32,6 +43,33 @@ var<uniform> material: MaterialUniforms;
 var albedo_texture: texture_2d<f32>;
 @group(2) @binding(2)
 var albedo_texture_sampler: sampler;
+@group(2) @binding(3)
+var normal_texture: texture_2d<f32>;
+@group(2) @binding(4)
+var normal_texture_sampler: sampler;
+// roughness in g, metallic in b
+@group(2) @binding(5)
+var metallic_roughness_texture: texture_2d<f32>;
+@group(2) @binding(6)
+var metallic_roughness_texture_sampler: sampler;
+@group(2) @binding(7)
+var occlusion_texture: texture_2d<f32>;
+@group(2) @binding(8)
+var occlusion_texture_sampler: sampler;
+@group(2) @binding(9)
+var emissive_texture: texture_2d<f32>;
+@group(2) @binding(10)
+var emissive_texture_sampler: sampler;
+
+struct GBufferOutput {
+    @location(0) albedo: vec4f,
+    @location(1) normal: vec4f,
+    @location(2) material: vec4f,
+    @location(3) emissive: vec4f,
+};
+
+// set by the pipeline from `RenderState::alpha_cutoff`
+override alpha_cutoff: f32 = 0.0;
 
 @vertex
 fn vs_main(
@@ -42,12 +80,89 @@ fn vs_main(
     out.tex_coord = model.tex_coord;
     out.color = model.color;
     let model_matrix = model_matrices[instance_idx];
-    out.clip_position = camera.view_proj * model_matrix * vec4f(model.position, 1.0);
+    let world_position = model_matrix * vec4f(model.position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position;
+
+    // normals go through the cofactor matrix, the inverse transpose scaled by the determinant,
+    // so that non-uniform scales keep them perpendicular; mirroring flips the bitangent
+    let m = mat3x3f(model_matrix[0].xyz, model_matrix[1].xyz, model_matrix[2].xyz);
+    let cofactor = mat3x3f(cross(m[1], m[2]), cross(m[2], m[0]), cross(m[0], m[1]));
+    let determinant_sign = sign(dot(m[0], cofactor[0]));
+    out.normal = cofactor * model.normal * determinant_sign;
+    out.tangent = vec4f(m * model.tangent.xyz, model.tangent.w * determinant_sign);
+    return out;
+}
+
+struct Surface {
+    // base color and alpha
+    albedo: vec4f,
+    normal: vec3f,
+    metallic: f32,
+    roughness: f32,
+    // only attenuates indirect light
+    occlusion: f32,
+    emissive: vec3f,
+};
+
+// Material inputs of a fragment; textures without an assigned texture sample white.
+fn pbr_surface(in: VertexOutput, front_facing: bool) -> Surface {
+    var out: Surface;
+    let albedo = textureSample(albedo_texture, albedo_texture_sampler, in.tex_coord);
+    out.albedo = albedo * material.albedo_factor * vec4f(in.color, 1.0);
+
+    // a normal scale of 0 ignores the normal texture
+    let sampled_normal = textureSample(normal_texture, normal_texture_sampler, in.tex_coord).xyz * 2.0 - 1.0;
+    let tangent_normal = normalize(vec3f(sampled_normal.xy * material.normal_scale, sampled_normal.z));
+    var normal = normalize(in.normal);
+    if !front_facing {
+        normal = -normal;
+    }
+    let tangent = normalize(in.tangent.xyz);
+    let bitangent = cross(normal, tangent) * in.tangent.w;
+    out.normal = normalize(mat3x3f(tangent, bitangent, normal) * tangent_normal);
+
+    let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_texture_sampler, in.tex_coord);
+    out.metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    out.roughness = clamp(material.roughness * metallic_roughness.g, 0.0, 1.0);
+
+    let occlusion = textureSample(occlusion_texture, occlusion_texture_sampler, in.tex_coord).r;
+    out.occlusion = mix(1.0, occlusion, material.occlusion_strength);
+
+    let emissive = textureSample(emissive_texture, emissive_texture_sampler, in.tex_coord).rgb;
+    out.emissive = emissive * material.emissive_factor;
     return out;
 }
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4f {
-    var color = textureSample(albedo_texture, albedo_texture_sampler, in.tex_coord) * material.albedo_factor;
-    return vec4f(color.rgb * in.color.rgb, color.a);
-}
\ No newline at end of file
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4f {
+    let surface = pbr_surface(in, front_facing);
+    if surface.albedo.a < alpha_cutoff {
+        discard;
+    }
+    let v = normalize(camera.position.xyz - in.world_position);
+    var color = surface.emissive
+        + environment_light(surface.albedo.rgb, surface.metallic, surface.roughness, surface.normal, v) * surface.occlusion;
+    for (var i = 0u; i < light_buffer.count; i++) {
+        let light = light_buffer.lights[i];
+        let incident = light_incident(light, in.world_position);
+        let shadow = light_shadow(light, in.world_position, surface.normal);
+        color += brdf_direct(surface.albedo.rgb, surface.metallic, surface.roughness, surface.normal, v, incident.direction)
+            * incident.radiance * shadow;
+    }
+    return vec4f(color, surface.albedo.a);
+}
+
+@fragment
+fn fs_gbuffer(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> GBufferOutput {
+    let surface = pbr_surface(in, front_facing);
+    if surface.albedo.a < alpha_cutoff {
+        discard;
+    }
+    var out: GBufferOutput;
+    out.albedo = vec4f(surface.albedo.rgb, 1.0);
+    out.normal = vec4f(surface.normal, 1.0);
+    out.material = vec4f(surface.roughness, surface.metallic, surface.occlusion, 1.0);
+    out.emissive = vec4f(surface.emissive, 1.0);
+    return out;
+}
@@ -0,0 +1,171 @@
+// Shadow maps of the lights that cast shadows, in the global bind group: cascades for the
+// directional light, a cube per point light and one atlas tile per spot light.
+// Needs the lights chunk.
+//
+// let incident = light_incident(light, world_position);
+// let shadow = light_shadow(light, world_position, normal);
+// color += brdf_direct(base_color, metallic, roughness, n, v, incident.direction) * incident.radiance * shadow;
+
+const MAX_CASCADES: u32 = 4u;
+const MAX_POINT_SHADOWS: u32 = 8u;
+const MAX_SPOT_SHADOWS: u32 = 16u;
+
+struct ShadowUniform {
+    // world to light clip space, nearest cascade first
+    cascades: array<mat4x4f, MAX_CASCADES>,
+    // world to light clip space, by atlas tile
+    spot_matrices: array<mat4x4f, MAX_SPOT_SHADOWS>,
+    // near, far, tan(fov / 2) of every cube
+    point_params: array<vec4f, MAX_POINT_SHADOWS>,
+    // near, far, tan(fov / 2) of every atlas tile
+    spot_params: array<vec4f, MAX_SPOT_SHADOWS>,
+    // world-space size of a cascade texel
+    texel_sizes: vec4f,
+    cascade_count: u32,
+    depth_bias: f32,
+    // in texels
+    normal_bias: f32,
+    // texels sampled on each side of the center texel
+    filter_radius: u32,
+    // width and height of cube faces and atlas tiles
+    local_resolution: f32,
+    // tiles per row and column of the atlas
+    atlas_columns: u32,
+};
+
+@group(0) @binding(2)
+var<uniform> shadows: ShadowUniform;
+@group(0) @binding(3)
+var shadow_map: texture_depth_2d_array;
+@group(0) @binding(4)
+var shadow_sampler: sampler_comparison;
+@group(0) @binding(5)
+var point_shadow_map: texture_depth_cube_array;
+@group(0) @binding(6)
+var spot_shadow_atlas: texture_depth_2d;
+
+fn pcf_weight() -> f32 {
+    let width = f32(shadows.filter_radius * 2u + 1u);
+    return 1.0 / (width * width);
+}
+
+// Fraction of the cascade texels around `uv` whose depth is not nearer than `depth`.
+fn shadow_pcf(uv: vec2f, cascade: u32, depth: f32) -> f32 {
+    let texel = 1.0 / vec2f(textureDimensions(shadow_map));
+    let radius = i32(shadows.filter_radius);
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let offset = vec2f(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, cascade, depth);
+        }
+    }
+    return lit * pcf_weight();
+}
+
+// Like `shadow_pcf`, on the plane through `direction` perpendicular to it; texels are
+// `texel` units wide at unit distance along `direction`. Samples crossing into a
+// neighbouring face are filtered across the seam.
+fn point_pcf(direction: vec3f, cube: u32, texel: f32, depth: f32) -> f32 {
+    let side = select(vec3f(0.0, 1.0, 0.0), vec3f(1.0, 0.0, 0.0), abs(direction.y) > 0.9);
+    let right = normalize(cross(direction, side)) * texel;
+    let up = normalize(cross(right, direction)) * texel;
+    let radius = i32(shadows.filter_radius);
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let offset = right * f32(x) + up * f32(y);
+            lit += textureSampleCompareLevel(point_shadow_map, shadow_sampler, direction + offset, cube, depth);
+        }
+    }
+    return lit * pcf_weight();
+}
+
+// Samples stay inside the tile between `tile_min` and `tile_max`, so neighbouring tiles
+// do not bleed in.
+fn spot_pcf(uv: vec2f, tile_min: vec2f, tile_max: vec2f, depth: f32) -> f32 {
+    let texel = 1.0 / vec2f(textureDimensions(spot_shadow_atlas));
+    let radius = i32(shadows.filter_radius);
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let offset = vec2f(f32(x), f32(y)) * texel;
+            let sample_uv = clamp(uv + offset, tile_min + texel * 0.5, tile_max - texel * 0.5);
+            lit += textureSampleCompareLevel(spot_shadow_atlas, shadow_sampler, sample_uv, depth);
+        }
+    }
+    return lit * pcf_weight();
+}
+
+// Perspective depth of a point `z` units in front of a camera, matching `PerspectiveProjection`.
+fn perspective_depth(z: f32, near: f32, far: f32) -> f32 {
+    return far * (z - near) / ((far - near) * z);
+}
+
+fn cascade_shadow(world_position: vec3f, normal: vec3f) -> f32 {
+    for (var i = 0u; i < shadows.cascade_count; i++) {
+        // offsetting along the normal keeps surfaces from shadowing themselves at grazing angles
+        let position = world_position + normal * shadows.normal_bias * shadows.texel_sizes[i];
+        let clip = shadows.cascades[i] * vec4f(position, 1.0);
+        let uv = vec2f(clip.x * 0.5 + 0.5, 0.5 - clip.y * 0.5);
+        if all(uv >= vec2f(0.0)) && all(uv <= vec2f(1.0)) && clip.z <= 1.0 {
+            return shadow_pcf(uv, i, clip.z - shadows.depth_bias);
+        }
+    }
+    return 1.0;
+}
+
+fn point_shadow(light: Light, world_position: vec3f, normal: vec3f) -> f32 {
+    let cube = u32(light.shadow);
+    let params = shadows.point_params[cube];
+    let distance = length(world_position - light.position);
+    // a texel of a 90 degree face is 2 * z / resolution wide
+    let texel_size = 2.0 * distance / shadows.local_resolution;
+    let to_surface = world_position + normal * shadows.normal_bias * texel_size - light.position;
+
+    // depth along the axis of the face the direction points through
+    let a = abs(to_surface);
+    let z = max(a.x, max(a.y, a.z));
+    if z <= params.x || z >= params.y {
+        return 1.0;
+    }
+    let depth = perspective_depth(z, params.x, params.y);
+    // faces are stored upside down, see `CUBE_FACES` in `render/shadows.rs`
+    let direction = to_surface * vec3f(1.0, -1.0, 1.0) / z;
+    return point_pcf(direction, cube, 2.0 / shadows.local_resolution, depth - shadows.depth_bias);
+}
+
+fn spot_shadow(light: Light, world_position: vec3f, normal: vec3f) -> f32 {
+    let tile = u32(light.shadow);
+    let params = shadows.spot_params[tile];
+    let distance = length(world_position - light.position);
+    let texel_size = 2.0 * distance * params.z / shadows.local_resolution;
+    let position = world_position + normal * shadows.normal_bias * texel_size;
+
+    let clip = shadows.spot_matrices[tile] * vec4f(position, 1.0);
+    if clip.w <= 0.0 {
+        return 1.0;
+    }
+    let ndc = clip.xyz / clip.w;
+    let local_uv = vec2f(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    if any(local_uv < vec2f(0.0)) || any(local_uv > vec2f(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+    let columns = f32(shadows.atlas_columns);
+    let cell = vec2f(f32(tile % shadows.atlas_columns), f32(tile / shadows.atlas_columns));
+    let uv = (cell + local_uv) / columns;
+    return spot_pcf(uv, cell / columns, (cell + 1.0) / columns, ndc.z - shadows.depth_bias);
+}
+
+// How much of `light` reaches `world_position` past the shadow casters: 0 in shadow, 1 lit.
+// `normal` is the unit surface normal; positions outside the light's shadow map are lit.
+fn light_shadow(light: Light, world_position: vec3f, normal: vec3f) -> f32 {
+    if light.shadow < 0 {
+        return 1.0;
+    }
+    switch light.kind {
+        case LIGHT_DIRECTIONAL: { return cascade_shadow(world_position, normal); }
+        case LIGHT_POINT: { return point_shadow(light, world_position, normal); }
+        default: { return spot_shadow(light, world_position, normal); }
+    }
+}
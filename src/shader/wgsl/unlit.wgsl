@@ -31,6 +31,16 @@ var albedo_texture: texture_2d<f32>;
 @group(2) @binding(2)
 var albedo_texture_sampler: sampler;
 
+struct GBufferOutput {
+    @location(0) albedo: vec4f,
+    @location(1) normal: vec4f,
+    @location(2) material: vec4f,
+    @location(3) emissive: vec4f,
+};
+
+// set by the pipeline from `RenderState::alpha_cutoff`
+override alpha_cutoff: f32 = 0.0;
+
 @vertex
 fn vs_main(
     model: VertexInput,
@@ -47,5 +57,23 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     var color = textureSample(albedo_texture, albedo_texture_sampler, in.tex_coord) * material.albedo_factor;
+    if color.a < alpha_cutoff {
+        discard;
+    }
     return vec4f(color.rgb * in.color.rgb, color.a);
-}
\ No newline at end of file
+}
+
+@fragment
+fn fs_gbuffer(in: VertexOutput) -> GBufferOutput {
+    var color = textureSample(albedo_texture, albedo_texture_sampler, in.tex_coord) * material.albedo_factor;
+    if color.a < alpha_cutoff {
+        discard;
+    }
+    var out: GBufferOutput;
+    out.albedo = vec4f(color.rgb * in.color.rgb, 1.0);
+    out.normal = vec4f(0.0, 0.0, 0.0, 1.0);
+    // unlit: the lighting pass outputs the albedo as is
+    out.material = vec4f(1.0, 0.0, 0.0, 0.0);
+    out.emissive = vec4f(0.0);
+    return out;
+}
@@ -11,6 +11,8 @@ pub struct RenderTarget {
     name: String,
     width: u32,
     height: u32,
+    sample_count: u32,
+    reverse_z: bool,
     color_attachments: Vec<RenderTargetColorAttachment>,
     depth_stencil_attachment: Option<RenderTargetDepthStencilAttachment>,
 }
@@ -22,6 +24,8 @@ impl RenderTarget {
             name: name.into(),
             width,
             height,
+            sample_count: 1,
+            reverse_z: false,
             color_attachments: Vec::new(),
             depth_stencil_attachment: None,
         }
@@ -69,6 +73,40 @@ impl RenderTarget {
         (self.width, self.height)
     }
 
+    /// Sets the MSAA sample count.
+    ///
+    /// Above 1, the renderer draws into multisampled textures of its own and
+    /// resolves them into the color attachments; the depth attachment is replaced
+    /// by a multisampled one and is not written. Counts the attachment formats do not
+    /// support fall back to the largest supported count.
+    #[inline]
+    pub fn set_sample_count(&mut self, sample_count: u32) -> &mut Self {
+        self.sample_count = sample_count.max(1);
+        self
+    }
+
+    #[inline]
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Enables reverse-Z: depth is stored as 1.0 at the near plane and 0.0 at the far plane.
+    ///
+    /// Materials keep their conventional depth compare (`Less` is drawn as `Greater`),
+    /// and depth clear values are given as conventional depth (`Clear(1.0)` clears to 0.0).
+    /// The camera needs a reverse-Z projection, e.g. `PerspectiveProjection::with_reverse_z`,
+    /// ideally infinite. With a `Depth32Float` attachment this greatly reduces z-fighting at distance.
+    #[inline]
+    pub fn set_reverse_z(&mut self, reverse_z: bool) -> &mut Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    #[inline]
+    pub fn reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+
     #[inline]
     pub fn set_color_attachments(
         &mut self,
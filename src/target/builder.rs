@@ -0,0 +1,173 @@
+use super::{
+    Operations, RenderTarget, RenderTargetColorAttachment, RenderTargetDepthStencilAttachment,
+};
+use crate::material::BlendMode;
+use crate::texture::{Texture, TextureKind};
+use crate::{Resources, SurfaceKey};
+
+pub struct RenderTargetBuilder {
+    name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    sample_count: u32,
+    reverse_z: bool,
+    color_textures: Vec<(Texture, Option<BlendMode>, wgpu::ColorWrites)>,
+    depth_stencil_texture: Option<Texture>,
+}
+
+impl Default for RenderTargetBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderTargetBuilder {
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            width: None,
+            height: None,
+            sample_count: 1,
+            reverse_z: false,
+            color_textures: Vec::new(),
+            depth_stencil_texture: None,
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Sets the MSAA sample count, see [`RenderTarget::set_sample_count`].
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Enables reverse-Z, see [`RenderTarget::set_reverse_z`].
+    pub fn reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    pub fn attach_surface(mut self, surface_key: SurfaceKey) -> Self {
+        let width = self.width.expect("Width must be set");
+        let height = self.height.expect("Height must be set");
+        let tex = Texture::surface_texture(surface_key, width, height);
+        self.color_textures
+            .push((tex, None, wgpu::ColorWrites::ALL));
+        self
+    }
+
+    pub fn attach_color(mut self, kind: TextureKind, format: wgpu::TextureFormat) -> Self {
+        let tex = Texture::new(
+            kind,
+            format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        );
+        self.color_textures
+            .push((tex, None, wgpu::ColorWrites::ALL));
+        self
+    }
+
+    /// Sets the blending of the last attached color texture, overriding the material's.
+    pub fn color_blend(mut self, blend: BlendMode) -> Self {
+        let (_, color_blend, _) = self
+            .color_textures
+            .last_mut()
+            .expect("No color texture attached");
+        *color_blend = Some(blend);
+        self
+    }
+
+    /// Sets the channels written to the last attached color texture.
+    pub fn color_write_mask(mut self, write_mask: wgpu::ColorWrites) -> Self {
+        let (_, _, color_write_mask) = self
+            .color_textures
+            .last_mut()
+            .expect("No color texture attached");
+        *color_write_mask = write_mask;
+        self
+    }
+
+    /// Attaches a depth (or depth-stencil) texture of `format`,
+    /// e.g. `Depth16Unorm`, `Depth24Plus`, `Depth32Float` or `Depth24PlusStencil8`.
+    pub fn attach_depth(mut self, format: wgpu::TextureFormat) -> Self {
+        assert!(
+            format.is_depth_stencil_format(),
+            "{format:?} is not a depth format"
+        );
+        let width = self.width.expect("Width must be set");
+        let height = self.height.expect("Height must be set");
+        let tex = Texture::new(
+            TextureKind::Render { width, height },
+            format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        self.depth_stencil_texture = Some(tex);
+        self
+    }
+
+    pub fn attach_depth24(self) -> Self {
+        self.attach_depth(wgpu::TextureFormat::Depth24Plus)
+    }
+
+    /// Attaches a `Depth24PlusStencil8` texture, for stencil masking and outlines.
+    pub fn attach_depth24_stencil8(self) -> Self {
+        self.attach_depth(wgpu::TextureFormat::Depth24PlusStencil8)
+    }
+
+    pub fn attach_depth32(self) -> Self {
+        self.attach_depth(wgpu::TextureFormat::Depth32Float)
+    }
+
+    pub fn build(self, resources: &mut Resources) -> RenderTarget {
+        let width = self.width.expect("Width must be set");
+        let height = self.height.expect("Height must be set");
+
+        let color_attachments = self
+            .color_textures
+            .into_iter()
+            .map(|(tex, blend, write_mask)| {
+                let handle = resources.insert_texture(tex);
+                RenderTargetColorAttachment {
+                    blend,
+                    write_mask,
+                    ..RenderTargetColorAttachment::new(handle)
+                }
+            })
+            .collect();
+
+        let depth_stencil_attachment = self.depth_stencil_texture.map(|tex| {
+            let handle = resources.insert_texture(tex);
+            RenderTargetDepthStencilAttachment {
+                texture: handle,
+                depth_ops: Operations::default(),
+                stencil_ops: Operations::default(),
+            }
+        });
+
+        let mut render_target = RenderTarget::new(
+            self.name.unwrap_or_else(|| "Unnamed".to_string()),
+            width,
+            height,
+        );
+
+        render_target
+            .set_sample_count(self.sample_count)
+            .set_reverse_z(self.reverse_z)
+            .set_color_attachments(color_attachments)
+            .set_depth_stencil_attachment(depth_stencil_attachment);
+
+        render_target
+    }
+}
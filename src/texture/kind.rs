@@ -3,8 +3,9 @@ use crate::texture::TextureData;
 use std::hash::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum TextureKind {
+    #[default]
     Empty,
     D1 {
         data: TextureData,
@@ -15,12 +16,20 @@ pub enum TextureKind {
         width: u32,
         height: u32,
     },
+    /// `layers` images of `width` × `height` texels, one after another.
+    D2Array {
+        data: TextureData,
+        width: u32,
+        height: u32,
+        layers: u32,
+    },
     D3 {
         data: TextureData,
         width: u32,
         height: u32,
         depth: u32,
     },
+    /// Six square faces in the order +X, -X, +Y, -Y, +Z, -Z, each `size` × `size` texels.
     Cube {
         data: TextureData,
         size: u32,
@@ -36,35 +45,52 @@ pub enum TextureKind {
     },
 }
 
-impl Default for TextureKind {
-    fn default() -> Self {
-        TextureKind::Empty
-    }
-}
-
 impl TextureKind {
+    /// Width, height and layers (or depth) of the texture's data and of the views shaders sample.
     pub fn dimensions(&self) -> (u32, u32, u32) {
         use TextureKind::*;
         match self {
             D1 { width, .. } => (*width, 1, 1),
             D2 { width, height, .. } => (*width, *height, 1),
+            D2Array {
+                width,
+                height,
+                layers,
+                ..
+            } => (*width, *height, *layers),
             D3 {
                 width,
                 height,
                 depth,
                 ..
             } => (*width, *height, *depth),
-            Cube { size, .. } => (*size, *size, 1),
+            Cube { size, .. } => (*size, *size, 6),
             Surface { width, height, .. } => (*width, *height, 1),
             Render { width, height } => (*width, *height, 1),
             Empty => (0, 0, 0),
         }
     }
 
+    pub fn data(&self) -> Option<&TextureData> {
+        use TextureKind::*;
+        match self {
+            D1 { data, .. }
+            | D2 { data, .. }
+            | D2Array { data, .. }
+            | D3 { data, .. }
+            | Cube { data, .. } => Some(data),
+            _ => None,
+        }
+    }
+
     pub fn data_mut(&mut self) -> Option<&mut TextureData> {
         use TextureKind::*;
         match self {
-            D1 { data, .. } | D2 { data, .. } | D3 { data, .. } | Cube { data, .. } => Some(data),
+            D1 { data, .. }
+            | D2 { data, .. }
+            | D2Array { data, .. }
+            | D3 { data, .. }
+            | Cube { data, .. } => Some(data),
             _ => None,
         }
     }
@@ -94,6 +120,18 @@ impl TextureKind {
                 width.hash(&mut hasher);
                 height.hash(&mut hasher);
             }
+            D2Array {
+                data,
+                width,
+                height,
+                layers,
+            } => {
+                7u8.hash(&mut hasher);
+                data.bytes_len().hash(&mut hasher);
+                width.hash(&mut hasher);
+                height.hash(&mut hasher);
+                layers.hash(&mut hasher);
+            }
             D3 {
                 data,
                 width,
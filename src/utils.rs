@@ -20,7 +20,7 @@ macro_rules! symbol {
     ($s:expr) => {{ $crate::Symbol($crate::fnv1a64($s.as_bytes())) }};
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Ord, PartialOrd, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Ord, PartialOrd, Hash, Default)]
 pub struct DirtyVersion(u64);
 
 impl DirtyVersion {